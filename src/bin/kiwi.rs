@@ -0,0 +1,274 @@
+// headless kicad schematic processing, see `kiwi help`
+use kiwi::erc::Severity;
+use kiwi::hierarchy::Hierarchy;
+use kiwi::raster::Region;
+use kiwi::schematic::*;
+use kiwi::search::natural_cmp;
+use kiwi::svg::SvgOptions;
+use kiwi::theme::Theme;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+const USAGE: &str = "usage: kiwi <command> <file.kicad_sch> [options]
+
+commands:
+  render   draw the schematic, format from the output extension
+             -o, --output <file.svg|png|pdf>  (required)
+             --theme <name>       built-in theme, default \"KiCad Default\"
+             --dpi <n>            png resolution, default 300
+             --region <ref|net>   png crop, \"ref:U3\" or \"net:VBUS\"
+  netlist  list nets with their pins
+  bom      bill of materials as csv, grouped by value and footprint
+  erc      electrical rules check, exits with 1 on errors
+  info     version and counts of symbols, nets and sheets
+  dump     parsed model as json, see src/json.rs for its shape";
+
+// command line after the command and file
+struct Options {
+    output: Option<String>,
+    theme: Theme,
+    dpi: f64,
+    region: Region,
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        output: None,
+        theme: Theme::new(),
+        dpi: 300.0,
+        region: Region::Page,
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("{} needs a value", arg))
+        };
+        match arg.as_str() {
+            "-o" | "--output" => options.output = Some(value()?),
+            "--theme" => {
+                let name = value()?;
+                options.theme = Theme::builtin(&name).ok_or_else(|| {
+                    format!(
+                        "unknown theme {:?}, one of {:?}",
+                        name,
+                        Theme::builtin_names()
+                    )
+                })?;
+            }
+            "--dpi" => {
+                options.dpi = value()?
+                    .parse::<f64>()
+                    .map_err(|_| "--dpi needs a number".to_string())?;
+            }
+            "--region" => {
+                let region = value()?;
+                options.region = match region.split_once(':') {
+                    Some(("ref", reference)) => Region::Symbol(reference.to_string()),
+                    Some(("net", net)) => Region::Net(net.to_string()),
+                    _ => {
+                        return Err(format!(
+                            "bad region {:?}, use ref:<ref> or net:<name>",
+                            region
+                        ))
+                    }
+                };
+            }
+            _ => return Err(format!("unknown option {:?}", arg)),
+        }
+    }
+    Ok(options)
+}
+
+fn load(file: &str) -> Result<Schematic, String> {
+    let text = fs::read_to_string(file).map_err(|error| format!("{}: {}", file, error))?;
+    let schem = Schematic::parse(&text).map_err(|error| format!("{}: {}", file, error))?;
+    for warning in &schem.warnings {
+        eprintln!("kiwi: {}: {}", file, warning);
+    }
+    Ok(schem)
+}
+
+// the root sheet and every sheet below it that exists on disk and parses
+fn load_hierarchy(file: &str) -> Result<Hierarchy, String> {
+    let root = load(file)?;
+    Ok(Hierarchy::load(root, file, &mut |path| {
+        fs::read_to_string(path).ok()
+    }))
+}
+
+fn csv(field: &str) -> String {
+    if field.contains(',') || field.contains('"') || field.contains('\n') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn render(file: &str, options: &Options) -> Result<(), String> {
+    let output = options.output.as_ref().ok_or("render needs -o <output>")?;
+    let extension = Path::new(output)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let bytes = match extension.as_str() {
+        "svg" => load(file)?
+            .to_svg(&options.theme, &SvgOptions::new())
+            .into_bytes(),
+        "png" => load(file)?
+            .render_png_region(options.dpi, &options.theme, &options.region)
+            .map_err(|error| error.to_string())?,
+        "pdf" => load_hierarchy(file)?.to_pdf(&options.theme),
+        _ => {
+            return Err(format!(
+                "can't render to {:?}, use .svg, .png or .pdf",
+                output
+            ))
+        }
+    };
+    fs::write(output, bytes).map_err(|error| format!("{}: {}", output, error))
+}
+
+fn netlist(schem: &Schematic) {
+    for net in schem.netlist().nets {
+        let mut pins = net
+            .pins
+            .iter()
+            .map(|pin| (pin.reference.as_str(), pin.pin.numb.0.as_str()))
+            .collect::<Vec<_>>();
+        pins.sort_by(|a, b| natural_cmp(a.0, b.0).then_with(|| natural_cmp(a.1, b.1)));
+        let pins = pins
+            .iter()
+            .map(|(reference, pin)| format!("{}-{}", reference, pin))
+            .collect::<Vec<_>>();
+        println!("{}: {}", net.name, pins.join(" "));
+    }
+}
+
+fn bom(schem: &Schematic) {
+    // (value, footprint, lib id) -> references
+    let mut groups = BTreeMap::<(String, String, String), Vec<String>>::new();
+    for symb in &schem.symbs {
+        let reference = symb.prop("Reference").unwrap_or("");
+        // power symbols and flags are "#" references
+        if !symb.in_bom || reference.starts_with('#') {
+            continue;
+        }
+        let key = (
+            symb.prop("Value").unwrap_or("").to_string(),
+            symb.prop("Footprint").unwrap_or("").to_string(),
+            symb.id.clone(),
+        );
+        let references = groups.entry(key).or_default();
+        // one line per multi unit part
+        if !references.iter().any(|other| other == reference) {
+            references.push(reference.to_string());
+        }
+    }
+    let mut lines = groups.into_iter().collect::<Vec<_>>();
+    for (_, references) in lines.iter_mut() {
+        references.sort_by(|a, b| natural_cmp(a, b));
+    }
+    lines.sort_by(|a, b| natural_cmp(&a.1[0], &b.1[0]));
+    println!("Qty,References,Value,Footprint,Symbol");
+    for ((value, footprint, lib_id), references) in lines {
+        println!(
+            "{},{},{},{},{}",
+            references.len(),
+            csv(&references.join(" ")),
+            csv(&value),
+            csv(&footprint),
+            csv(&lib_id)
+        );
+    }
+}
+
+// true if there were errors
+fn erc(schem: &Schematic) -> bool {
+    let violations = schem.erc();
+    for violation in &violations {
+        let severity = match violation.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        println!(
+            "{} @ ({}, {}): {}",
+            severity, violation.pos.x, violation.pos.y, violation.message
+        );
+    }
+    let errors = violations
+        .iter()
+        .filter(|violation| violation.severity == Severity::Error)
+        .count();
+    println!("{} errors, {} warnings", errors, violations.len() - errors);
+    errors > 0
+}
+
+fn info(file: &str) -> Result<(), String> {
+    let hierarchy = load_hierarchy(file)?;
+    let root = &hierarchy.sheets[0].schematic;
+    // totals over every sheet, nets that cross sheets count once per sheet
+    let (mut symbols, mut power, mut nets, mut wires, mut labels) = (0, 0, 0, 0, 0);
+    for node in &hierarchy.sheets {
+        let schem = &node.schematic;
+        for symb in &schem.symbs {
+            match &symb.parent {
+                Some(parent) if parent.power => power += 1,
+                _ => symbols += 1,
+            }
+        }
+        nets += schem.netlist().nets.len();
+        wires += schem.wires.len();
+        labels += schem.labels.len();
+    }
+    println!("version: {}", root.version);
+    println!("uuid: {}", root.uuid);
+    println!("symbols: {}", symbols);
+    println!("power symbols: {}", power);
+    println!("nets: {}", nets);
+    println!("wires: {}", wires);
+    println!("labels: {}", labels);
+    println!("sheets: {}", hierarchy.sheets.len());
+    for node in hierarchy.sheets.iter().skip(1) {
+        println!("  {} ({})", node.path, node.file);
+    }
+    Ok(())
+}
+
+fn run(args: &[String]) -> Result<bool, String> {
+    let (command, file) = match args {
+        [command, file, ..] => (command.as_str(), file.as_str()),
+        [command] if command == "help" || command == "--help" || command == "-h" => {
+            println!("{}", USAGE);
+            return Ok(true);
+        }
+        _ => return Err(USAGE.to_string()),
+    };
+    let options = parse_options(&args[2..])?;
+    match command {
+        "render" => render(file, &options)?,
+        "netlist" => netlist(&load(file)?),
+        "bom" => bom(&load(file)?),
+        "erc" => return Ok(!erc(&load(file)?)),
+        "info" => info(file)?,
+        "dump" => println!("{}", load(file)?.to_json_pretty()),
+        _ => return Err(format!("unknown command {:?}\n\n{}", command, USAGE)),
+    }
+    Ok(true)
+}
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match run(&args) {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(error) => {
+            eprintln!("kiwi: {}", error);
+            process::exit(2);
+        }
+    }
+}
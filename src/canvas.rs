@@ -0,0 +1,117 @@
+use crate::erc::ErcViolation;
+use crate::renderer::*;
+use crate::schematic::*;
+use crate::theme::Theme;
+use crate::viewport::Viewport;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+// the browser's 2d context as a `Renderer`
+impl Renderer for web_sys::CanvasRenderingContext2d {
+    fn begin_path(&mut self) {
+        web_sys::CanvasRenderingContext2d::begin_path(self);
+    }
+
+    fn move_to(&mut self, x: f64, y: f64) {
+        web_sys::CanvasRenderingContext2d::move_to(self, x, y);
+    }
+
+    fn line_to(&mut self, x: f64, y: f64) {
+        web_sys::CanvasRenderingContext2d::line_to(self, x, y);
+    }
+
+    fn arc(&mut self, x: f64, y: f64, radius: f64, start: f64, end: f64) {
+        // only throws on a negative radius
+        let _ = web_sys::CanvasRenderingContext2d::arc(self, x, y, radius.abs(), start, end);
+    }
+
+    fn rect(&mut self, x: f64, y: f64, width: f64, height: f64) {
+        web_sys::CanvasRenderingContext2d::rect(self, x, y, width, height);
+    }
+
+    fn close_path(&mut self) {
+        web_sys::CanvasRenderingContext2d::close_path(self);
+    }
+
+    fn fill(&mut self) {
+        web_sys::CanvasRenderingContext2d::fill(self);
+    }
+
+    fn stroke(&mut self) {
+        web_sys::CanvasRenderingContext2d::stroke(self);
+    }
+
+    fn text(&mut self, text: &str, x: f64, y: f64) {
+        let _ = self.fill_text(text, x, y);
+    }
+
+    fn push_transform(&mut self, transform: &Transform) {
+        self.save();
+        let t = transform;
+        let _ = self.transform(t.a, t.b, t.c, t.d, t.e, t.f);
+    }
+
+    fn pop_transform(&mut self) {
+        self.restore();
+    }
+
+    fn set_style(&mut self, style: &Paint) {
+        self.set_stroke_style_str(&style.stroke.to_css());
+        self.set_fill_style_str(&style.fill.to_css());
+        self.set_line_width(style.width);
+        let dash = style
+            .dash
+            .iter()
+            .map(|len| JsValue::from_f64(*len))
+            .collect::<js_sys::Array>();
+        let _ = self.set_line_dash(&dash);
+        self.set_font(&format!(
+            "{}{}{}px monospace",
+            if style.italic { "italic " } else { "" },
+            if style.bold { "bold " } else { "" },
+            style.font_size
+        ));
+        self.set_text_align(match style.align {
+            TextAlign::Left => "left",
+            TextAlign::Center => "center",
+            TextAlign::Right => "right",
+        });
+        self.set_text_baseline(match style.baseline {
+            TextBaseline::Alphabetic => "alphabetic",
+            TextBaseline::Top => "top",
+            TextBaseline::Middle => "middle",
+            TextBaseline::Bottom => "bottom",
+        });
+    }
+}
+
+impl Schematic {
+    // draws into the canvas as it's sized, through the viewport. ratio is
+    // backing store px per viewport px (the screen's devicePixelRatio),
+    // highlighted items are drawn again on top
+    pub fn draw(
+        &self,
+        canvas: &web_sys::HtmlCanvasElement,
+        viewport: &Viewport,
+        ratio: f64,
+        theme: &Theme,
+        markers: &[ErcViolation],
+        highlight: &[ItemRef],
+    ) -> Result<(), JsValue> {
+        let context = &mut canvas
+            .get_context("2d")?
+            .unwrap()
+            .dyn_into::<web_sys::CanvasRenderingContext2d>()?;
+        context.set_fill_style_str(&theme.background.to_css());
+        context.fill_rect(0.0, 0.0, canvas.width() as f64, canvas.height() as f64);
+
+        context.push_transform(&Transform::scale(ratio, ratio).then(&viewport.transform()));
+        self.render_frame(context, theme);
+        self.render(context, theme, markers);
+        if !highlight.is_empty() {
+            self.render_highlight(context, theme, highlight);
+        }
+        context.pop_transform();
+        Ok(())
+    }
+}
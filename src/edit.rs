@@ -0,0 +1,789 @@
+use crate::schematic::*;
+use std::fmt;
+
+// editing operations on a schematic. items are addressed by uuid, every
+// operation leaves the uuid index current, and symbol references live in
+// the symbols' properties so the writer keeps kicad's instance data in step
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditError {
+    NotFound(Uuid),
+    UnknownSymbol(String),    // lib id missing from the embedded lib
+    UnknownReference(String), // no symbol has this reference
+    Unsupported(Uuid),        // the item can't do that, like rotating a sheet
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EditError::NotFound(uuid) => write!(f, "no item with uuid {}", uuid),
+            EditError::UnknownSymbol(id) => write!(f, "{:?} is not in the symbol lib", id),
+            EditError::UnknownReference(reference) => {
+                write!(f, "no symbol with reference {:?}", reference)
+            }
+            EditError::Unsupported(uuid) => write!(f, "item {} can't be changed that way", uuid),
+        }
+    }
+}
+
+impl std::error::Error for EditError {}
+
+// mirror axes, kicad's naming: `X` flips top to bottom, `Y` left to right
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+}
+
+fn spin(angle: f64, by: f64) -> f64 {
+    (angle + by).rem_euclid(360.0)
+}
+
+// a quarter turn counter clockwise (as seen, y down) around center
+fn rotate(point: &mut Point, center: &Point) {
+    let (dx, dy) = (point.x - center.x, point.y - center.y);
+    point.x = center.x + dy;
+    point.y = center.y - dx;
+}
+
+fn flip(point: &mut Point, center: &Point, axis: Axis) {
+    match axis {
+        Axis::X => point.y = 2.0 * center.y - point.y,
+        Axis::Y => point.x = 2.0 * center.x - point.x,
+    }
+}
+
+fn vertical(angle: f64) -> bool {
+    (angle - 90.0).abs() < 1e-6 || (angle - 270.0).abs() < 1e-6
+}
+
+// text turned over an axis reads the other way
+fn flip_angle(angle: f64, axis: Axis) -> f64 {
+    match (axis, vertical(angle)) {
+        (Axis::X, true) | (Axis::Y, false) => spin(angle, 180.0),
+        _ => angle,
+    }
+}
+
+// middle of the points' bounding box
+fn center(points: &[Point]) -> Point {
+    let (mut min, mut max) = (
+        Point::new(f64::MAX, f64::MAX),
+        Point::new(f64::MIN, f64::MIN),
+    );
+    for point in points {
+        min.x = min.x.min(point.x);
+        min.y = min.y.min(point.y);
+        max.x = max.x.max(point.x);
+        max.y = max.y.max(point.y);
+    }
+    Point::new((min.x + max.x) / 2.0, (min.y + max.y) / 2.0)
+}
+
+impl Schematic {
+    // a uuid no item of this schematic has
+    pub fn new_uuid(&self) -> Uuid {
+        loop {
+            let uuid = Uuid::new_v4();
+            if uuid != self.uuid && !self.index.contains_key(&uuid) {
+                return uuid;
+            }
+        }
+    }
+
+    pub fn add_wire(&mut self, start: Point, end: Point) -> Uuid {
+        let mut wire = Wire::blank();
+        wire.poss = vec![Point::new(start.x, start.y), Point::new(end.x, end.y)];
+        wire.uuid = self.new_uuid();
+        self.wires.push(wire);
+        self.reindex();
+        self.wires.last().unwrap().uuid
+    }
+
+    pub fn add_label(&mut self, text: &str, shape: Style, at: Point) -> Uuid {
+        let mut label = Label::blank();
+        label.id = text.to_string();
+        label.shape = shape;
+        label.pos = at;
+        label.uuid = self.new_uuid();
+        self.labels.push(label);
+        self.reindex();
+        self.labels.last().unwrap().uuid
+    }
+
+    // a new, unannotated ("R?") instance of a lib symbol, its fields placed
+    // where the lib has them. rotation is in degrees counter clockwise and
+    // mirror is (about y, about x) like `SymbolInst::mirror`
+    pub fn place_symbol(
+        &mut self,
+        lib_id: &str,
+        at: Point,
+        rotation: f64,
+        mirror: (bool, bool),
+    ) -> Result<Uuid, EditError> {
+        let parent = match self.lib.get(lib_id) {
+            Some(parent) => parent.clone(),
+            None => return Err(EditError::UnknownSymbol(lib_id.to_string())),
+        };
+        let mut symb = SymbolInst::blank();
+        symb.id = lib_id.to_string();
+        symb.pos = Point {
+            x: at.x,
+            y: at.y,
+            a: spin(rotation, 0.0),
+        };
+        symb.mirror = mirror;
+        symb.uuid = self.new_uuid();
+        // ki_keywords and the like only describe the lib symbol
+        for prop in parent
+            .props
+            .iter()
+            .filter(|prop| !prop.key.starts_with("ki_"))
+        {
+            let mut prop = prop.clone();
+            prop.pos = Point {
+                a: prop.pos.a,
+                ..symb.transform(&prop.pos)
+            };
+            if prop.key == "Reference" {
+                prop.value = format!("{}?", prop.value.trim_end_matches('?'));
+            }
+            symb.props.push(prop);
+        }
+        symb.parent = Some(parent);
+        self.symbs.push(symb);
+        self.reindex();
+        Ok(self.symbs.last().unwrap().uuid)
+    }
+
+    pub fn move_item(&mut self, uuid: &Uuid, dx: f64, dy: f64) -> Result<(), EditError> {
+        let shift = |point: &mut Point| {
+            point.x += dx;
+            point.y += dy;
+        };
+        match self.find_by_uuid(uuid) {
+            Some(ItemRef::Wire(i)) => self.wires[i].poss.iter_mut().for_each(shift),
+            Some(ItemRef::Polyline(i)) => self.polys[i].poss.iter_mut().for_each(shift),
+            Some(ItemRef::Junction(i)) => shift(&mut self.juncs[i].pos),
+            Some(ItemRef::Text(i)) => shift(&mut self.texts[i].pos),
+            Some(ItemRef::Label(i)) => shift(&mut self.labels[i].pos),
+            Some(ItemRef::Symbol(i)) => {
+                let symb = &mut self.symbs[i];
+                shift(&mut symb.pos);
+                symb.props.iter_mut().for_each(|prop| shift(&mut prop.pos));
+            }
+            Some(ItemRef::Sheet(i)) => {
+                let sheet = &mut self.sheets[i];
+                shift(&mut sheet.pos);
+                sheet.props.iter_mut().for_each(|prop| shift(&mut prop.pos));
+                sheet.pins.iter_mut().for_each(|pin| shift(&mut pin.pos));
+            }
+            None => return Err(EditError::NotFound(*uuid)),
+        }
+        self.update_bounds(uuid);
+        Ok(())
+    }
+
+    // a quarter turn counter clockwise, like kicad's `R`. symbols turn about
+    // their anchor, lines about their middle and text in place
+    pub fn rotate_item(&mut self, uuid: &Uuid) -> Result<(), EditError> {
+        match self.find_by_uuid(uuid) {
+            Some(ItemRef::Wire(i)) => {
+                let center = center(&self.wires[i].poss);
+                self.wires[i]
+                    .poss
+                    .iter_mut()
+                    .for_each(|point| rotate(point, &center));
+            }
+            Some(ItemRef::Polyline(i)) => {
+                let center = center(&self.polys[i].poss);
+                self.polys[i]
+                    .poss
+                    .iter_mut()
+                    .for_each(|point| rotate(point, &center));
+            }
+            Some(ItemRef::Junction(_)) => {}
+            Some(ItemRef::Text(i)) => self.texts[i].pos.a = spin(self.texts[i].pos.a, 90.0),
+            Some(ItemRef::Label(i)) => self.labels[i].pos.a = spin(self.labels[i].pos.a, 90.0),
+            Some(ItemRef::Symbol(i)) => {
+                let symb = &mut self.symbs[i];
+                // a single mirror reverses the sense of the symbol's angle
+                let by = match symb.mirror.0 != symb.mirror.1 {
+                    true => -90.0,
+                    false => 90.0,
+                };
+                symb.pos.a = spin(symb.pos.a, by);
+                let center = symb.pos.clone();
+                for prop in symb.props.iter_mut() {
+                    rotate(&mut prop.pos, &center);
+                    // fields stay readable, kicad toggles them between
+                    // horizontal and vertical
+                    prop.pos.a = match vertical(prop.pos.a) {
+                        true => 0.0,
+                        false => 90.0,
+                    };
+                }
+            }
+            Some(ItemRef::Sheet(_)) => return Err(EditError::Unsupported(*uuid)),
+            None => return Err(EditError::NotFound(*uuid)),
+        }
+        self.update_bounds(uuid);
+        Ok(())
+    }
+
+    pub fn mirror_item(&mut self, uuid: &Uuid, axis: Axis) -> Result<(), EditError> {
+        match self.find_by_uuid(uuid) {
+            Some(ItemRef::Wire(i)) => {
+                let center = center(&self.wires[i].poss);
+                self.wires[i]
+                    .poss
+                    .iter_mut()
+                    .for_each(|point| flip(point, &center, axis));
+            }
+            Some(ItemRef::Polyline(i)) => {
+                let center = center(&self.polys[i].poss);
+                self.polys[i]
+                    .poss
+                    .iter_mut()
+                    .for_each(|point| flip(point, &center, axis));
+            }
+            Some(ItemRef::Junction(_)) => {}
+            Some(ItemRef::Text(i)) => self.texts[i].pos.a = flip_angle(self.texts[i].pos.a, axis),
+            Some(ItemRef::Label(i)) => {
+                self.labels[i].pos.a = flip_angle(self.labels[i].pos.a, axis)
+            }
+            Some(ItemRef::Symbol(i)) => {
+                let symb = &mut self.symbs[i];
+                match axis {
+                    Axis::X => symb.mirror.1 = !symb.mirror.1,
+                    Axis::Y => symb.mirror.0 = !symb.mirror.0,
+                }
+                // kicad can't store both, that's a half turn
+                if symb.mirror == (true, true) {
+                    symb.mirror = (false, false);
+                    symb.pos.a = spin(symb.pos.a, 180.0);
+                }
+                let center = symb.pos.clone();
+                for prop in symb.props.iter_mut() {
+                    flip(&mut prop.pos, &center, axis);
+                }
+            }
+            Some(ItemRef::Sheet(_)) => return Err(EditError::Unsupported(*uuid)),
+            None => return Err(EditError::NotFound(*uuid)),
+        }
+        self.update_bounds(uuid);
+        Ok(())
+    }
+
+    // removes an item, and its symbol's lib entry once nothing uses it
+    pub fn delete_item(&mut self, uuid: &Uuid) -> Result<(), EditError> {
+        match self.find_by_uuid(uuid) {
+            Some(ItemRef::Wire(i)) => drop(self.wires.remove(i)),
+            Some(ItemRef::Polyline(i)) => drop(self.polys.remove(i)),
+            Some(ItemRef::Junction(i)) => drop(self.juncs.remove(i)),
+            Some(ItemRef::Text(i)) => drop(self.texts.remove(i)),
+            Some(ItemRef::Label(i)) => drop(self.labels.remove(i)),
+            Some(ItemRef::Symbol(i)) => {
+                let symb = self.symbs.remove(i);
+                if !self.symbs.iter().any(|other| other.id == symb.id) {
+                    self.lib.remove(&symb.id);
+                }
+            }
+            Some(ItemRef::Sheet(i)) => drop(self.sheets.remove(i)),
+            None => return Err(EditError::NotFound(*uuid)),
+        }
+        self.reindex();
+        Ok(())
+    }
+
+    // sets a field on every symbol (every unit) with this reference, new
+    // fields are added hidden at the symbol's anchor
+    pub fn set_property(
+        &mut self,
+        reference: &str,
+        key: &str,
+        value: &str,
+    ) -> Result<(), EditError> {
+        let mut found = Vec::new();
+        for symb in self.symbs.iter_mut() {
+            if symb.prop("Reference") != Some(reference) {
+                continue;
+            }
+            found.push(symb.uuid);
+            match symb.props.iter_mut().find(|prop| prop.key == key) {
+                Some(prop) => prop.value = value.to_string(),
+                None => {
+                    let mut prop = Property::blank();
+                    prop.key = key.to_string();
+                    prop.value = value.to_string();
+                    prop.id = symb.props.iter().map(|prop| prop.id + 1).max().unwrap_or(0);
+                    prop.show = false;
+                    prop.pos = Point::new(symb.pos.x, symb.pos.y);
+                    symb.props.push(prop);
+                }
+            }
+        }
+        for uuid in &found {
+            self.update_bounds(uuid);
+        }
+        match found.is_empty() {
+            false => Ok(()),
+            true => Err(EditError::UnknownReference(reference.to_string())),
+        }
+    }
+}
+
+// wire ends that follow an item when it's dragged
+impl Schematic {
+    // (wire, point index) of every wire end on the item's connection
+    // points, the pins of a symbol or a label's anchor
+    pub fn attached_ends(&self, uuid: &Uuid) -> Vec<(Uuid, usize)> {
+        let points = match self.find_by_uuid(uuid) {
+            Some(ItemRef::Symbol(i)) => self.symbs[i]
+                .pins()
+                .into_iter()
+                .map(|(_, point)| point)
+                .collect(),
+            Some(ItemRef::Label(i)) => vec![self.labels[i].pos.clone()],
+            Some(ItemRef::Sheet(i)) => self.sheets[i]
+                .pins
+                .iter()
+                .map(|pin| pin.pos.clone())
+                .collect(),
+            _ => Vec::new(),
+        };
+        let mut ends = Vec::new();
+        for wire in &self.wires {
+            let last = wire.poss.len().saturating_sub(1);
+            for index in [0, last] {
+                if points.iter().any(|point| point.same_as(&wire.poss[index])) {
+                    ends.push((wire.uuid, index));
+                }
+            }
+        }
+        ends.dedup();
+        ends
+    }
+
+    pub fn move_wire_end(
+        &mut self,
+        uuid: &Uuid,
+        index: usize,
+        dx: f64,
+        dy: f64,
+    ) -> Result<(), EditError> {
+        match self.find_by_uuid(uuid) {
+            Some(ItemRef::Wire(i)) if index < self.wires[i].poss.len() => {
+                let point = &mut self.wires[i].poss[index];
+                point.x += dx;
+                point.y += dy;
+                self.update_bounds(uuid);
+                Ok(())
+            }
+            Some(_) => Err(EditError::Unsupported(*uuid)),
+            None => Err(EditError::NotFound(*uuid)),
+        }
+    }
+}
+
+// an item's whole state, what undo puts back
+#[derive(Debug, Clone)]
+enum Snapshot {
+    Wire(Wire),
+    Junction(Junction),
+    Text(Text),
+    Polyline(Polyline),
+    Label(Label),
+    Symbol(SymbolInst),
+    Sheet(Sheet),
+}
+
+impl Schematic {
+    fn snapshot(&self, uuid: &Uuid) -> Option<Snapshot> {
+        Some(match self.find_by_uuid(uuid)? {
+            ItemRef::Wire(i) => Snapshot::Wire(self.wires[i].clone()),
+            ItemRef::Junction(i) => Snapshot::Junction(self.juncs[i].clone()),
+            ItemRef::Text(i) => Snapshot::Text(self.texts[i].clone()),
+            ItemRef::Polyline(i) => Snapshot::Polyline(self.polys[i].clone()),
+            ItemRef::Label(i) => Snapshot::Label(self.labels[i].clone()),
+            ItemRef::Symbol(i) => Snapshot::Symbol(self.symbs[i].clone()),
+            ItemRef::Sheet(i) => Snapshot::Sheet(self.sheets[i].clone()),
+        })
+    }
+
+    // puts an item back the way it was, edits don't change uuids
+    fn restore(&mut self, snapshot: &Snapshot) {
+        self.restore_item(snapshot);
+        self.update_bounds(&snapshot.uuid());
+    }
+
+    fn restore_item(&mut self, snapshot: &Snapshot) {
+        match snapshot {
+            Snapshot::Wire(item) => {
+                if let Some(ItemRef::Wire(i)) = self.find_by_uuid(&item.uuid) {
+                    self.wires[i] = item.clone();
+                }
+            }
+            Snapshot::Junction(item) => {
+                if let Some(ItemRef::Junction(i)) = self.find_by_uuid(&item.uuid) {
+                    self.juncs[i] = item.clone();
+                }
+            }
+            Snapshot::Text(item) => {
+                if let Some(ItemRef::Text(i)) = self.find_by_uuid(&item.uuid) {
+                    self.texts[i] = item.clone();
+                }
+            }
+            Snapshot::Polyline(item) => {
+                if let Some(ItemRef::Polyline(i)) = self.find_by_uuid(&item.uuid) {
+                    self.polys[i] = item.clone();
+                }
+            }
+            Snapshot::Label(item) => {
+                if let Some(ItemRef::Label(i)) = self.find_by_uuid(&item.uuid) {
+                    self.labels[i] = item.clone();
+                }
+            }
+            Snapshot::Symbol(item) => {
+                if let Some(ItemRef::Symbol(i)) = self.find_by_uuid(&item.uuid) {
+                    self.symbs[i] = item.clone();
+                }
+            }
+            Snapshot::Sheet(item) => {
+                if let Some(ItemRef::Sheet(i)) = self.find_by_uuid(&item.uuid) {
+                    self.sheets[i] = item.clone();
+                }
+            }
+        }
+    }
+}
+
+// undo and redo of in place edits (moves, rotations, mirrors, field
+// changes). a step is everything one action changed, so a drag that moves a
+// symbol and the wire ends on its pins undoes in one go
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    undo: Vec<Vec<(Snapshot, Snapshot)>>, // (before, after) of each item
+    redo: Vec<Vec<(Snapshot, Snapshot)>>,
+    open: Vec<Snapshot>, // befores of the step being recorded
+}
+
+impl History {
+    pub fn new() -> History {
+        History::default()
+    }
+
+    // starts a step that will change these items
+    pub fn begin(&mut self, schem: &Schematic, uuids: &[Uuid]) {
+        self.open = uuids
+            .iter()
+            .filter_map(|uuid| schem.snapshot(uuid))
+            .collect();
+    }
+
+    // ends the step, it can now be undone
+    pub fn commit(&mut self, schem: &Schematic) {
+        let step = std::mem::take(&mut self.open)
+            .into_iter()
+            .filter_map(|before| {
+                let after = schem.snapshot(&before.uuid())?;
+                Some((before, after))
+            })
+            .collect::<Vec<_>>();
+        if !step.is_empty() {
+            self.undo.push(step);
+            self.redo.clear();
+        }
+    }
+
+    // drops the step and puts its items back
+    pub fn cancel(&mut self, schem: &mut Schematic) {
+        for before in std::mem::take(&mut self.open).iter().rev() {
+            schem.restore(before);
+        }
+    }
+
+    // one edit as a step of its own, nothing is recorded if it fails
+    pub fn record<F>(
+        &mut self,
+        schem: &mut Schematic,
+        uuids: &[Uuid],
+        edit: F,
+    ) -> Result<(), EditError>
+    where
+        F: FnOnce(&mut Schematic) -> Result<(), EditError>,
+    {
+        self.begin(schem, uuids);
+        match edit(schem) {
+            Ok(()) => {
+                self.commit(schem);
+                Ok(())
+            }
+            Err(error) => {
+                self.cancel(schem);
+                Err(error)
+            }
+        }
+    }
+
+    pub fn undo(&mut self, schem: &mut Schematic) -> bool {
+        match self.undo.pop() {
+            Some(step) => {
+                for (before, _) in step.iter().rev() {
+                    schem.restore(before);
+                }
+                self.redo.push(step);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn redo(&mut self, schem: &mut Schematic) -> bool {
+        match self.redo.pop() {
+            Some(step) => {
+                for (_, after) in &step {
+                    schem.restore(after);
+                }
+                self.undo.push(step);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
+
+impl Snapshot {
+    fn uuid(&self) -> Uuid {
+        match self {
+            Snapshot::Wire(item) => item.uuid,
+            Snapshot::Junction(item) => item.uuid,
+            Snapshot::Text(item) => item.uuid,
+            Snapshot::Polyline(item) => item.uuid,
+            Snapshot::Label(item) => item.uuid,
+            Snapshot::Symbol(item) => item.uuid,
+            Snapshot::Sheet(item) => item.uuid,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spatial::SpatialIndex;
+
+    const FILE: &str = include_str!("../test/kicad7.kicad_sch");
+
+    // R1, and the wire into its pin 2
+    fn ids() -> (Uuid, Uuid) {
+        (
+            "b3c4d5e6-f708-4314-8f90-a1b2c3d4e5f6".parse().unwrap(),
+            "3b4c5d6e-7f80-4b9c-8d1e-2f3a4b5c6d7e".parse().unwrap(),
+        )
+    }
+
+    fn r1(schem: &Schematic) -> &SymbolInst {
+        &schem.symbs[0]
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut schem = Schematic::from_str(FILE);
+        let mut history = History::new();
+        let (symbol, wire) = ids();
+        assert!(!history.can_undo() && !history.undo(&mut schem));
+
+        // a drag of the symbol and its wire end is one step
+        history
+            .record(&mut schem, &[symbol, wire], |schem| {
+                schem.move_item(&symbol, 0.0, 2.54)?;
+                schem.move_wire_end(&wire, 0, 0.0, 2.54)
+            })
+            .unwrap();
+        history
+            .record(&mut schem, &[symbol], |schem| {
+                schem.set_property("R1", "Value", "22k")
+            })
+            .unwrap();
+        assert_eq!(r1(&schem).prop("Value"), Some("22k"));
+
+        assert!(history.undo(&mut schem));
+        assert_eq!(r1(&schem).prop("Value"), Some("10k"));
+        assert_eq!(r1(&schem).pos.y, 76.2 + 2.54);
+        assert!(history.undo(&mut schem));
+        assert_eq!(r1(&schem).pos.y, 76.2);
+        assert_eq!(schem.wires[1].poss[0].y, 76.2);
+        assert!(!history.can_undo() && history.can_redo());
+        assert_eq!(schem.to_kicad_sch(), FILE);
+
+        assert!(history.redo(&mut schem));
+        assert_eq!(r1(&schem).pos.y, 76.2 + 2.54);
+        assert_eq!(schem.wires[1].poss[0].y, 76.2 + 2.54);
+        assert!(history.redo(&mut schem));
+        assert_eq!(r1(&schem).prop("Value"), Some("22k"));
+        assert!(!history.redo(&mut schem));
+
+        // a new step after an undo drops what could be redone
+        history.undo(&mut schem);
+        history
+            .record(&mut schem, &[symbol], |schem| schem.rotate_item(&symbol))
+            .unwrap();
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn cancel_puts_items_back() {
+        let mut schem = Schematic::from_str(FILE);
+        let mut history = History::new();
+        let (symbol, wire) = ids();
+        history.begin(&schem, &[symbol, wire]);
+        schem.move_item(&symbol, 5.08, 5.08).unwrap();
+        schem.move_wire_end(&wire, 0, 5.08, 5.08).unwrap();
+        history.cancel(&mut schem);
+        assert_eq!((r1(&schem).pos.x, r1(&schem).pos.y), (100.33, 76.2));
+        assert!(!history.can_undo());
+        assert_eq!(schem.to_kicad_sch(), FILE);
+
+        // a failed edit is cancelled, not recorded
+        let missing = Uuid::nil();
+        let result = history.record(&mut schem, &[symbol], |schem| {
+            schem.move_item(&symbol, 5.08, 0.0)?;
+            schem.move_item(&missing, 5.08, 0.0)
+        });
+        assert_eq!(result, Err(EditError::NotFound(missing)));
+        assert_eq!(r1(&schem).pos.x, 100.33);
+        assert!(!history.can_undo());
+    }
+
+    fn c1() -> Uuid {
+        "e6f70819-2a3b-4647-9c23-d4e5f6071829".parse().unwrap()
+    }
+
+    fn field_angles(symb: &SymbolInst) -> Vec<f64> {
+        symb.props.iter().map(|prop| prop.pos.a).collect()
+    }
+
+    #[test]
+    fn place_symbols() {
+        let mut schem = Schematic::from_str(FILE);
+        let missing = schem.place_symbol("Device:L", Point::new(0.0, 0.0), 0.0, (false, false));
+        assert_eq!(
+            missing,
+            Err(EditError::UnknownSymbol("Device:L".to_string()))
+        );
+        assert_eq!(schem.symbs.len(), 3);
+
+        let at = Point::new(50.8, 50.8);
+        let uuid = schem
+            .place_symbol("Device:R", at.clone(), 450.0, (false, false))
+            .unwrap();
+        assert_eq!(schem.find_by_uuid(&uuid), Some(ItemRef::Symbol(3)));
+        let symb = &schem.symbs[3];
+        assert_eq!((symb.pos.x, symb.pos.y, symb.pos.a), (50.8, 50.8, 90.0));
+        assert_eq!(symb.prop("Reference"), Some("R?"));
+        assert_eq!(symb.prop("Value"), Some("R"));
+        assert!(symb.parent.is_some());
+        assert!(symb.props.iter().all(|prop| !prop.key.starts_with("ki_")));
+        assert!(schem
+            .hit_test(at.x, at.y, 0.1)
+            .contains(&ItemRef::Symbol(3)));
+    }
+
+    #[test]
+    fn add_wires_and_labels() {
+        let mut schem = Schematic::from_str(FILE);
+        let wire = schem.add_wire(Point::new(10.16, 10.16), Point::new(20.32, 10.16));
+        let label = schem.add_label("CLK", Style::Global, Point::new(20.32, 10.16));
+        assert_ne!(wire, label);
+        let wires = schem.wires.len();
+        assert_eq!(schem.find_by_uuid(&wire), Some(ItemRef::Wire(wires - 1)));
+        assert_eq!(schem.wires[wires - 1].poss.len(), 2);
+        let labels = schem.labels.len();
+        assert_eq!(schem.find_by_uuid(&label), Some(ItemRef::Label(labels - 1)));
+        let added = &schem.labels[labels - 1];
+        assert_eq!(added.id, "CLK");
+        assert!(matches!(added.shape, Style::Global));
+        let hits = schem.hit_test(15.24, 10.16, 0.1);
+        assert_eq!(hits, vec![ItemRef::Wire(wires - 1)]);
+        // the label names the new wire's net
+        let netlist = schem.netlist();
+        assert!(netlist.nets.iter().any(|net| net.name == "CLK"));
+    }
+
+    #[test]
+    fn delete_items() {
+        let mut schem = Schematic::from_str(FILE);
+        let (r1, wire) = ids();
+        let c1 = c1();
+        let gnd = schem.symbs[2].uuid;
+        let (x, y) = (schem.symbs[2].pos.x, schem.symbs[2].pos.y);
+
+        // the only Device:C goes with its lib entry, R1 keeps its own
+        schem.delete_item(&c1).unwrap();
+        assert!(!schem.lib.contains_key("Device:C"));
+        assert!(schem.lib.contains_key("Device:R"));
+        assert_eq!(schem.find_by_uuid(&c1), None);
+        assert_eq!(schem.find_by_uuid(&r1), Some(ItemRef::Symbol(0)));
+        assert_eq!(schem.find_by_uuid(&gnd), Some(ItemRef::Symbol(1)));
+        assert!(schem.hit_test(x, y, 0.1).contains(&ItemRef::Symbol(1)));
+        assert_eq!(schem.delete_item(&c1), Err(EditError::NotFound(c1)));
+
+        let wires = schem.wires.len();
+        schem.delete_item(&wire).unwrap();
+        assert_eq!(schem.wires.len(), wires - 1);
+        assert_eq!(schem.find_by_uuid(&wire), None);
+        for (i, other) in schem.wires.iter().enumerate() {
+            assert_eq!(schem.find_by_uuid(&other.uuid), Some(ItemRef::Wire(i)));
+        }
+        // the spatial index matches one built from scratch
+        let (min, max) = (Point::new(-1e6, -1e6), Point::new(1e6, 1e6));
+        let items = |index: &SpatialIndex| {
+            let mut items = index.search(&min, &max);
+            items.sort_by_key(|item| format!("{:?}", item));
+            items
+        };
+        assert_eq!(items(&schem.spatial), items(&schem.build_spatial_index()));
+        assert!(schem.lib.contains_key("Device:R"));
+    }
+
+    #[test]
+    fn rotate_turns_fields() {
+        let mut schem = Schematic::from_str(FILE);
+        let (r1, _) = ids();
+        assert_eq!(field_angles(&schem.symbs[0]), [90.0, 90.0, 90.0, 0.0]);
+        schem.rotate_item(&r1).unwrap();
+        assert_eq!(schem.symbs[0].pos.a, 180.0);
+        assert_eq!(field_angles(&schem.symbs[0]), [0.0, 0.0, 0.0, 90.0]);
+        schem.rotate_item(&r1).unwrap();
+        assert_eq!(schem.symbs[0].pos.a, 270.0);
+        assert_eq!(field_angles(&schem.symbs[0]), [90.0, 90.0, 90.0, 0.0]);
+    }
+
+    #[test]
+    fn mirror_twice_is_a_half_turn() {
+        let mut mirrored = Schematic::from_str(FILE);
+        let mut turned = mirrored.clone();
+        let c1 = c1();
+        mirrored.mirror_item(&c1, Axis::X).unwrap();
+        assert_eq!(mirrored.symbs[1].mirror, (false, true));
+        mirrored.mirror_item(&c1, Axis::Y).unwrap();
+        turned.rotate_item(&c1).unwrap();
+        turned.rotate_item(&c1).unwrap();
+
+        let (a, b) = (&mirrored.symbs[1], &turned.symbs[1]);
+        assert_eq!(a.mirror, (false, false));
+        assert_eq!((a.pos.x, a.pos.y, a.pos.a), (b.pos.x, b.pos.y, b.pos.a));
+        assert_eq!(a.pos.a, 180.0);
+        for (a, b) in a.props.iter().zip(&b.props) {
+            assert!((a.pos.x - b.pos.x).abs() < 1e-9 && (a.pos.y - b.pos.y).abs() < 1e-9);
+            assert_eq!(a.pos.a, b.pos.a, "{}", a.key);
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use crate::netlist::*;
+use crate::schematic::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct ErcViolation {
+    pub severity: Severity,
+    pub message: String,
+    pub pos: Point,
+}
+
+impl ErcViolation {
+    fn new(severity: Severity, message: String, pos: &Point) -> ErcViolation {
+        ErcViolation {
+            severity,
+            message,
+            pos: pos.clone(),
+        }
+    }
+}
+
+const OK: Option<Severity> = None;
+const WAR: Option<Severity> = Some(Severity::Warning);
+const ERR: Option<Severity> = Some(Severity::Error);
+
+// kicad's default pin conflict matrix, ordered as `pin_index`
+#[rustfmt::skip]
+const PIN_MATRIX: [[Option<Severity>; 12]; 12] = [
+    /*          In   Out  Bi   3S   Pas  Free Uns  PwrI PwrO OC   OE   NC */
+    /* In   */ [OK,  OK,  OK,  OK,  OK,  OK,  WAR, OK,  OK,  OK,  OK,  ERR],
+    /* Out  */ [OK,  ERR, OK,  WAR, OK,  OK,  WAR, OK,  ERR, ERR, ERR, ERR],
+    /* Bi   */ [OK,  OK,  OK,  OK,  OK,  OK,  WAR, OK,  WAR, OK,  WAR, ERR],
+    /* 3S   */ [OK,  WAR, OK,  OK,  OK,  OK,  WAR, WAR, ERR, WAR, WAR, ERR],
+    /* Pas  */ [OK,  OK,  OK,  OK,  OK,  OK,  WAR, OK,  OK,  OK,  OK,  ERR],
+    /* Free */ [OK,  OK,  OK,  OK,  OK,  OK,  OK,  OK,  OK,  OK,  OK,  ERR],
+    /* Uns  */ [WAR, WAR, WAR, WAR, WAR, OK,  WAR, WAR, WAR, WAR, WAR, ERR],
+    /* PwrI */ [OK,  OK,  OK,  WAR, OK,  OK,  WAR, OK,  OK,  OK,  OK,  ERR],
+    /* PwrO */ [OK,  ERR, WAR, ERR, OK,  OK,  WAR, OK,  ERR, ERR, ERR, ERR],
+    /* OC   */ [OK,  ERR, OK,  WAR, OK,  OK,  WAR, OK,  ERR, OK,  OK,  ERR],
+    /* OE   */ [OK,  ERR, WAR, WAR, OK,  OK,  WAR, OK,  ERR, OK,  OK,  ERR],
+    /* NC   */ [ERR, ERR, ERR, ERR, ERR, ERR, ERR, ERR, ERR, ERR, ERR, ERR],
+];
+
+fn pin_index(kind: PinType) -> usize {
+    match kind {
+        PinType::Input => 0,
+        PinType::Output => 1,
+        PinType::Bidirectional => 2,
+        PinType::TriState => 3,
+        PinType::Passive => 4,
+        PinType::Free => 5,
+        PinType::Unspecified => 6,
+        PinType::PowerIn => 7,
+        PinType::PowerOut => 8,
+        PinType::OpenCollector => 9,
+        PinType::OpenEmitter => 10,
+        PinType::NoConnect => 11,
+    }
+}
+
+pub fn pin_conflict(a: PinType, b: PinType) -> Option<Severity> {
+    PIN_MATRIX[pin_index(a)][pin_index(b)]
+}
+
+fn pin_desc(pin: &NetPin) -> String {
+    format!(
+        "{} pin {} ({:?})",
+        pin.reference, pin.pin.numb.0, pin.pin.kind
+    )
+}
+
+impl Schematic {
+    pub fn erc(&self) -> Vec<ErcViolation> {
+        let netlist = self.netlist();
+        let mut violations = Vec::<ErcViolation>::new();
+
+        for net in &netlist.nets {
+            let noconns = net
+                .labels
+                .iter()
+                .filter(|label| matches!(self.labels[**label].shape, Style::Noconn))
+                .collect::<Vec<_>>();
+
+            // pins left floating
+            if net.pins.len() == 1 && net.names.is_empty() && noconns.is_empty() {
+                let pin = &net.pins[0];
+                if pin.pin.kind != PinType::NoConnect {
+                    violations.push(ErcViolation::new(
+                        Severity::Error,
+                        format!("Pin not connected: {}", pin_desc(pin)),
+                        &pin.pos,
+                    ));
+                }
+            }
+
+            // no_connect markers on something that is connected
+            if net.pins.len() > 1 || (!net.pins.is_empty() && !net.names.is_empty()) {
+                for label in &noconns {
+                    violations.push(ErcViolation::new(
+                        Severity::Warning,
+                        format!("No-connect marker on connected net {}", net.name),
+                        &self.labels[**label].pos,
+                    ));
+                }
+            }
+
+            // pin to pin conflicts, one marker per pin for its worst pairing
+            let mut reported = Vec::<(usize, usize)>::new();
+            for (index, a) in net.pins.iter().enumerate() {
+                let worst = net
+                    .pins
+                    .iter()
+                    .enumerate()
+                    .filter(|(other, _)| *other != index)
+                    .filter_map(|(other, b)| {
+                        pin_conflict(a.pin.kind, b.pin.kind).map(|severity| (severity, other))
+                    })
+                    .max_by_key(|(severity, _)| *severity);
+                if let Some((severity, other)) = worst {
+                    if reported.contains(&(other, index)) {
+                        continue;
+                    }
+                    reported.push((index, other));
+                    violations.push(ErcViolation::new(
+                        severity,
+                        format!(
+                            "Pin conflict on net {}: {} and {}",
+                            net.name,
+                            pin_desc(a),
+                            pin_desc(&net.pins[other])
+                        ),
+                        &a.pos,
+                    ));
+                }
+            }
+
+            // power inputs need a power output (or PWR_FLAG) on the net, one
+            // marker per net on its first power input like kicad does
+            let driven = net.pins.iter().any(|pin| pin.pin.kind == PinType::PowerOut);
+            let input = net.pins.iter().find(|pin| pin.pin.kind == PinType::PowerIn);
+            if let (false, Some(pin)) = (driven, input) {
+                violations.push(ErcViolation::new(
+                    Severity::Error,
+                    format!(
+                        "Power input not driven on net {}: {}",
+                        net.name,
+                        pin_desc(pin)
+                    ),
+                    &pin.pos,
+                ));
+            }
+
+            // more than one name on a single net
+            if net.names.len() > 1 {
+                let pos = match net.labels.first() {
+                    Some(label) => self.labels[*label].pos.clone(),
+                    None => net
+                        .pins
+                        .first()
+                        .map(|pin| pin.pos.clone())
+                        .unwrap_or_else(Point::blank),
+                };
+                violations.push(ErcViolation::new(
+                    Severity::Warning,
+                    format!("Multiple net names on one net: {}", net.names.join(", ")),
+                    &pos,
+                ));
+            }
+        }
+
+        // labels touching nothing
+        for label in &self.labels {
+            if matches!(label.shape, Style::Noconn) {
+                continue;
+            }
+            let touching = self
+                .nodes_at(&label.pos)
+                .into_iter()
+                .filter(|node| !matches!(node, Node::Label(_)))
+                .count();
+            if touching == 0 {
+                violations.push(ErcViolation::new(
+                    Severity::Warning,
+                    format!("Label not connected: {}", label.id),
+                    &label.pos,
+                ));
+            }
+        }
+
+        // wire ends touching nothing
+        for (index, wire) in self.wires.iter().enumerate() {
+            for end in [wire.poss.first(), wire.poss.last()].iter().flatten() {
+                let touching = self
+                    .nodes_at(end)
+                    .into_iter()
+                    .filter(|node| *node != Node::Wire(index))
+                    .count();
+                let junction = self.juncs.iter().any(|junc| junc.pos.same_as(end));
+                if touching == 0 && !junction {
+                    violations.push(ErcViolation::new(
+                        Severity::Warning,
+                        "Wire end not connected".to_string(),
+                        end,
+                    ));
+                }
+            }
+        }
+
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::netlist::tests::{part, sheet, symbol, wire};
+
+    fn found<'a>(violations: &'a [ErcViolation], message: &str) -> Vec<&'a ErcViolation> {
+        violations
+            .iter()
+            .filter(|violation| violation.message.starts_with(message))
+            .collect()
+    }
+
+    #[test]
+    fn undriven_power_inputs() {
+        let lib = vec![
+            part("Load", "power_in", false, false),
+            part("Reg", "power_out", false, false),
+        ];
+        let mut items = vec![
+            symbol("Load", "U1", "", 0.0, 0.0, 1),
+            symbol("Load", "U2", "", 10.0, 0.0, 2),
+            wire((0.0, 0.0), (10.0, 0.0), 3),
+        ];
+        let violations = sheet(&lib, &items).erc();
+        // one marker for the net, on its first power input
+        let undriven = found(&violations, "Power input not driven");
+        assert_eq!(undriven.len(), 1);
+        assert_eq!(undriven[0].severity, Severity::Error);
+        assert!(undriven[0].pos.same_as(&Point::new(0.0, 0.0)));
+
+        items.push(symbol("Reg", "U3", "", 5.0, 0.0, 4));
+        let violations = sheet(&lib, &items).erc();
+        assert!(found(&violations, "Power input not driven").is_empty());
+    }
+
+    #[test]
+    fn outputs_driving_one_net() {
+        assert_eq!(
+            pin_conflict(PinType::Output, PinType::Output),
+            Some(Severity::Error)
+        );
+        let lib = vec![part("Drv", "output", false, false)];
+        let items = [
+            symbol("Drv", "U1", "", 0.0, 0.0, 1),
+            symbol("Drv", "U2", "", 10.0, 0.0, 2),
+            wire((0.0, 0.0), (10.0, 0.0), 3),
+        ];
+        let violations = sheet(&lib, &items).erc();
+        let conflicts = found(&violations, "Pin conflict");
+        // one marker for the pair
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn dangling_wire_end() {
+        let lib = vec![part("R", "passive", false, false)];
+        let items = [
+            symbol("R", "R1", "", 0.0, 0.0, 1),
+            symbol("R", "R2", "", 0.0, 20.0, 2),
+            wire((0.0, 0.0), (10.0, 0.0), 3),
+            wire((0.0, 20.0), (0.0, 0.0), 4),
+        ];
+        let violations = sheet(&lib, &items).erc();
+        let loose = found(&violations, "Wire end not connected");
+        assert_eq!(loose.len(), 1);
+        assert_eq!(loose[0].severity, Severity::Warning);
+        assert!(loose[0].pos.same_as(&Point::new(10.0, 0.0)));
+        assert!(found(&violations, "Pin not connected").is_empty());
+    }
+
+    #[test]
+    fn floating_pin() {
+        let lib = vec![part("R", "passive", false, false)];
+        let violations = sheet(&lib, &[symbol("R", "R1", "", 0.0, 0.0, 1)]).erc();
+        assert_eq!(found(&violations, "Pin not connected").len(), 1);
+    }
+}
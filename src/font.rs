@@ -0,0 +1,138 @@
+// built-in stroke font for backends without text of their own (like kicad,
+// schematics are drawn with stroked glyphs), printable ascii only
+
+// monospace cell, per unit of font size, matching courier
+pub const ADVANCE: f64 = 0.6;
+pub const CAP_HEIGHT: f64 = 0.7;
+pub const DESCENT: f64 = 0.2;
+
+// strokes on a grid of 0..4 wide, 0 at the cap line, 7 at the baseline and
+// 9 at the bottom of descenders, "x,y x,y;x,y x,y" for two polylines
+fn glyph(c: char) -> &'static str {
+    match c {
+        ' ' => "",
+        '!' => "2,0 2,5;2,6.6 2,7",
+        '"' => "1,0 1,2;3,0 3,2",
+        '#' => "1,0 1,7;3,0 3,7;0,2 4,2;0,5 4,5",
+        '$' => "4,1 1,1 0,2 1,3.5 3,3.5 4,5 3,6 0,6;2,0 2,7",
+        '%' => "0,7 4,0;0,0 1,0 1,1 0,1 0,0;3,6 4,6 4,7 3,7 3,6",
+        '&' => "4,7 1,2 1,1 2,0 3,1 3,2 0,5 0,6 1,7 2,7 4,5",
+        '\'' => "2,0 2,2",
+        '(' => "3,0 2,1 1,3 1,4 2,6 3,7",
+        ')' => "1,0 2,1 3,3 3,4 2,6 1,7",
+        '*' => "2,1 2,5;0,2 4,4;4,2 0,4",
+        '+' => "2,1.5 2,5.5;0,3.5 4,3.5",
+        ',' => "2,6.5 2,7 1,8",
+        '-' => "0,3.5 4,3.5",
+        '.' => "2,6.6 2,7",
+        '/' => "0,7 4,0",
+        '0' => "1,0 3,0 4,1 4,6 3,7 1,7 0,6 0,1 1,0;0,6 4,1",
+        '1' => "1,1 2,0 2,7;1,7 3,7",
+        '2' => "0,1 1,0 3,0 4,1 4,2 0,7 4,7",
+        '3' => "0,0 4,0 2,3 3,3 4,4 4,6 3,7 1,7 0,6",
+        '4' => "3,7 3,0 0,5 4,5",
+        '5' => "4,0 0,0 0,3 3,3 4,4 4,6 3,7 0,7",
+        '6' => "3,0 1,0 0,1 0,6 1,7 3,7 4,6 4,4 3,3 0,3",
+        '7' => "0,0 4,0 1,7",
+        '8' => "1,0 3,0 4,1 4,2 3,3 1,3 0,4 0,6 1,7 3,7 4,6 4,4 3,3;1,3 0,2 0,1 1,0",
+        '9' => "4,4 1,4 0,3 0,1 1,0 3,0 4,1 4,6 3,7 1,7",
+        ':' => "2,2 2,2.4;2,6.6 2,7",
+        ';' => "2,2 2,2.4;2,6.5 2,7 1,8",
+        '<' => "4,1 0,3.5 4,6",
+        '=' => "0,2.5 4,2.5;0,4.5 4,4.5",
+        '>' => "0,1 4,3.5 0,6",
+        '?' => "0,1 1,0 3,0 4,1 4,2 2,4 2,5;2,6.6 2,7",
+        '@' => "3,4 2,4 2,3 3,3 3,5 4,5 4,1 3,0 1,0 0,1 0,6 1,7 4,7",
+        'A' => "0,7 0,2 2,0 4,2 4,7;0,4 4,4",
+        'B' => "0,0 3,0 4,1 4,2 3,3 0,3;3,3 4,4 4,6 3,7 0,7 0,0",
+        'C' => "4,1 3,0 1,0 0,1 0,6 1,7 3,7 4,6",
+        'D' => "0,0 3,0 4,1 4,6 3,7 0,7 0,0",
+        'E' => "4,0 0,0 0,7 4,7;0,3.5 3,3.5",
+        'F' => "4,0 0,0 0,7;0,3.5 3,3.5",
+        'G' => "4,1 3,0 1,0 0,1 0,6 1,7 3,7 4,6 4,4 2,4",
+        'H' => "0,0 0,7;4,0 4,7;0,3.5 4,3.5",
+        'I' => "1,0 3,0;2,0 2,7;1,7 3,7",
+        'J' => "1,0 4,0;3,0 3,6 2,7 1,7 0,6",
+        'K' => "0,0 0,7;4,0 0,4;1,3 4,7",
+        'L' => "0,0 0,7 4,7",
+        'M' => "0,7 0,0 2,3 4,0 4,7",
+        'N' => "0,7 0,0 4,7 4,0",
+        'O' => "1,0 3,0 4,1 4,6 3,7 1,7 0,6 0,1 1,0",
+        'P' => "0,7 0,0 3,0 4,1 4,3 3,4 0,4",
+        'Q' => "1,0 3,0 4,1 4,6 3,7 1,7 0,6 0,1 1,0;2,5 4,8",
+        'R' => "0,7 0,0 3,0 4,1 4,3 3,4 0,4;2,4 4,7",
+        'S' => "4,1 3,0 1,0 0,1 0,2 1,3 3,4 4,5 4,6 3,7 1,7 0,6",
+        'T' => "0,0 4,0;2,0 2,7",
+        'U' => "0,0 0,6 1,7 3,7 4,6 4,0",
+        'V' => "0,0 2,7 4,0",
+        'W' => "0,0 1,7 2,3 3,7 4,0",
+        'X' => "0,0 4,7;4,0 0,7",
+        'Y' => "0,0 2,3.5 4,0;2,3.5 2,7",
+        'Z' => "0,0 4,0 0,7 4,7",
+        '[' => "3,0 1,0 1,7 3,7",
+        '\\' => "0,0 4,7",
+        ']' => "1,0 3,0 3,7 1,7",
+        '^' => "0,2 2,0 4,2",
+        '_' => "0,8 4,8",
+        '`' => "1,0 2,1",
+        'a' => "1,3 3,3 4,4 4,7 1,7 0,6 1,5 4,5",
+        'b' => "0,0 0,7 3,7 4,6 4,4 3,3 0,3",
+        'c' => "4,3 1,3 0,4 0,6 1,7 4,7",
+        'd' => "4,0 4,7 1,7 0,6 0,4 1,3 4,3",
+        'e' => "0,5 4,5 4,4 3,3 1,3 0,4 0,6 1,7 4,7",
+        'f' => "4,0 3,0 2,1 2,7;1,3 4,3",
+        'g' => "4,7 1,7 0,6 0,4 1,3 4,3 4,8 3,9 0,9",
+        'h' => "0,0 0,7;0,3 3,3 4,4 4,7",
+        'i' => "2,3 2,7;2,1 2,1.4",
+        'j' => "3,3 3,8 2,9 1,9;3,1 3,1.4",
+        'k' => "0,0 0,7;4,3 0,5.5;1.5,5 4,7",
+        'l' => "1,0 2,0 2,7;1,7 3,7",
+        'm' => "0,7 0,3;0,4 1,3 2,4 2,7;2,4 3,3 4,4 4,7",
+        'n' => "0,3 0,7;0,4 1,3 3,3 4,4 4,7",
+        'o' => "1,3 3,3 4,4 4,6 3,7 1,7 0,6 0,4 1,3",
+        'p' => "0,9 0,3 3,3 4,4 4,6 3,7 0,7",
+        'q' => "4,9 4,3 1,3 0,4 0,6 1,7 4,7",
+        'r' => "0,3 0,7;0,5 2,3 4,3",
+        's' => "4,3 1,3 0,4 1,5 3,5 4,6 3,7 0,7",
+        't' => "2,1 2,6 3,7 4,7;0,3 4,3",
+        'u' => "0,3 0,6 1,7 3,7 4,6;4,3 4,7",
+        'v' => "0,3 2,7 4,3",
+        'w' => "0,3 1,7 2,4.5 3,7 4,3",
+        'x' => "0,3 4,7;4,3 0,7",
+        'y' => "0,3 2,7;4,3 1,9",
+        'z' => "0,3 4,3 0,7 4,7",
+        '{' => "3,0 2,1 2,3 1,3.5 2,4 2,6 3,7",
+        '|' => "2,0 2,8",
+        '}' => "1,0 2,1 2,3 3,3.5 2,4 2,6 1,7",
+        '~' => "0,4 1,3 3,4 4,3",
+        // anything else is drawn as an empty box
+        _ => "0,0 4,0 4,7 0,7 0,0",
+    }
+}
+
+// polylines for a line of text starting at the origin on its baseline, y down
+pub fn strokes(text: &str, size: f64) -> Vec<Vec<(f64, f64)>> {
+    let unit = size / 10.0;
+    let mut lines = Vec::<Vec<(f64, f64)>>::new();
+    for (index, c) in text.chars().enumerate() {
+        let left = index as f64 * ADVANCE * size + unit;
+        for stroke in glyph(c).split(';').filter(|stroke| !stroke.is_empty()) {
+            let line = stroke
+                .split(' ')
+                .map(|point| {
+                    let (x, y) = point.split_once(',').unwrap();
+                    (
+                        left + x.parse::<f64>().unwrap() * unit,
+                        (y.parse::<f64>().unwrap() - 7.0) * unit,
+                    )
+                })
+                .collect();
+            lines.push(line);
+        }
+    }
+    lines
+}
+
+pub fn width(text: &str, size: f64) -> f64 {
+    text.chars().count() as f64 * ADVANCE * size
+}
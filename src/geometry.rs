@@ -0,0 +1,203 @@
+use crate::schematic::*;
+use std::f64;
+
+// positions closer than this (mm) are treated as the same point
+pub const EPSILON: f64 = 1e-3;
+
+impl Point {
+    pub fn new(x: f64, y: f64) -> Point {
+        Point { x, y, a: 0.0 }
+    }
+
+    pub fn same_as(&self, other: &Point) -> bool {
+        (self.x - other.x).abs() < EPSILON && (self.y - other.y).abs() < EPSILON
+    }
+
+    pub fn distance(&self, other: &Point) -> f64 {
+        (self.x - other.x).hypot(self.y - other.y)
+    }
+
+    // shortest distance to the segment a -> b
+    pub fn segment_distance(&self, a: &Point, b: &Point) -> f64 {
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let len = dx * dx + dy * dy;
+        if len < EPSILON * EPSILON {
+            return self.distance(a);
+        }
+        let t = (((self.x - a.x) * dx + (self.y - a.y) * dy) / len).clamp(0.0, 1.0);
+        self.distance(&Point::new(a.x + t * dx, a.y + t * dy))
+    }
+
+    // true if self lies on the segment a -> b (endpoints included)
+    pub fn on_segment(&self, a: &Point, b: &Point) -> bool {
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let len = (dx * dx + dy * dy).sqrt();
+        if len < EPSILON {
+            return self.same_as(a);
+        }
+        let cross = (self.x - a.x) * dy - (self.y - a.y) * dx;
+        if (cross / len).abs() > EPSILON {
+            return false;
+        }
+        let dot = (self.x - a.x) * dx + (self.y - a.y) * dy;
+        dot >= -EPSILON * len && dot <= len * len + EPSILON * len
+    }
+}
+
+impl Wire {
+    pub fn contains(&self, point: &Point) -> bool {
+        self.poss
+            .windows(2)
+            .any(|seg| point.on_segment(&seg[0], &seg[1]))
+    }
+}
+
+impl SymbolInst {
+    // maps a point from library space (y up) into sheet space,
+    // matching the canvas transform used by `SymbolInst::draw`
+    pub fn transform(&self, point: &Point) -> Point {
+        let angle = self.pos.a / 180.0 * f64::consts::PI;
+        let (sin, cos) = angle.sin_cos();
+        let x = point.x * cos - point.y * sin;
+        let y = point.x * sin + point.y * cos;
+        let (sx, sy) = (
+            if self.mirror.0 { -1.0 } else { 1.0 },
+            if self.mirror.1 { 1.0 } else { -1.0 },
+        );
+        Point {
+            x: self.pos.x + x * sx,
+            y: self.pos.y + y * sy,
+            a: point.a,
+        }
+    }
+
+    // the instance's fields, then those of the template it lacks placed on
+    // the sheet. kicad copies template fields in when placing a symbol, so
+    // these are the ki_ fields and fields added to the library since
+    pub fn fields(&self) -> Vec<Property> {
+        let mut fields = self.props.clone();
+        if let Some(parent) = &self.parent {
+            for prop in &parent.props {
+                if fields.iter().any(|field| field.key == prop.key) {
+                    continue;
+                }
+                let mut prop = prop.clone();
+                prop.pos = self.transform(&prop.pos);
+                fields.push(prop);
+            }
+        }
+        fields
+    }
+
+    // sub symbols drawn for this instance's unit (unit 0 is shared)
+    pub fn symbols(&self) -> Vec<&Symbol> {
+        match &self.parent {
+            Some(parent) => parent
+                .symbs
+                .iter()
+                .filter(|symb| symb.unit == 0 || symb.unit == self.unit)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    // pins of this instance with their connection point in sheet space
+    pub fn pins(&self) -> Vec<(&Pin, Point)> {
+        self.symbols()
+            .into_iter()
+            .flat_map(|symb| symb.pins.iter())
+            .map(|pin| (pin, self.transform(&pin.pos)))
+            .collect()
+    }
+
+    pub fn prop(&self, key: &str) -> Option<&str> {
+        self.props
+            .iter()
+            .find(|prop| prop.key == key)
+            .map(|prop| prop.value.as_str())
+    }
+}
+
+impl Page {
+    pub fn from_name(name: &str) -> Option<Page> {
+        Some(match name {
+            "A5" => Page::A5,
+            "A4" => Page::A4,
+            "A3" => Page::A3,
+            "A2" => Page::A2,
+            "A1" => Page::A1,
+            "A0" => Page::A0,
+            "A" => Page::A,
+            "B" => Page::B,
+            "C" => Page::C,
+            "D" => Page::D,
+            "E" => Page::E,
+            "USLetter" => Page::USLetter,
+            "USLegal" => Page::USLegal,
+            "USLedger" => Page::USLedger,
+            _ => return None,
+        })
+    }
+
+    // paper size in mm, (width, height)
+    pub fn size(&self, portrait: bool) -> (f64, f64) {
+        let size = match self {
+            Page::A5 => (210.0, 148.0),
+            Page::A4 => (297.0, 210.0),
+            Page::A3 => (420.0, 297.0),
+            Page::A2 => (594.0, 420.0),
+            Page::A1 => (841.0, 594.0),
+            Page::A0 => (1189.0, 841.0),
+            Page::A => (279.4, 215.9),
+            Page::B => (431.8, 279.4),
+            Page::C => (558.8, 431.8),
+            Page::D => (863.6, 558.8),
+            Page::E => (1117.6, 863.6),
+            Page::USLetter => (279.4, 215.9),
+            Page::USLegal => (355.6, 215.9),
+            Page::USLedger => (431.8, 279.4),
+            // user sizes are given as is
+            Page::User(width, height) => return (*width, *height),
+        };
+        if portrait {
+            (size.1, size.0)
+        } else {
+            size
+        }
+    }
+}
+
+impl Schematic {
+    pub fn page_size(&self) -> (f64, f64) {
+        self.page.size(self.portrait)
+    }
+}
+
+impl Sheet {
+    pub fn prop(&self, key: &str) -> Option<&str> {
+        self.props
+            .iter()
+            .find(|prop| prop.key == key)
+            .map(|prop| prop.value.as_str())
+    }
+
+    // kicad 6 writes "Sheet name", kicad 7+ "Sheetname"
+    pub fn name(&self) -> &str {
+        self.prop("Sheetname")
+            .or_else(|| self.prop("Sheet name"))
+            .unwrap_or("")
+    }
+
+    pub fn file(&self) -> &str {
+        self.prop("Sheetfile")
+            .or_else(|| self.prop("Sheet file"))
+            .unwrap_or("")
+    }
+
+    pub fn contains(&self, point: &Point) -> bool {
+        point.x >= self.pos.x
+            && point.x <= self.pos.x + self.size.0
+            && point.y >= self.pos.y
+            && point.y <= self.pos.y + self.size.1
+    }
+}
@@ -0,0 +1,132 @@
+use crate::schematic::*;
+use std::path::Path;
+
+// one sheet of a design, the root or an instance of a sheet symbol
+#[derive(Debug, Clone)]
+pub struct SheetNode {
+    pub path: String, // "/" for the root, then sheet names ("/power/usb")
+    pub name: String,
+    pub file: String,
+    pub sheet: Uuid, // sheet symbol in the parent, nil for the root
+    pub parent: Option<usize>,
+    pub schematic: Schematic,
+}
+
+// every sheet of a design in page order (depth first, like kicad)
+#[derive(Debug, Clone)]
+pub struct Hierarchy {
+    pub sheets: Vec<SheetNode>,
+}
+
+impl Hierarchy {
+    // a design made of the root sheet only
+    pub fn new(root: Schematic, file: &str) -> Hierarchy {
+        let name = Path::new(file)
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("")
+            .to_string();
+        Hierarchy {
+            sheets: vec![SheetNode {
+                path: "/".to_string(),
+                name,
+                file: file.to_string(),
+                sheet: Uuid::nil(),
+                parent: None,
+                schematic: root,
+            }],
+        }
+    }
+
+    // follows sheet symbols, `load` is given each file path (relative to the
+    // root's directory) and returns its contents, missing files and ones that
+    // don't parse are skipped
+    pub fn load(
+        root: Schematic,
+        file: &str,
+        load: &mut dyn FnMut(&str) -> Option<String>,
+    ) -> Hierarchy {
+        let mut hierarchy = Hierarchy::new(root, file);
+        hierarchy.load_children(0, load);
+        hierarchy
+    }
+
+    fn load_children(&mut self, index: usize, load: &mut dyn FnMut(&str) -> Option<String>) {
+        let dir = Path::new(&self.sheets[index].file)
+            .parent()
+            .map(|dir| dir.to_path_buf())
+            .unwrap_or_default();
+        let sheets = self.sheets[index].schematic.sheets.clone();
+        for sheet in &sheets {
+            let file = dir.join(sheet.file()).to_string_lossy().replace('\\', "/");
+            // a sheet can't contain itself
+            if self
+                .ancestors(index)
+                .any(|other| self.sheets[other].file == file)
+            {
+                continue;
+            }
+            let schematic = match load(&file).map(|text| Schematic::parse(&text)) {
+                Some(Ok(schematic)) => schematic,
+                _ => continue,
+            };
+            let path = match self.sheets[index].path.as_str() {
+                "/" => format!("/{}", sheet.name()),
+                parent => format!("{}/{}", parent, sheet.name()),
+            };
+            self.sheets.push(SheetNode {
+                path,
+                name: sheet.name().to_string(),
+                file,
+                sheet: sheet.uuid,
+                parent: Some(index),
+                schematic,
+            });
+            self.load_children(self.sheets.len() - 1, load);
+        }
+    }
+
+    // index itself, then its parent and so on up to the root
+    pub fn ancestors(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+        std::iter::successors(Some(index), move |index| self.sheets[*index].parent)
+    }
+
+    pub fn children(&self, index: usize) -> Vec<usize> {
+        (0..self.sheets.len())
+            .filter(|other| self.sheets[*other].parent == Some(index))
+            .collect()
+    }
+
+    // the sheet a sheet symbol of sheets[index] leads to
+    pub fn child(&self, index: usize, sheet: &Uuid) -> Option<usize> {
+        self.children(index)
+            .into_iter()
+            .find(|other| self.sheets[*other].sheet == *sheet)
+    }
+
+    pub fn find(&self, path: &str) -> Option<usize> {
+        let path = match path.trim_end_matches('/') {
+            "" => "/",
+            path => path,
+        };
+        self.sheets.iter().position(|node| node.path == path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn broken_sheets_are_skipped() {
+        let root = Schematic::from_str(include_str!("../test/kicad7.kicad_sch"));
+        let mut loaded = Vec::<String>::new();
+        let hierarchy = Hierarchy::load(root, "filter.kicad_sch", &mut |path| {
+            loaded.push(path.to_string());
+            Some("(kicad_sch (version 20230121)".to_string())
+        });
+        assert_eq!(loaded, vec!["buffer.kicad_sch"]);
+        assert_eq!(hierarchy.sheets.len(), 1);
+        assert_eq!(hierarchy.find("/buffer"), None);
+    }
+}
@@ -0,0 +1,164 @@
+use crate::netlist::{Netlist, Node};
+use crate::schematic::*;
+
+// what an inspector panel or tooltip shows for a symbol
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SymbolInfo {
+    pub reference: String,
+    pub value: String,
+    pub footprint: String,
+    pub datasheet: String, // empty when there's none ("~" in the file)
+    pub lib_id: String,
+    pub uuid: Uuid,
+    pub unit: i32,
+    pub fields: Vec<FieldInfo>, // every field, the template's merged in
+    pub pins: Vec<PinInfo>,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FieldInfo {
+    pub name: String,
+    pub value: String,
+    pub shown: bool,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PinInfo {
+    pub number: String,
+    pub name: String,
+    pub kind: PinType,
+    pub net: String, // empty when the pin connects to nothing
+}
+
+impl Schematic {
+    // symbs[index] for the inspector, net names come from the sheet's netlist
+    pub fn symbol_info(&self, netlist: &Netlist, index: usize) -> SymbolInfo {
+        let symb = &self.symbs[index];
+        let fields = symb.fields();
+        let field = |key: &str| {
+            fields
+                .iter()
+                .find(|field| field.key == key)
+                .map(|field| field.value.clone())
+                .unwrap_or_default()
+        };
+        let pins = symb
+            .pins()
+            .into_iter()
+            .enumerate()
+            .map(|(i, (pin, _))| {
+                let net = netlist
+                    .nodes
+                    .get(&Node::Pin(index, i))
+                    .map(|net| &netlist.nets[*net])
+                    .filter(|net| {
+                        net.pins.len() > 1 || !net.wires.is_empty() || !net.labels.is_empty()
+                    });
+                PinInfo {
+                    number: pin.numb.0.clone(),
+                    name: pin.name.0.clone(),
+                    kind: pin.kind,
+                    net: net.map(|net| net.name.clone()).unwrap_or_default(),
+                }
+            })
+            .collect();
+        let datasheet = match field("Datasheet").as_str() {
+            "~" => "".to_string(),
+            datasheet => datasheet.to_string(),
+        };
+        SymbolInfo {
+            reference: field("Reference"),
+            value: field("Value"),
+            footprint: field("Footprint"),
+            datasheet,
+            lib_id: symb.id.clone(),
+            uuid: symb.uuid,
+            unit: symb.unit,
+            fields: fields
+                .iter()
+                .map(|field| FieldInfo {
+                    name: field.key.clone(),
+                    value: field.value.clone(),
+                    shown: field.show,
+                })
+                .collect(),
+            pins,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::netlist::tests::{part, sheet, symbol, wire};
+
+    #[test]
+    fn template_fields_are_merged() {
+        let schem = Schematic::from_str(include_str!("../test/kicad7.kicad_sch"));
+        let info = schem.symbol_info(&schem.netlist(), 0);
+        assert_eq!(
+            (info.reference.as_str(), info.value.as_str()),
+            ("R1", "10k")
+        );
+        assert_eq!(info.lib_id, "Device:R");
+        // the instance's fields first and over the template's, then the
+        // template's own
+        let fields = info
+            .fields
+            .iter()
+            .map(|field| (field.name.as_str(), field.value.as_str(), field.shown))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            [
+                ("Reference", "R1", true),
+                ("Value", "10k", true),
+                ("Footprint", "Resistor_SMD:R_0603_1608Metric", false),
+                ("Datasheet", "~", false),
+                ("ki_keywords", "R res resistor", false),
+                ("ki_description", "Resistor", false),
+            ]
+        );
+        // "~" is kicad's no datasheet
+        assert_eq!(info.datasheet, "");
+        let nets = info
+            .pins
+            .iter()
+            .map(|pin| pin.net.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(nets, ["IN", "FILTERED"]);
+    }
+
+    #[test]
+    fn datasheets_and_unconnected_pins() {
+        let mut schem = sheet(
+            &[part("A", "input", false, false)],
+            &[
+                symbol("A", "U1", "x", 0.0, 0.0, 1),
+                symbol("A", "U2", "y", 10.0, 0.0, 2),
+                wire((10.0, 0.0), (20.0, 0.0), 3),
+            ],
+        );
+        schem
+            .set_property("U2", "Datasheet", "https://example.com/a.pdf")
+            .unwrap();
+        let netlist = schem.netlist();
+
+        let lone = schem.symbol_info(&netlist, 0);
+        assert_eq!(lone.datasheet, "");
+        assert_eq!(lone.footprint, "");
+        assert_eq!(lone.pins.len(), 1);
+        assert_eq!(
+            (lone.pins[0].number.as_str(), lone.pins[0].net.as_str()),
+            ("1", "")
+        );
+        assert_eq!(lone.pins[0].kind, PinType::Input);
+
+        let wired = schem.symbol_info(&netlist, 1);
+        assert_eq!(wired.datasheet, "https://example.com/a.pdf");
+        assert!(!wired.pins[0].net.is_empty());
+    }
+}
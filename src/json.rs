@@ -0,0 +1,228 @@
+// json form of the schematic model, for tools that don't want to parse
+// s-expressions (the web front-end, python scripts)
+//
+// the shape is the serde form of `Schematic` and is kept stable, fields are
+// only ever added:
+//
+//   {
+//     "uuid": "e1d888f9-47ba-4b65-8b76-ae4073eb1c03",
+//     "version": 20211123,            // kicad file format version
+//     "page": "A4",                   // or {"User": [width, height]} in mm
+//     "portrait": false,
+//     "wires": [Wire], "junctions": [Junction], "texts": [Text],
+//     "polylines": [Polyline], "labels": [Label], "symbols": [SymbolInst],
+//     "sheets": [Sheet],
+//     "lib": {"Device:R": SymbolTemp, ...}  // sorted by lib id
+//   }
+//
+// where
+//
+//   Point      {"x", "y", "angle"}, mm with y down, angle in degrees
+//   Wire       {"points": [Point], "stroke", "uuid"}
+//   Junction   {"pos", "diameter", "color", "uuid"}
+//   Text       {"text", "pos", "effect", "uuid"}
+//   Polyline   {"points": [Point], "stroke", "fill", "uuid"}
+//   Label      {"text", "kind": "Heir" | "Global" | "Local" | "Noconn",
+//               "pos", "effect", "uuid"}
+//   SymbolInst {"lib_id", "props": [Property], "pos", "mirror": [x, y],
+//               "unit", "in_bom", "uuid"}, drawn from lib[lib_id]
+//   Sheet      {"pos", "size": [w, h], "stroke", "fill", "props",
+//               "pins": [{"name", "pos", "effect", "uuid"}], "uuid"}
+//   SymbolTemp {"id", "power", "props", "pos", "units": [Symbol], "uuid"}
+//   Symbol     {"id", "unit" (0 = every unit), "lines", "arcs", "pins",
+//               "rects", "circs"}
+//   Pin        {"kind", "style", "pos", "length", "name": [text, Effect],
+//               "number": [text, Effect], "hide"}
+//   Property   {"key", "value", "id", "show", "effect", "pos"}
+//   Effect     {"font_name", "size": [w, h], "thickness", "bold", "italic",
+//               "line_spacing", "justify": [bool, bool, bool], "hide", "color"}
+//   Stroke     {"width", "format": "Default" | "Solid" | "Dash" | ..., "color"}
+//
+// uuids are strings as kicad writes them (8 hex digits for kicad 5
+// timestamps), colors are css strings ("rgb(r, g, b)" / "rgba(r, g, b, a)")
+// and null means the theme's color, enums are their variant names
+use crate::schematic::*;
+use crate::theme::Color;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
+
+impl Serialize for Uuid {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Uuid {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Uuid, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+// hash maps in a stable order, so the same file gives the same json
+pub(crate) fn sorted<S: Serializer, V: Serialize>(
+    map: &HashMap<String, V>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
+impl Schematic {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn to_json_pretty(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    // relinks symbols to their templates and rebuilds the uuid index, which
+    // aren't part of the json
+    pub fn from_json(text: &str) -> Result<Schematic, serde_json::Error> {
+        let mut schem = serde_json::from_str::<Schematic>(text)?;
+        for symb in schem.symbs.iter_mut() {
+            symb.parent = schem.lib.get(&symb.id).cloned();
+        }
+        schem.reindex();
+        Ok(schem)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    const FILE: &str = include_str!("../test/kicad7.kicad_sch");
+
+    // serde_json keeps object keys sorted
+    fn keys(value: &Value) -> Vec<&str> {
+        value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(|key| key.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn keys_are_stable() {
+        let json = Schematic::from_str(FILE).to_json();
+        let root = serde_json::from_str::<Value>(&json).unwrap();
+        assert_eq!(
+            keys(&root),
+            [
+                "junctions",
+                "labels",
+                "lib",
+                "page",
+                "polylines",
+                "portrait",
+                "sheets",
+                "symbols",
+                "texts",
+                "uuid",
+                "version",
+                "wires"
+            ]
+        );
+        assert_eq!(root["uuid"], "5b1c6f0e-2d7a-4c3b-9e84-1f0a6d2c7b93");
+        assert_eq!(root["version"], 20230121);
+        assert_eq!(root["page"], "A4");
+        assert_eq!(keys(&root["lib"]), ["Device:C", "Device:R", "power:GND"]);
+
+        let first = |key: &str| &root[key][0];
+        assert_eq!(keys(first("wires")), ["points", "stroke", "uuid"]);
+        assert_eq!(
+            keys(first("junctions")),
+            ["color", "diameter", "pos", "uuid"]
+        );
+        assert_eq!(
+            keys(first("labels")),
+            ["effect", "kind", "pos", "text", "uuid"]
+        );
+        assert_eq!(
+            keys(first("symbols")),
+            ["in_bom", "lib_id", "mirror", "pos", "props", "unit", "uuid"]
+        );
+        assert_eq!(
+            keys(first("sheets")),
+            ["fill", "pins", "pos", "props", "size", "stroke", "uuid"]
+        );
+        assert_eq!(
+            keys(&first("sheets")["pins"][0]),
+            ["effect", "name", "pos", "uuid"]
+        );
+        assert_eq!(keys(&first("wires")["points"][0]), ["angle", "x", "y"]);
+        assert_eq!(
+            keys(&first("wires")["stroke"]),
+            ["color", "format", "width"]
+        );
+        assert_eq!(
+            keys(&first("symbols")["props"][0]),
+            ["effect", "id", "key", "pos", "show", "value"]
+        );
+        assert_eq!(
+            keys(&first("labels")["effect"]),
+            [
+                "bold",
+                "color",
+                "font_name",
+                "hide",
+                "italic",
+                "justify",
+                "line_spacing",
+                "size",
+                "thickness"
+            ]
+        );
+
+        let temp = &root["lib"]["Device:R"];
+        assert_eq!(keys(temp), ["id", "pos", "power", "props", "units", "uuid"]);
+        assert_eq!(
+            keys(&temp["units"][0]),
+            ["arcs", "circs", "id", "lines", "pins", "rects", "unit"]
+        );
+        let pin = temp["units"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find_map(|unit| unit["pins"].get(0))
+            .unwrap();
+        assert_eq!(
+            keys(pin),
+            ["hide", "kind", "length", "name", "number", "pos", "style"]
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let schem = Schematic::from_str(FILE);
+        let json = schem.to_json();
+        let again = Schematic::from_json(&json).unwrap();
+        assert_eq!(again.to_json(), json);
+        assert_eq!(
+            serde_json::from_str::<Value>(&again.to_json_pretty()).unwrap(),
+            serde_json::from_str::<Value>(&json).unwrap()
+        );
+        // symbols are linked to their templates and indexed again
+        assert!(again.symbs.iter().all(|symb| symb.parent.is_some()));
+        assert_eq!(again.index, schem.index);
+        assert!(Schematic::from_json("{}").is_err());
+    }
+}
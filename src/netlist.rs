@@ -0,0 +1,443 @@
+use crate::schematic::*;
+use std::collections::HashMap;
+
+// an item that takes part in connectivity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Node {
+    Wire(usize),       // index into schematic.wires
+    Pin(usize, usize), // index into schematic.symbs, index into its pins()
+    Label(usize),      // index into schematic.labels
+}
+
+#[derive(Debug, Clone)]
+pub struct NetPin {
+    pub symb: usize,
+    pub reference: String,
+    pub pin: Pin,
+    pub pos: Point,
+}
+
+#[derive(Debug, Clone)]
+pub struct Net {
+    pub name: String,
+    pub names: Vec<String>, // every label / power name attached to the net
+    pub pins: Vec<NetPin>,
+    pub wires: Vec<usize>,
+    pub labels: Vec<usize>,
+}
+
+impl Net {
+    pub fn blank() -> Net {
+        Net {
+            name: "".to_string(),
+            names: Vec::<String>::new(),
+            pins: Vec::<NetPin>::new(),
+            wires: Vec::<usize>::new(),
+            labels: Vec::<usize>::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Netlist {
+    pub nets: Vec<Net>,
+    pub nodes: HashMap<Node, usize>, // node -> index into nets
+}
+
+// minimal union find over node indices
+struct Groups {
+    parent: Vec<usize>,
+}
+
+impl Groups {
+    fn new(size: usize) -> Groups {
+        Groups {
+            parent: (0..size).collect(),
+        }
+    }
+
+    fn find(&mut self, index: usize) -> usize {
+        let mut root = index;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut index = index;
+        while self.parent[index] != root {
+            let next = self.parent[index];
+            self.parent[index] = root;
+            index = next;
+        }
+        root
+    }
+
+    fn join(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            self.parent[b] = a;
+        }
+    }
+}
+
+// name a node gives its net, if any, with a priority (lower wins)
+fn node_name(schem: &Schematic, node: &Node) -> Option<(u8, String)> {
+    match node {
+        Node::Label(index) => {
+            let label = &schem.labels[*index];
+            match label.shape {
+                Style::Global => Some((0, label.id.clone())),
+                Style::Local => Some((2, label.id.clone())),
+                Style::Heir => Some((3, label.id.clone())),
+                Style::Noconn => None,
+            }
+        }
+        Node::Pin(symb, pin) => {
+            let inst = &schem.symbs[*symb];
+            let (pin, _) = inst.pins()[*pin];
+            let power = inst.parent.as_ref().map(|p| p.power).unwrap_or(false);
+            if power {
+                // power symbols name their net after their value
+                inst.prop("Value").map(|value| (1, value.to_string()))
+            } else if pin.hide && pin.kind == PinType::PowerIn {
+                // hidden power pins connect by name
+                Some((1, pin.name.0.clone()))
+            } else {
+                None
+            }
+        }
+        Node::Wire(_) => None,
+    }
+}
+
+impl Schematic {
+    pub fn nodes(&self) -> Vec<(Node, Point)> {
+        let mut nodes = Vec::<(Node, Point)>::new();
+        for (index, wire) in self.wires.iter().enumerate() {
+            for point in &wire.poss {
+                nodes.push((Node::Wire(index), point.clone()));
+            }
+        }
+        for (index, symb) in self.symbs.iter().enumerate() {
+            for (pin, (_, pos)) in symb.pins().into_iter().enumerate() {
+                nodes.push((Node::Pin(index, pin), pos));
+            }
+        }
+        for (index, label) in self.labels.iter().enumerate() {
+            nodes.push((Node::Label(index), label.pos.clone()));
+        }
+        nodes
+    }
+
+    // everything physically touching a point, wires included when the
+    // point lies anywhere along them
+    pub fn nodes_at(&self, point: &Point) -> Vec<Node> {
+        let mut found = Vec::<Node>::new();
+        for (index, wire) in self.wires.iter().enumerate() {
+            if wire.contains(point) {
+                found.push(Node::Wire(index));
+            }
+        }
+        for (index, symb) in self.symbs.iter().enumerate() {
+            for (pin, (_, pos)) in symb.pins().into_iter().enumerate() {
+                if pos.same_as(point) {
+                    found.push(Node::Pin(index, pin));
+                }
+            }
+        }
+        for (index, label) in self.labels.iter().enumerate() {
+            if label.pos.same_as(point) {
+                found.push(Node::Label(index));
+            }
+        }
+        found
+    }
+
+    // the net of the wire, pin or label closest to a point, if one is
+    // within tolerance (mm)
+    pub fn net_at(&self, netlist: &Netlist, point: &Point, tolerance: f64) -> Option<usize> {
+        let mut near = Vec::<(f64, Node)>::new();
+        for (index, wire) in self.wires.iter().enumerate() {
+            let distance = wire
+                .poss
+                .windows(2)
+                .map(|seg| point.segment_distance(&seg[0], &seg[1]))
+                .fold(f64::INFINITY, f64::min);
+            near.push((distance, Node::Wire(index)));
+        }
+        for (index, symb) in self.symbs.iter().enumerate() {
+            for (pin, (_, pos)) in symb.pins().into_iter().enumerate() {
+                near.push((point.distance(&pos), Node::Pin(index, pin)));
+            }
+        }
+        for (index, label) in self.labels.iter().enumerate() {
+            near.push((point.distance(&label.pos), Node::Label(index)));
+        }
+        near.into_iter()
+            .filter(|(distance, node)| *distance <= tolerance && netlist.nodes.contains_key(node))
+            .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap())
+            .map(|(_, node)| netlist.nodes[&node])
+    }
+
+    // what draws a net: its wires, the junctions on them and its labels
+    pub fn net_items(&self, net: &Net) -> Vec<ItemRef> {
+        let mut items = net
+            .wires
+            .iter()
+            .map(|wire| ItemRef::Wire(*wire))
+            .collect::<Vec<_>>();
+        for (index, junc) in self.juncs.iter().enumerate() {
+            if net
+                .wires
+                .iter()
+                .any(|wire| self.wires[*wire].contains(&junc.pos))
+            {
+                items.push(ItemRef::Junction(index));
+            }
+        }
+        items.extend(net.labels.iter().map(|label| ItemRef::Label(*label)));
+        items
+    }
+
+    pub fn netlist(&self) -> Netlist {
+        let nodes = self.nodes();
+        let mut ids = HashMap::<Node, usize>::new();
+        let mut order = Vec::<Node>::new();
+        for (node, _) in &nodes {
+            if !ids.contains_key(node) {
+                ids.insert(*node, order.len());
+                order.push(*node);
+            }
+        }
+
+        // physical connections
+        let mut groups = Groups::new(order.len());
+        let mut points = nodes.iter().map(|(_, pos)| pos.clone()).collect::<Vec<_>>();
+        points.extend(self.juncs.iter().map(|junc| junc.pos.clone()));
+        for point in &points {
+            let touching = self.nodes_at(point);
+            for node in touching.iter().skip(1) {
+                groups.join(ids[&touching[0]], ids[node]);
+            }
+        }
+
+        // connections by name
+        let mut named = HashMap::<String, usize>::new();
+        for (index, node) in order.iter().enumerate() {
+            if let Some((_, name)) = node_name(self, node) {
+                match named.get(&name) {
+                    Some(other) => groups.join(*other, index),
+                    None => {
+                        named.insert(name, index);
+                    }
+                }
+            }
+        }
+
+        // collect nets
+        let mut netlist = Netlist {
+            nets: Vec::<Net>::new(),
+            nodes: HashMap::<Node, usize>::new(),
+        };
+        let mut roots = HashMap::<usize, usize>::new();
+        let mut priorities = Vec::<Option<(u8, String)>>::new();
+        for (index, node) in order.iter().enumerate() {
+            let root = groups.find(index);
+            let net = *roots.entry(root).or_insert_with(|| {
+                netlist.nets.push(Net::blank());
+                priorities.push(None);
+                netlist.nets.len() - 1
+            });
+            netlist.nodes.insert(*node, net);
+            match node {
+                Node::Wire(wire) => netlist.nets[net].wires.push(*wire),
+                Node::Label(label) => netlist.nets[net].labels.push(*label),
+                Node::Pin(symb, pin) => {
+                    let inst = &self.symbs[*symb];
+                    let (pin, pos) = inst.pins()[*pin].clone();
+                    netlist.nets[net].pins.push(NetPin {
+                        symb: *symb,
+                        reference: inst.prop("Reference").unwrap_or("").to_string(),
+                        pin: pin.clone(),
+                        pos,
+                    });
+                }
+            }
+            if let Some(name) = node_name(self, node) {
+                if !netlist.nets[net].names.contains(&name.1) {
+                    netlist.nets[net].names.push(name.1.clone());
+                }
+                if priorities[net].as_ref().is_none_or(|best| name < *best) {
+                    priorities[net] = Some(name);
+                }
+            }
+        }
+
+        // unnamed nets take the name of their first pin
+        for (net, priority) in netlist.nets.iter_mut().zip(priorities) {
+            net.name = match priority {
+                Some((_, name)) => name,
+                None => {
+                    let mut pins = net.pins.iter().collect::<Vec<_>>();
+                    pins.sort_by(|a, b| {
+                        (&a.reference, &a.pin.numb.0).cmp(&(&b.reference, &b.pin.numb.0))
+                    });
+                    match pins.first() {
+                        Some(pin) => format!("Net-({}-Pad{})", pin.reference, pin.pin.numb.0),
+                        None => "".to_string(),
+                    }
+                }
+            };
+        }
+        netlist
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::schematic::*;
+
+    const EFFECTS: &str = "(effects (font (size 1.27 1.27)))";
+
+    // a lib symbol with one pin at its origin, named after the part
+    pub(crate) fn part(name: &str, kind: &str, power: bool, hide: bool) -> String {
+        format!(
+            "(symbol \"t:{name}\"{power} (in_bom yes) (on_board yes)
+              (property \"Reference\" \"U\" (id 0) (at 0 0 0) {effects})
+              (symbol \"{name}_1_1\"
+                (pin {kind} line (at 0 0 0) (length 0){hide}
+                  (name \"{name}\" {effects})
+                  (number \"1\" {effects}))))",
+            name = name,
+            kind = kind,
+            power = if power { " (power)" } else { "" },
+            hide = if hide { " hide" } else { "" },
+            effects = EFFECTS
+        )
+    }
+
+    fn uuid(id: u32) -> String {
+        format!("00000000-0000-4000-8000-{:012}", id)
+    }
+
+    pub(crate) fn symbol(
+        name: &str,
+        reference: &str,
+        value: &str,
+        x: f64,
+        y: f64,
+        id: u32,
+    ) -> String {
+        format!(
+            "(symbol (lib_id \"t:{}\") (at {} {} 0) (unit 1) (in_bom yes) (on_board yes)
+              (uuid {})
+              (property \"Reference\" \"{}\" (id 0) (at {} {} 0) {})
+              (property \"Value\" \"{}\" (id 1) (at {} {} 0) {}))",
+            name,
+            x,
+            y,
+            uuid(id),
+            reference,
+            x,
+            y,
+            EFFECTS,
+            value,
+            x,
+            y,
+            EFFECTS
+        )
+    }
+
+    pub(crate) fn wire(from: (f64, f64), to: (f64, f64), id: u32) -> String {
+        format!(
+            "(wire (pts (xy {} {}) (xy {} {})) (stroke (width 0) (type default) (color 0 0 0 0)) (uuid {}))",
+            from.0, from.1, to.0, to.1, uuid(id)
+        )
+    }
+
+    // `head` is label, global_label or hierarchical_label
+    pub(crate) fn label(head: &str, name: &str, x: f64, y: f64, id: u32) -> String {
+        let shape = match head {
+            "label" => "",
+            _ => " (shape input)",
+        };
+        format!(
+            "({} \"{}\"{} (at {} {} 0) {} (uuid {}))",
+            head,
+            name,
+            shape,
+            x,
+            y,
+            EFFECTS,
+            uuid(id)
+        )
+    }
+
+    pub(crate) fn sheet(lib: &[String], items: &[String]) -> Schematic {
+        Schematic::from_str(&format!(
+            "(kicad_sch (version 20211123) (generator eeschema) (uuid {}) (paper \"A4\")
+              (lib_symbols {})
+              {})",
+            uuid(0),
+            lib.join("\n"),
+            items.join("\n")
+        ))
+    }
+
+    // the name of the net wires[0] is on
+    fn wire_net(schem: &Schematic) -> String {
+        let netlist = schem.netlist();
+        netlist.nets[netlist.nodes[&super::Node::Wire(0)]]
+            .name
+            .clone()
+    }
+
+    #[test]
+    fn name_priority() {
+        let lib = vec![part("VCC", "power_in", true, true)];
+        let global = label("global_label", "G", 10.0, 0.0, 2);
+        let power = symbol("VCC", "#PWR1", "P", 20.0, 0.0, 3);
+        let local = label("label", "L", 30.0, 0.0, 4);
+        let hier = label("hierarchical_label", "H", 40.0, 0.0, 5);
+        let line = wire((0.0, 0.0), (50.0, 0.0), 1);
+
+        let all = [&line, &global, &power, &local, &hier].map(|item| item.clone());
+        let schem = sheet(&lib, &all);
+        assert_eq!(wire_net(&schem), "G");
+        let netlist = schem.netlist();
+        let mut names = netlist.nets[netlist.nodes[&super::Node::Wire(0)]]
+            .names
+            .clone();
+        names.sort();
+        assert_eq!(names, ["G", "H", "L", "P"]);
+
+        let schem = sheet(&lib, &[line.clone(), hier.clone(), local.clone(), power]);
+        assert_eq!(wire_net(&schem), "P");
+        let schem = sheet(&lib, &[line.clone(), hier.clone(), local]);
+        assert_eq!(wire_net(&schem), "L");
+        let schem = sheet(&lib, &[line, hier]);
+        assert_eq!(wire_net(&schem), "H");
+    }
+
+    #[test]
+    fn hidden_power_pins_connect_by_name() {
+        let lib = vec![part("VDD", "power_in", false, true)];
+        let items = [
+            symbol("VDD", "U1", "", 0.0, 0.0, 1),
+            symbol("VDD", "U2", "", 50.0, 50.0, 2),
+        ];
+        let netlist = sheet(&lib, &items).netlist();
+        assert_eq!(netlist.nets.len(), 1);
+        assert_eq!(netlist.nets[0].name, "VDD");
+        assert_eq!(netlist.nets[0].pins.len(), 2);
+    }
+
+    #[test]
+    fn unnamed_nets_take_their_first_pin() {
+        let lib = vec![part("R", "passive", false, false)];
+        let items = [
+            symbol("R", "R2", "", 10.0, 0.0, 1),
+            symbol("R", "R10", "", 0.0, 0.0, 2),
+            wire((0.0, 0.0), (10.0, 0.0), 3),
+        ];
+        assert_eq!(wire_net(&sheet(&lib, &items)), "Net-(R10-Pad1)");
+    }
+}
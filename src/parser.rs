@@ -1,5 +1,5 @@
 use crate::schematic::*;
-use symbolic_expressions;
+use crate::theme::Color;
 use symbolic_expressions::Sexp;
 
 fn get_name(object: &Sexp) -> &str {
@@ -9,6 +9,38 @@ fn get_name(object: &Sexp) -> &str {
     }
 }
 
+// kicad 8 writes flags as `(hide yes)` where older files have a bare `hide`
+fn get_flag(object: &Sexp) -> bool {
+    match object.list().unwrap().get(1) {
+        Some(value) => value.is_string() && value.string().unwrap() == "yes",
+        None => true,
+    }
+}
+
+// an id that isn't a uuid doesn't stop the parse, `Schematic::from_sexp`
+// reports it
+fn get_uuid(object: &Sexp) -> Uuid {
+    Uuid::from_text(object.list().unwrap()[1].string().unwrap())
+}
+
+// every `(uuid ...)` below `object` that isn't one
+fn bad_uuids(object: &Sexp, out: &mut Vec<String>) {
+    let list = match object.list() {
+        Ok(list) => list,
+        Err(_) => return,
+    };
+    let text = |index: usize| list.get(index).and_then(|item| item.string().ok());
+    if text(0).is_some_and(|head| head == "uuid") {
+        if let Some(Err(error)) = text(1).map(|id| id.parse::<Uuid>()) {
+            out.push(error.to_string());
+        }
+        return;
+    }
+    for item in list {
+        bad_uuids(item, out);
+    }
+}
+
 // generic parsers
 impl Point {
     pub fn from_sexp(obj: &Sexp) -> Point {
@@ -25,6 +57,24 @@ impl Point {
     }
 }
 
+impl Color {
+    // (color r g b a), all zero means "use the theme color"
+    pub fn from_sexp(obj: &Sexp) -> Option<Color> {
+        let rgba = obj.list().unwrap();
+        let channel = |index: usize| rgba[index].string().unwrap().parse::<u8>().unwrap();
+        let color = Color::new(
+            channel(1),
+            channel(2),
+            channel(3),
+            rgba[4].string().unwrap().parse::<f64>().unwrap(),
+        );
+        match color == Color::blank() {
+            true => None,
+            false => Some(color),
+        }
+    }
+}
+
 impl FillType {
     pub fn from_sexp(obj: &Sexp) -> FillType {
         match obj.list().unwrap()[1].list().unwrap()[1]
@@ -57,9 +107,11 @@ impl Junction {
                         .parse::<f64>()
                         .unwrap();
                 }
-                // todo color
+                (true, "color") => {
+                    junction.color = Color::from_sexp(obj);
+                }
                 (true, "uuid") => {
-                    junction.uuid = obj.list().unwrap()[1].string().unwrap().to_string();
+                    junction.uuid = get_uuid(obj);
                 }
                 // todo : stroke
                 _ => {
@@ -92,13 +144,7 @@ impl Stroke {
                     };
                 }
                 (true, "color") => {
-                    let color = obj.list().unwrap();
-                    stroke.color = (
-                        color[1].string().unwrap().parse::<u8>().unwrap(),
-                        color[2].string().unwrap().parse::<u8>().unwrap(),
-                        color[3].string().unwrap().parse::<u8>().unwrap(),
-                        color[4].string().unwrap().parse::<u8>().unwrap(),
-                    )
+                    stroke.color = Color::from_sexp(obj);
                 }
                 _ => {}
             }
@@ -123,7 +169,7 @@ impl Polyline {
                     }
                 }
                 (true, "uuid") => {
-                    poly.uuid = obj.list().unwrap()[1].string().unwrap().to_string();
+                    poly.uuid = get_uuid(obj);
                 }
                 (true, "stroke") => {
                     poly.stroke = Stroke::from_sexp(obj);
@@ -158,7 +204,7 @@ impl Arc {
                     arc.poss.2 = Point::from_sexp(obj);
                 }
                 (true, "uuid") => {
-                    arc.uuid = obj.list().unwrap()[1].string().unwrap().to_string();
+                    arc.uuid = get_uuid(obj);
                 }
                 (true, "stroke") => {
                     arc.stroke = Stroke::from_sexp(obj);
@@ -175,12 +221,73 @@ impl Arc {
     }
 }
 
+impl PinType {
+    pub fn from_name(name: &str) -> PinType {
+        match name {
+            "input" => PinType::Input,
+            "output" => PinType::Output,
+            "bidirectional" => PinType::Bidirectional,
+            "tri_state" => PinType::TriState,
+            "passive" => PinType::Passive,
+            "free" => PinType::Free,
+            "power_in" => PinType::PowerIn,
+            "power_out" => PinType::PowerOut,
+            "open_collector" => PinType::OpenCollector,
+            "open_emitter" => PinType::OpenEmitter,
+            "no_connect" => PinType::NoConnect,
+            _ => PinType::Unspecified,
+        }
+    }
+}
+
+impl PinStyle {
+    pub fn from_name(name: &str) -> PinStyle {
+        match name {
+            "inverted" => PinStyle::Inverted,
+            "clock" => PinStyle::Clock,
+            "inverted_clock" => PinStyle::InvertedClock,
+            "input_low" => PinStyle::InputLow,
+            "clock_low" => PinStyle::ClockLow,
+            "output_low" => PinStyle::OutputLow,
+            "edge_clock_high" => PinStyle::EdgeClockHigh,
+            "non_logic" => PinStyle::NonLogic,
+            _ => PinStyle::Line,
+        }
+    }
+}
+
 impl Pin {
     pub fn from_sexp(obj: &Sexp) -> Pin {
         let mut pin = Pin::blank();
-        for obj in obj.list().unwrap() {
+        for (index, obj) in obj.list().unwrap().iter().enumerate() {
             let name = get_name(obj);
             match (obj.is_list(), name) {
+                // (pin <type> <style> ...)
+                (false, _) if index == 1 => {
+                    pin.kind = PinType::from_name(name);
+                }
+                (false, _) if index == 2 => {
+                    pin.style = PinStyle::from_name(name);
+                }
+                (false, "hide") => {
+                    pin.hide = true;
+                }
+                (true, "hide") => {
+                    pin.hide = get_flag(obj);
+                }
+                (true, "name") | (true, "number") => {
+                    let list = obj.list().unwrap();
+                    let text = list[1].string().unwrap().clone();
+                    let effect = match list.get(2) {
+                        Some(effects) if effects.is_list() => Effect::from_sexp(effects),
+                        _ => Effect::blank(),
+                    };
+                    if name == "name" {
+                        pin.name = (text, effect);
+                    } else {
+                        pin.numb = (text, effect);
+                    }
+                }
                 (true, "at") => {
                     pin.pos = Point::from_sexp(obj);
                 }
@@ -217,7 +324,7 @@ impl Rect {
                     rect.fill = FillType::from_sexp(obj);
                 }
                 (true, "uuid") => {
-                    rect.uuid = obj.list().unwrap()[1].string().unwrap().to_string();
+                    rect.uuid = get_uuid(obj);
                 }
                 (true, "stroke") => {
                     rect.stroke = Stroke::from_sexp(obj);
@@ -242,16 +349,14 @@ impl Circ {
                     circ.pos = Point::from_sexp(obj);
                 }
                 (true, "radius") => {
-                    if obj.is_list() {
-                        circ.radius = obj.list().unwrap()[1]
-                            .string()
-                            .unwrap()
-                            .parse::<f64>()
-                            .unwrap();
-                    }
+                    circ.radius = obj.list().unwrap()[1]
+                        .string()
+                        .unwrap()
+                        .parse::<f64>()
+                        .unwrap();
                 }
                 (true, "uuid") => {
-                    circ.uuid = obj.list().unwrap()[1].string().unwrap().clone();
+                    circ.uuid = get_uuid(obj);
                 }
                 (true, "stroke") => {
                     circ.stroke = Stroke::from_sexp(obj);
@@ -278,11 +383,12 @@ impl Text {
                 (true, "at") => {
                     text.pos = Point::from_sexp(obj);
                 }
-                // todo color
                 (true, "uuid") => {
-                    text.uuid = obj.list().unwrap()[1].string().unwrap().to_string();
+                    text.uuid = get_uuid(obj);
+                }
+                (true, "effects") => {
+                    text.effect = Effect::from_sexp(obj);
                 }
-                // todo : effects
                 (false, _) => {
                     text.text = obj.string().unwrap().clone();
                 }
@@ -323,9 +429,18 @@ impl Effect {
                             (false, "bold") => {
                               effect.bold = true;
                             }
+                            (true, "bold") => {
+                              effect.bold = get_flag(obj);
+                            }
                             (false, "italic") => {
                               effect.italic = true;
                             }
+                            (true, "italic") => {
+                              effect.italic = get_flag(obj);
+                            }
+                            (true, "color") => {
+                              effect.color = Color::from_sexp(obj);
+                            }
                             _ => {}
                         }
                     }
@@ -336,6 +451,9 @@ impl Effect {
                 (false, "hide") => {
                     effect.hide = true;
                 }
+                (true, "hide") => {
+                    effect.hide = get_flag(obj);
+                }
                 _ => {}
             }
         }
@@ -360,7 +478,7 @@ impl Wire {
                     }
                 }
                 (true, "uuid") => {
-                    wire.uuid = obj.list().unwrap()[1].string().unwrap().to_string();
+                    wire.uuid = get_uuid(obj);
                 }
                 (true, "stroke") => {
                     wire.stroke = Stroke::from_sexp(obj);
@@ -382,6 +500,7 @@ impl Label {
         let label_name = get_name(obj);
         label.shape = match label_name {
             "hierarchical_label" => Style::Heir,
+            "global_label" => Style::Global,
             "no_connect" => Style::Noconn,
             _ => Style::Local,
         };
@@ -401,7 +520,7 @@ impl Label {
                     label.effect = Effect::from_sexp(obj);
                 }
                 (true, "uuid") => {
-                    label.uuid = obj.list().unwrap()[1].string().unwrap().to_string();
+                    label.uuid = get_uuid(obj);
                 }
                 _ => { // should be string
                      //println!("{:?}", name);
@@ -420,20 +539,21 @@ impl Property {
         let props = obj.list().unwrap();
         prop.key = props[1].string().unwrap().clone();
         prop.value = props[2].string().unwrap().clone();
-        prop.id = props[3].list().unwrap()[1]
-            .string()
-            .unwrap()
-            .parse::<i32>()
-            .unwrap();
-        if props[4].is_list() {
-            prop.pos = Point::from_sexp(&props[4]);
-        }
-        if props.len() >= 6 && props[5].is_list() {
-            // todo : effect
-            for obj in props[5].list().unwrap() {
-                if obj.is_string() && obj.string().unwrap().as_str() == "hide" {
-                    prop.show = false;
+        // kicad 8 dropped the (id n)
+        for obj in &props[3..] {
+            let name = get_name(obj);
+            match (obj.is_list(), name) {
+                (true, "id") => {
+                    prop.id = obj.list().unwrap()[1].string().unwrap().parse::<i32>().unwrap();
                 }
+                (true, "at") => {
+                    prop.pos = Point::from_sexp(obj);
+                }
+                (true, "effects") => {
+                    prop.effect = Effect::from_sexp(obj);
+                    prop.show = !prop.effect.hide;
+                }
+                _ => {}
             }
         }
         //
@@ -449,6 +569,13 @@ impl Symbol {
             match (obj.is_list(), name) {
                 (false, _) => {
                     symb.id = obj.string().unwrap().clone();
+                    // sub symbols are named "<name>_<unit>_<style>"
+                    symb.unit = symb
+                        .id
+                        .rsplit('_')
+                        .nth(1)
+                        .and_then(|unit| unit.parse::<i32>().ok())
+                        .unwrap_or(0);
                 }
                 (true, "polyline") => {
                     symb.lines.push(Polyline::from_sexp(obj));
@@ -494,11 +621,18 @@ impl SymbolInst {
                     symb.props.push(Property::from_sexp(obj));
                 }
                 (true, "uuid") => {
-                    symb.uuid = obj.list().unwrap()[1].string().unwrap().to_string();
+                    symb.uuid = get_uuid(obj);
                 }
                 (true, "at") => {
                     symb.pos = Point::from_sexp(obj);
                 }
+                (true, "unit") => {
+                    symb.unit = obj.list().unwrap()[1]
+                        .string()
+                        .unwrap()
+                        .parse::<i32>()
+                        .unwrap();
+                }
                 (true, "mirror") => {
                     symb.mirror = match obj.list().unwrap()[1].string().unwrap().as_str() {
                         "x" => (false, true),
@@ -507,10 +641,12 @@ impl SymbolInst {
                         _ => (false, false),
                     }
                 }
+                (true, "in_bom") => {
+                    symb.in_bom = obj.list().unwrap()[1].string().unwrap() == "yes";
+                }
                 // todo : (power)
                 // todo : pin_names
                 // todo : offset
-                // todo : in_bom
                 // todo : on_board
                 _ => {
                     //println!("{:?}", name);
@@ -539,9 +675,11 @@ impl SymbolTemp {
                     symb.symbs.push(Symbol::from_sexp(obj));
                 }
                 (true, "uuid") => {
-                    symb.uuid = obj.list().unwrap()[1].string().unwrap().to_string();
+                    symb.uuid = get_uuid(obj);
+                }
+                (true, "power") => {
+                    symb.power = true;
                 }
-                // todo : (power)
                 // todo : pin_names
                 // todo : offset
                 // todo : in_bom
@@ -556,25 +694,118 @@ impl SymbolTemp {
     }
 }
 
+impl SheetPin {
+    pub fn from_sexp(obj: &Sexp) -> SheetPin {
+        let mut pin = SheetPin::blank();
+        //
+        for obj in obj.list().unwrap().iter().skip(1) {
+            let name = get_name(obj);
+            match (obj.is_list(), name) {
+                (false, _) if pin.name.is_empty() => {
+                    // name first, then the shape (input, output...)
+                    pin.name = obj.string().unwrap().clone();
+                }
+                (true, "at") => {
+                    pin.pos = Point::from_sexp(obj);
+                }
+                (true, "effects") => {
+                    pin.effect = Effect::from_sexp(obj);
+                }
+                (true, "uuid") => {
+                    pin.uuid = get_uuid(obj);
+                }
+                _ => {}
+            }
+        }
+        //
+        pin
+    }
+}
+
+impl Sheet {
+    pub fn from_sexp(obj: &Sexp) -> Sheet {
+        let mut sheet = Sheet::blank();
+        //
+        for obj in obj.list().unwrap() {
+            let name = get_name(obj);
+            match (obj.is_list(), name) {
+                (true, "at") => {
+                    sheet.pos = Point::from_sexp(obj);
+                }
+                (true, "size") => {
+                    let size = Point::from_sexp(obj);
+                    sheet.size = (size.x, size.y);
+                }
+                (true, "stroke") => {
+                    sheet.stroke = Stroke::from_sexp(obj);
+                }
+                (true, "fill") => {
+                    for obj in obj.list().unwrap() {
+                        if let (true, "color") = (obj.is_list(), get_name(obj)) {
+                            sheet.fill = Color::from_sexp(obj);
+                        }
+                    }
+                }
+                (true, "uuid") => {
+                    sheet.uuid = get_uuid(obj);
+                }
+                (true, "property") => {
+                    sheet.props.push(Property::from_sexp(obj));
+                }
+                (true, "pin") => {
+                    sheet.pins.push(SheetPin::from_sexp(obj));
+                }
+                _ => {}
+            }
+        }
+        //
+        sheet
+    }
+}
+
 impl Schematic {
     pub fn from_sexp(obj: &Sexp) -> Schematic {
+        Schematic::try_from_sexp(obj).unwrap()
+    }
+
+    // fails on a root that isn't `kicad_sch` and on symbols missing from
+    // lib_symbols, other malformed items still panic
+    pub fn try_from_sexp(obj: &Sexp) -> Result<Schematic, ParseSchematicError> {
         let mut schem = Schematic::blank();
+        let list = match (obj.is_list(), obj.list()) {
+            (true, Ok(list)) if get_name(obj) == "kicad_sch" => list,
+            _ => return Err(ParseSchematicError("not a kicad_sch file".to_string())),
+        };
 
         // Lets Parse!
-        for obj in obj.list().unwrap() {
+        for obj in list {
             //
             let name = get_name(obj);
             match (obj.is_list(), name) {
-                (false, "version") => schem.version = obj.string().unwrap().parse::<i32>().unwrap(),
+                (true, "version") => {
+                    schem.version = obj.list().unwrap()[1].string().unwrap().parse::<i32>().unwrap();
+                }
+                (true, "uuid") => {
+                    schem.uuid = get_uuid(obj);
+                }
+                (true, "paper") => {
+                    let paper = obj.list().unwrap();
+                    let size = paper[1].string().unwrap();
+                    schem.page = match Page::from_name(size) {
+                        Some(page) => page,
+                        None => Page::User(
+                            paper[2].string().unwrap().parse::<f64>().unwrap(),
+                            paper[3].string().unwrap().parse::<f64>().unwrap(),
+                        ),
+                    };
+                    schem.portrait = paper.iter().any(|obj| obj.is_string() && obj.string().unwrap() == "portrait");
+                }
+                (true, "sheet") => schem.sheets.push(Sheet::from_sexp(obj)),
                 (true, "lib_symbols") => {
                     for obj in obj.list().unwrap() {
-                        let name = get_name(obj);
-                        match (obj.is_list(), name) {
-                            (true, "symbol") => {
-                                let symb = SymbolTemp::from_sexp(obj);
-                                schem.lib.insert(symb.id.clone(), symb);
-                            }
-                            _ => {}
+                        if let (true, "symbol") = (obj.is_list(), get_name(obj)) {
+                            let symb = SymbolTemp::from_sexp(obj);
+                            schem.lib.insert(symb.id.clone(), symb);
                         }
                     }
                 }
@@ -582,16 +813,69 @@ impl Schematic {
                 (true, "junction") => schem.juncs.push(Junction::from_sexp(obj)),
                 (true, "text") => schem.texts.push(Text::from_sexp(obj)),
                 (true, "polyline") => schem.polys.push(Polyline::from_sexp(obj)),
-                (true, "hierarchical_label") | (true, "no_connect") => schem.labels.push(Label::from_sexp(obj)),
+                (true, "hierarchical_label")
+                | (true, "global_label")
+                | (true, "label")
+                | (true, "no_connect") => schem.labels.push(Label::from_sexp(obj)),
                 (true, "symbol") => {
                     let mut symb = SymbolInst::from_sexp(obj);
-                    symb.parent = Some(schem.lib.get(&symb.id).unwrap().clone());
+                    let parent = schem.lib.get(&symb.id).ok_or_else(|| {
+                        ParseSchematicError(format!(
+                            "symbol {} uses {:?}, which is missing from lib_symbols",
+                            symb.prop("Reference").unwrap_or("?"),
+                            symb.id
+                        ))
+                    })?;
+                    symb.parent = Some(parent.clone());
                     schem.symbs.push(symb);
                 }
                 _ => {}
             }
         }
+        bad_uuids(obj, &mut schem.warnings);
+        schem.reindex();
         //
-        schem
+        Ok(schem)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = include_str!("../test/kicad7.kicad_sch");
+
+    #[test]
+    fn parses() {
+        let schem = Schematic::parse(FILE).unwrap();
+        assert_eq!(schem.symbs.len(), 3);
+        assert!(schem.symbs.iter().all(|symb| symb.parent.is_some()));
+        assert!(schem.source.is_some());
+    }
+
+    #[test]
+    fn missing_lib_symbol() {
+        let file = FILE.replace("(lib_id \"Device:R\")", "(lib_id \"Device:R_Small\")");
+        assert_eq!(
+            Schematic::parse(&file).unwrap_err().to_string(),
+            "invalid schematic: symbol R1 uses \"Device:R_Small\", which is missing from lib_symbols"
+        );
+    }
+
+    #[test]
+    fn truncated_file() {
+        let end = FILE.find("(symbol (lib_id \"Device:C\")").unwrap();
+        assert_eq!(
+            Schematic::parse(&FILE[..end]).unwrap_err().to_string(),
+            format!("invalid schematic: malformed s-expression at byte {}", end)
+        );
+        assert!(Schematic::parse("").is_err());
+    }
+
+    #[test]
+    fn not_a_schematic() {
+        let error = Schematic::parse("(kicad_pcb (version 20221018))").unwrap_err();
+        assert_eq!(error.to_string(), "invalid schematic: not a kicad_sch file");
+        assert!(Schematic::parse("kicad_sch").is_err());
     }
 }
@@ -0,0 +1,560 @@
+use crate::hierarchy::Hierarchy;
+use crate::renderer::*;
+use crate::schematic::*;
+use crate::theme::{Color, Theme};
+use std::f64;
+use std::fmt::Write;
+
+// pdf user space is 1/72 inch
+const POINTS_PER_MM: f64 = 72.0 / 25.4;
+
+// the standard 14 courier faces need no embedding and match the canvas'
+// monospace, indexed by bold + italic * 2
+const FONTS: [&str; 4] = [
+    "Courier",
+    "Courier-Bold",
+    "Courier-Oblique",
+    "Courier-BoldOblique",
+];
+
+fn num(value: f64) -> String {
+    let text = format!("{:.4}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    match text {
+        "-0" | "" => "0".to_string(),
+        _ => text.to_string(),
+    }
+}
+
+// pdf has no alpha without extra graphics states, so colors are drawn opaque
+fn color(color: &Color) -> String {
+    format!(
+        "{} {} {}",
+        num(color.r as f64 / 255.0),
+        num(color.g as f64 / 255.0),
+        num(color.b as f64 / 255.0)
+    )
+}
+
+// literal string in the font's WinAnsiEncoding, unknown characters become "?"
+fn literal(text: &str) -> String {
+    let mut out = String::from("(");
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            ' '..='~' => out.push(c),
+            '\u{a0}'..='\u{ff}' => {
+                let _ = write!(out, "\\{:03o}", c as u32);
+            }
+            _ => out.push('?'),
+        }
+    }
+    out.push(')');
+    out
+}
+
+// text string outside of content streams (bookmarks, info), utf-16 when needed
+fn text_string(text: &str) -> String {
+    if text.chars().all(|c| (' '..='~').contains(&c)) {
+        return literal(text);
+    }
+    let mut out = String::from("<FEFF");
+    for unit in text.encode_utf16() {
+        let _ = write!(out, "{:04X}", unit);
+    }
+    out.push('>');
+    out
+}
+
+// writes a page's content stream, in mm with y down like the other backends
+pub struct PdfRenderer {
+    pub out: String,
+    path: String,
+    style: Paint,
+}
+
+impl PdfRenderer {
+    pub fn new(height: f64) -> PdfRenderer {
+        let mut out = String::new();
+        // flip to y down mm, round caps and joins
+        let _ = writeln!(
+            out,
+            "{} 0 0 {} 0 {} cm 1 J 1 j",
+            num(POINTS_PER_MM),
+            num(-POINTS_PER_MM),
+            num(height * POINTS_PER_MM)
+        );
+        PdfRenderer {
+            out,
+            path: String::new(),
+            style: Paint::blank(),
+        }
+    }
+
+    fn point(&mut self, x: f64, y: f64) {
+        let command = if self.path.is_empty() { "m" } else { "l" };
+        let _ = writeln!(self.path, "{} {} {}", num(x), num(y), command);
+    }
+}
+
+impl Renderer for PdfRenderer {
+    fn begin_path(&mut self) {
+        self.path.clear();
+    }
+
+    fn move_to(&mut self, x: f64, y: f64) {
+        let _ = writeln!(self.path, "{} {} m", num(x), num(y));
+    }
+
+    fn line_to(&mut self, x: f64, y: f64) {
+        self.point(x, y);
+    }
+
+    fn arc(&mut self, x: f64, y: f64, radius: f64, start: f64, end: f64) {
+        let (start, curves) = arc_beziers(x, y, radius, start, end);
+        self.point(start.0, start.1);
+        for curve in curves {
+            let curve = curve.iter().map(|value| num(*value)).collect::<Vec<_>>();
+            let _ = writeln!(self.path, "{} c", curve.join(" "));
+        }
+    }
+
+    fn rect(&mut self, x: f64, y: f64, width: f64, height: f64) {
+        let _ = writeln!(
+            self.path,
+            "{} {} {} {} re",
+            num(x),
+            num(y),
+            num(width),
+            num(height)
+        );
+    }
+
+    fn close_path(&mut self) {
+        self.path.push_str("h\n");
+    }
+
+    fn fill(&mut self) {
+        if self.path.is_empty() {
+            return;
+        }
+        let _ = write!(self.out, "{} rg\n{}f\n", color(&self.style.fill), self.path);
+    }
+
+    fn stroke(&mut self) {
+        if self.path.is_empty() {
+            return;
+        }
+        let dash = self
+            .style
+            .dash
+            .iter()
+            .map(|len| num(*len))
+            .collect::<Vec<_>>();
+        let _ = write!(
+            self.out,
+            "{} RG {} w [{}] 0 d\n{}S\n",
+            color(&self.style.stroke),
+            num(self.style.width),
+            dash.join(" "),
+            self.path
+        );
+    }
+
+    fn text(&mut self, text: &str, x: f64, y: f64) {
+        let style = &self.style;
+        let (x, y) = text_origin(style, text, x, y);
+        let font = style.bold as usize + style.italic as usize * 2 + 1;
+        // text is set upright again, against the page's y flip
+        let _ = writeln!(
+            self.out,
+            "{} rg BT /F{} {} Tf 1 0 0 -1 {} {} Tm {} Tj ET",
+            color(&style.fill),
+            font,
+            num(style.font_size),
+            num(x),
+            num(y),
+            literal(text)
+        );
+    }
+
+    fn push_transform(&mut self, t: &Transform) {
+        let _ = writeln!(
+            self.out,
+            "q {} {} {} {} {} {} cm",
+            num(t.a),
+            num(t.b),
+            num(t.c),
+            num(t.d),
+            num(t.e),
+            num(t.f)
+        );
+    }
+
+    fn pop_transform(&mut self) {
+        self.out.push_str("Q\n");
+    }
+
+    fn set_style(&mut self, style: &Paint) {
+        self.style = style.clone();
+    }
+}
+
+// numbered objects, written out with their cross reference table
+struct PdfFile {
+    objects: Vec<String>,
+}
+
+impl PdfFile {
+    fn new() -> PdfFile {
+        PdfFile {
+            objects: Vec::<String>::new(),
+        }
+    }
+
+    // reserves an object number, filled in later by `set`
+    fn alloc(&mut self) -> usize {
+        self.objects.push(String::new());
+        self.objects.len()
+    }
+
+    fn set(&mut self, id: usize, body: String) {
+        self.objects[id - 1] = body;
+    }
+
+    fn stream(&mut self, content: &str) -> usize {
+        let id = self.alloc();
+        self.set(
+            id,
+            format!(
+                "<< /Length {} >>\nstream\n{}endstream",
+                content.len(),
+                content
+            ),
+        );
+        id
+    }
+
+    fn finish(self, root: usize, info: usize) -> Vec<u8> {
+        let mut out = String::from("%PDF-1.4\n");
+        let mut offsets = Vec::<usize>::new();
+        // every byte is ascii, so string and byte offsets agree
+        for (index, body) in self.objects.iter().enumerate() {
+            offsets.push(out.len());
+            let _ = write!(out, "{} 0 obj\n{}\nendobj\n", index + 1, body);
+        }
+        let xref = out.len();
+        let _ = write!(
+            out,
+            "xref\n0 {}\n0000000000 65535 f \n",
+            self.objects.len() + 1
+        );
+        for offset in offsets {
+            let _ = writeln!(out, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(
+            out,
+            "trailer\n<< /Size {} /Root {} 0 R /Info {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            self.objects.len() + 1,
+            root,
+            info,
+            xref
+        );
+        out.into_bytes()
+    }
+}
+
+impl Hierarchy {
+    // one vector page per sheet at its paper size, with a bookmark per sheet
+    // and links from sheet symbols to the page they lead to
+    pub fn to_pdf(&self, theme: &Theme) -> Vec<u8> {
+        let mut file = PdfFile::new();
+        let catalog = file.alloc();
+        let pages = file.alloc();
+        let outlines = file.alloc();
+        let info = file.alloc();
+
+        let mut fonts = String::new();
+        for (index, name) in FONTS.iter().enumerate() {
+            let font = file.alloc();
+            file.set(
+                font,
+                format!(
+                    "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+                    name
+                ),
+            );
+            let _ = write!(fonts, "/F{} {} 0 R ", index + 1, font);
+        }
+
+        // page objects first so links can point forward
+        let page_ids = self.sheets.iter().map(|_| file.alloc()).collect::<Vec<_>>();
+        for (index, node) in self.sheets.iter().enumerate() {
+            let schem = &node.schematic;
+            let (width, height) = schem.page_size();
+
+            let mut r = PdfRenderer::new(height);
+            r.set_style(&Paint::fill(&theme.background));
+            r.begin_path();
+            r.rect(0.0, 0.0, width, height);
+            r.fill();
+            schem.render_frame(&mut r, theme);
+            schem.render(&mut r, theme, &[]);
+            let content = file.stream(&r.out);
+
+            let mut annots = Vec::<String>::new();
+            for sheet in &schem.sheets {
+                let target = match self.child(index, &sheet.uuid) {
+                    Some(target) => target,
+                    None => continue,
+                };
+                let link = file.alloc();
+                file.set(
+                    link,
+                    format!(
+                        "<< /Type /Annot /Subtype /Link /Rect [{} {} {} {}] /Border [0 0 0] /Dest [{} 0 R /Fit] >>",
+                        num(sheet.pos.x * POINTS_PER_MM),
+                        num((height - sheet.pos.y - sheet.size.1) * POINTS_PER_MM),
+                        num((sheet.pos.x + sheet.size.0) * POINTS_PER_MM),
+                        num((height - sheet.pos.y) * POINTS_PER_MM),
+                        page_ids[target]
+                    ),
+                );
+                annots.push(format!("{} 0 R", link));
+            }
+
+            file.set(
+                page_ids[index],
+                format!(
+                    "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] /Resources << /Font << {}>> >> /Contents {} 0 R /Annots [{}] >>",
+                    pages,
+                    num(width * POINTS_PER_MM),
+                    num(height * POINTS_PER_MM),
+                    fonts,
+                    content,
+                    annots.join(" ")
+                ),
+            );
+        }
+
+        let kids = page_ids
+            .iter()
+            .map(|id| format!("{} 0 R", id))
+            .collect::<Vec<_>>();
+        file.set(
+            pages,
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                kids.join(" "),
+                kids.len()
+            ),
+        );
+
+        // bookmarks mirror the sheet tree
+        let items = self.sheets.iter().map(|_| file.alloc()).collect::<Vec<_>>();
+        for (index, node) in self.sheets.iter().enumerate() {
+            let parent = match node.parent {
+                Some(parent) => items[parent],
+                None => outlines,
+            };
+            let siblings = match node.parent {
+                Some(parent) => self.children(parent),
+                None => vec![index],
+            };
+            let position = siblings.iter().position(|other| *other == index).unwrap();
+            let title = if node.name.is_empty() {
+                node.path.as_str()
+            } else {
+                node.name.as_str()
+            };
+            let mut body = format!(
+                "<< /Title {} /Parent {} 0 R /Dest [{} 0 R /Fit]",
+                text_string(title),
+                parent,
+                page_ids[index]
+            );
+            if position > 0 {
+                let _ = write!(body, " /Prev {} 0 R", items[siblings[position - 1]]);
+            }
+            if position + 1 < siblings.len() {
+                let _ = write!(body, " /Next {} 0 R", items[siblings[position + 1]]);
+            }
+            let children = self.children(index);
+            if let (Some(first), Some(last)) = (children.first(), children.last()) {
+                // open, showing every descendant
+                let count = self
+                    .sheets
+                    .iter()
+                    .enumerate()
+                    .filter(|(other, _)| {
+                        *other != index && self.ancestors(*other).any(|up| up == index)
+                    })
+                    .count();
+                let _ = write!(
+                    body,
+                    " /First {} 0 R /Last {} 0 R /Count {}",
+                    items[*first], items[*last], count
+                );
+            }
+            body.push_str(" >>");
+            file.set(items[index], body);
+        }
+        file.set(
+            outlines,
+            format!(
+                "<< /Type /Outlines /First {} 0 R /Last {} 0 R /Count {} >>",
+                items[0],
+                items[0],
+                items.len()
+            ),
+        );
+
+        file.set(
+            catalog,
+            format!(
+                "<< /Type /Catalog /Pages {} 0 R /Outlines {} 0 R /PageMode /UseOutlines >>",
+                pages, outlines
+            ),
+        );
+        file.set(
+            info,
+            format!(
+                "<< /Title {} /Producer (kiwi) >>",
+                text_string(&self.sheets[0].name)
+            ),
+        );
+        file.finish(catalog, info)
+    }
+}
+
+impl Schematic {
+    // single page pdf of this sheet alone
+    pub fn to_pdf(&self, theme: &Theme) -> Vec<u8> {
+        Hierarchy::new(self.clone(), "").to_pdf(theme)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a root with a sheet symbol leading to buffer.kicad_sch, on A5 paper
+    fn design() -> Hierarchy {
+        let root = Schematic::from_str(include_str!("../test/kicad7.kicad_sch"));
+        Hierarchy::load(root, "filter.kicad_sch", &mut |path| match path {
+            "buffer.kicad_sch" => Some(
+                "(kicad_sch (version 20230121) (generator eeschema)
+                  (uuid 5b1c6f0e-2d7a-4c3b-9e84-1f0a6d2c7b93) (paper \"A5\"))"
+                    .to_string(),
+            ),
+            _ => None,
+        })
+    }
+
+    // the body of every object, found through the cross reference table
+    fn objects(pdf: &str) -> Vec<&str> {
+        let start = pdf.rfind("startxref\n").unwrap() + "startxref\n".len();
+        let xref = pdf[start..]
+            .lines()
+            .next()
+            .unwrap()
+            .parse::<usize>()
+            .unwrap();
+        assert!(pdf[xref..].starts_with("xref\n0 "));
+        let mut lines = pdf[xref..].lines().skip(1);
+        let count = lines.next().unwrap()[2..].parse::<usize>().unwrap();
+        assert_eq!(lines.next(), Some("0000000000 65535 f "));
+        let mut objects = Vec::<&str>::new();
+        for id in 1..count {
+            let entry = lines.next().unwrap();
+            assert_eq!(entry.len(), 19);
+            assert!(entry.ends_with(" 00000 n "));
+            let offset = entry[..10].parse::<usize>().unwrap();
+            let head = format!("{} 0 obj\n", id);
+            assert!(pdf[offset..].starts_with(&head), "object {}", id);
+            let body = &pdf[offset + head.len()..];
+            objects.push(&body[..body.find("\nendobj\n").unwrap()]);
+        }
+        assert_eq!(lines.next(), Some("trailer"));
+        assert!(pdf.ends_with("%%EOF\n"));
+        objects
+    }
+
+    // the object numbers in a `[1 0 R 2 0 R]` style array after `key`
+    fn refs(body: &str, key: &str) -> Vec<usize> {
+        let start = body.find(key).unwrap() + key.len();
+        let end = start + body[start..].find(']').unwrap();
+        body[start..end]
+            .trim_start_matches(" [")
+            .split(" 0 R")
+            .filter_map(|id| id.trim().parse::<usize>().ok())
+            .collect()
+    }
+
+    #[test]
+    fn two_sheets() {
+        let design = design();
+        assert_eq!(design.sheets.len(), 2);
+        let pdf = String::from_utf8(design.to_pdf(&Theme::kicad_default())).unwrap();
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        let objects = objects(&pdf);
+
+        let pages = objects
+            .iter()
+            .find(|body| body.starts_with("<< /Type /Pages "))
+            .unwrap();
+        assert!(pages.contains("/Count 2"));
+        let kids = refs(pages, "/Kids");
+        assert_eq!(kids.len(), 2);
+        let page = |index: usize| objects[kids[index] - 1];
+        assert!(page(0).starts_with("<< /Type /Page /Parent"));
+        assert!(page(0).contains("/MediaBox [0 0 841.8898 595.2756]"));
+        assert!(page(1).contains("/MediaBox [0 0 595.2756 419.5276]"));
+
+        // the sheet symbol on the root page links to the child page
+        let links = refs(page(0), "/Annots");
+        assert_eq!(links.len(), 1);
+        let link = objects[links[0] - 1];
+        assert!(link.starts_with("<< /Type /Annot /Subtype /Link /Rect [396 "));
+        assert_eq!(refs(link, "/Dest"), vec![kids[1]]);
+        assert!(refs(page(1), "/Annots").is_empty());
+
+        let outline = objects
+            .iter()
+            .find(|body| body.starts_with("<< /Type /Outlines "))
+            .unwrap();
+        assert!(outline.contains("/Count 2"));
+        let items = objects
+            .iter()
+            .filter(|body| body.starts_with("<< /Title ") && body.contains("/Dest"))
+            .collect::<Vec<_>>();
+        assert_eq!(items.len(), 2);
+        assert!(items[0].starts_with("<< /Title (filter) "));
+        assert_eq!(refs(items[0], "/Dest"), vec![kids[0]]);
+        assert!(items[0].contains(" /Count 1"));
+        assert!(items[1].starts_with("<< /Title (buffer) "));
+        assert_eq!(refs(items[1], "/Dest"), vec![kids[1]]);
+    }
+
+    #[test]
+    fn single_sheet() {
+        let schem = Schematic::from_str(include_str!("../test/example.kicad_sch"));
+        let pdf = String::from_utf8(schem.to_pdf(&Theme::kicad_default())).unwrap();
+        let objects = objects(&pdf);
+        assert_eq!(
+            objects
+                .iter()
+                .filter(|body| body.starts_with("<< /Type /Page "))
+                .count(),
+            1
+        );
+        let outline = objects
+            .iter()
+            .find(|body| body.starts_with("<< /Type /Outlines "))
+            .unwrap();
+        assert!(outline.contains("/Count 1"));
+    }
+}
@@ -0,0 +1,308 @@
+use crate::font;
+use crate::renderer::*;
+use crate::schematic::*;
+use crate::theme::{Color, Theme};
+use std::fmt;
+
+// part of a sheet to rasterize
+#[derive(Debug, Clone)]
+pub enum Region {
+    Page,
+    Area(Point, Point),
+    Symbol(String), // by reference
+    Net(String),
+}
+
+// space left around symbols and nets when cropping to them, mm
+const REGION_MARGIN: f64 = 2.54;
+
+// largest png drawn, 800 MB of pixmap: an a4 page at 1200 dpi fits
+pub const MAX_PIXELS: u64 = 200_000_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PngError {
+    Region,         // names nothing on the sheet
+    Size(u64, u64), // width and height, more than MAX_PIXELS
+    Encode(String),
+}
+
+impl fmt::Display for PngError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PngError::Region => write!(f, "region not found on the sheet"),
+            PngError::Size(width, height) => {
+                write!(f, "image too large: {}x{} pixels", width, height)
+            }
+            PngError::Encode(error) => write!(f, "png encoding failed: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for PngError {}
+
+// anti-aliased drawing into a pixmap, the base transform maps mm to pixels
+pub struct RasterRenderer {
+    pub pixmap: tiny_skia::Pixmap,
+    current: Transform,
+    stack: Vec<Transform>,
+    path: tiny_skia::PathBuilder,
+    style: Paint,
+}
+
+fn paint(color: &Color) -> tiny_skia::Paint<'static> {
+    let mut paint = tiny_skia::Paint::default();
+    paint.set_color_rgba8(color.r, color.g, color.b, (color.a * 255.0).round() as u8);
+    paint.anti_alias = true;
+    paint
+}
+
+impl RasterRenderer {
+    // none if the pixmap can't be allocated
+    pub fn new(width: u32, height: u32, base: &Transform) -> Option<RasterRenderer> {
+        Some(RasterRenderer {
+            pixmap: tiny_skia::Pixmap::new(width.max(1), height.max(1))?,
+            current: *base,
+            stack: Vec::<Transform>::new(),
+            path: tiny_skia::PathBuilder::new(),
+            style: Paint::blank(),
+        })
+    }
+
+    fn transform(&self) -> tiny_skia::Transform {
+        let t = &self.current;
+        tiny_skia::Transform::from_row(
+            t.a as f32, t.b as f32, t.c as f32, t.d as f32, t.e as f32, t.f as f32,
+        )
+    }
+
+    fn stroke_path(&mut self, path: &tiny_skia::Path, width: f64, dash: &[f64]) {
+        let stroke = tiny_skia::Stroke {
+            width: width as f32,
+            line_cap: tiny_skia::LineCap::Round,
+            line_join: tiny_skia::LineJoin::Round,
+            dash: tiny_skia::StrokeDash::new(dash.iter().map(|len| *len as f32).collect(), 0.0),
+            ..tiny_skia::Stroke::default()
+        };
+        let transform = self.transform();
+        let paint = paint(&self.style.stroke);
+        self.pixmap
+            .stroke_path(path, &paint, &stroke, transform, None);
+    }
+}
+
+impl Renderer for RasterRenderer {
+    fn begin_path(&mut self) {
+        self.path.clear();
+    }
+
+    fn move_to(&mut self, x: f64, y: f64) {
+        self.path.move_to(x as f32, y as f32);
+    }
+
+    fn line_to(&mut self, x: f64, y: f64) {
+        if self.path.is_empty() {
+            self.path.move_to(x as f32, y as f32);
+        } else {
+            self.path.line_to(x as f32, y as f32);
+        }
+    }
+
+    fn arc(&mut self, x: f64, y: f64, radius: f64, start: f64, end: f64) {
+        let (start, curves) = arc_beziers(x, y, radius, start, end);
+        self.line_to(start.0, start.1);
+        for c in curves {
+            self.path.cubic_to(
+                c[0] as f32,
+                c[1] as f32,
+                c[2] as f32,
+                c[3] as f32,
+                c[4] as f32,
+                c[5] as f32,
+            );
+        }
+    }
+
+    fn rect(&mut self, x: f64, y: f64, width: f64, height: f64) {
+        self.path.move_to(x as f32, y as f32);
+        self.path.line_to((x + width) as f32, y as f32);
+        self.path.line_to((x + width) as f32, (y + height) as f32);
+        self.path.line_to(x as f32, (y + height) as f32);
+        self.path.close();
+    }
+
+    fn close_path(&mut self) {
+        self.path.close();
+    }
+
+    fn fill(&mut self) {
+        if let Some(path) = self.path.clone().finish() {
+            let transform = self.transform();
+            let paint = paint(&self.style.fill);
+            self.pixmap
+                .fill_path(&path, &paint, tiny_skia::FillRule::Winding, transform, None);
+        }
+    }
+
+    fn stroke(&mut self) {
+        if let Some(path) = self.path.clone().finish() {
+            let style = self.style.clone();
+            self.stroke_path(&path, style.width, &style.dash);
+        }
+    }
+
+    // drawn with the built-in stroke font
+    fn text(&mut self, text: &str, x: f64, y: f64) {
+        let size = self.style.font_size;
+        let (x, y) = text_origin(&self.style, text, x, y);
+        let slant = if self.style.italic { 0.2 } else { 0.0 };
+        let mut path = tiny_skia::PathBuilder::new();
+        for line in font::strokes(text, size) {
+            for (index, (px, py)) in line.into_iter().enumerate() {
+                let (px, py) = ((x + px - py * slant) as f32, (y + py) as f32);
+                if index == 0 {
+                    path.move_to(px, py);
+                } else {
+                    path.line_to(px, py);
+                }
+            }
+        }
+        // glyphs are stroked in the fill color, like filled text would be
+        if let Some(path) = path.finish() {
+            let width = size * if self.style.bold { 0.16 } else { 0.1 };
+            let stroke = self.style.stroke.clone();
+            self.style.stroke = self.style.fill.clone();
+            self.stroke_path(&path, width, &[]);
+            self.style.stroke = stroke;
+        }
+    }
+
+    fn push_transform(&mut self, transform: &Transform) {
+        self.stack.push(self.current);
+        self.current = self.current.then(transform);
+    }
+
+    fn pop_transform(&mut self) {
+        if let Some(transform) = self.stack.pop() {
+            self.current = transform;
+        }
+    }
+
+    fn set_style(&mut self, style: &Paint) {
+        self.style = style.clone();
+    }
+}
+
+fn union(a: Option<(Point, Point)>, b: Option<(Point, Point)>) -> Option<(Point, Point)> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some((
+            Point::new(a.0.x.min(b.0.x), a.0.y.min(b.0.y)),
+            Point::new(a.1.x.max(b.1.x), a.1.y.max(b.1.y)),
+        )),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+impl Schematic {
+    // the area a region covers in mm, none if it names nothing on this sheet
+    pub fn region_bounds(&self, region: &Region) -> Option<(Point, Point)> {
+        let (min, max) = match region {
+            Region::Page => return Some(self.page_bounds()),
+            Region::Area(min, max) => return Some((min.clone(), max.clone())),
+            Region::Symbol(reference) => {
+                let index = self
+                    .symbs
+                    .iter()
+                    .position(|symb| symb.prop("Reference") == Some(reference.as_str()))?;
+                self.item_bounds(ItemRef::Symbol(index))?
+            }
+            Region::Net(name) => {
+                let netlist = self.netlist();
+                let net = netlist
+                    .nets
+                    .iter()
+                    .find(|net| net.name == *name || net.names.contains(name))?;
+                let mut bounds = None;
+                for wire in &net.wires {
+                    bounds = union(bounds, self.item_bounds(ItemRef::Wire(*wire)));
+                }
+                for label in &net.labels {
+                    bounds = union(bounds, self.item_bounds(ItemRef::Label(*label)));
+                }
+                for pin in &net.pins {
+                    bounds = union(bounds, Some((pin.pos.clone(), pin.pos.clone())));
+                }
+                bounds?
+            }
+        };
+        Some((
+            Point::new(min.x - REGION_MARGIN, min.y - REGION_MARGIN),
+            Point::new(max.x + REGION_MARGIN, max.y + REGION_MARGIN),
+        ))
+    }
+
+    // png of the whole page at `dpi` pixels per inch
+    pub fn render_png(&self, dpi: f64, theme: &Theme) -> Result<Vec<u8>, PngError> {
+        self.render_png_region(dpi, theme, &Region::Page)
+    }
+
+    // png cropped to a region, sized before anything is allocated so a huge
+    // dpi is an error rather than an abort
+    pub fn render_png_region(
+        &self,
+        dpi: f64,
+        theme: &Theme,
+        region: &Region,
+    ) -> Result<Vec<u8>, PngError> {
+        let (min, max) = self.region_bounds(region).ok_or(PngError::Region)?;
+        let scale = dpi / 25.4;
+        let width = ((max.x - min.x) * scale).ceil().max(1.0);
+        let height = ((max.y - min.y) * scale).ceil().max(1.0);
+        let size = PngError::Size(width as u64, height as u64);
+        if width * height > MAX_PIXELS as f64 {
+            return Err(size);
+        }
+        let base = Transform::scale(scale, scale).then(&Transform::translate(-min.x, -min.y));
+
+        let mut r = RasterRenderer::new(width as u32, height as u32, &base).ok_or(size)?;
+        let background = &theme.background;
+        r.pixmap.fill(tiny_skia::Color::from_rgba8(
+            background.r,
+            background.g,
+            background.b,
+            (background.a * 255.0).round() as u8,
+        ));
+        self.render_frame(&mut r, theme);
+        self.render(&mut r, theme, &[]);
+        r.pixmap
+            .encode_png()
+            .map_err(|error| PngError::Encode(error.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::netlist::tests::{sheet, wire};
+
+    #[test]
+    fn png_sizes() {
+        let schem = sheet(&[], &[wire((0.0, 0.0), (10.0, 0.0), 1)]);
+        let theme = Theme::kicad_default();
+        let png = schem.render_png(25.4, &theme).unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+        let wire = Region::Area(Point::new(0.0, 0.0), Point::new(10.0, 5.0));
+        assert!(schem.render_png_region(10.0, &theme, &wire).is_ok());
+
+        // a4 at 100000 dpi, refused before allocating
+        assert_eq!(
+            schem.render_png(100000.0, &theme),
+            Err(PngError::Size(1169292, 826772))
+        );
+        assert!(schem.render_png(f64::INFINITY, &theme).is_err());
+        assert_eq!(
+            schem.render_png_region(300.0, &theme, &Region::Symbol("U1".to_string())),
+            Err(PngError::Region)
+        );
+    }
+}
@@ -1,209 +1,147 @@
-use crate::theme::Theme;
+use crate::theme::{Color, Theme};
 use std::f64;
 
+use crate::erc::{ErcViolation, Severity};
+use crate::renderer::*;
 use crate::schematic::*;
-use wasm_bindgen::prelude::*;
-use wasm_bindgen::JsCast;
-
-#[wasm_bindgen]
-extern "C" {
-    // Use `js_namespace` here to bind `console.log(..)` instead of just
-    // `log(..)`
-    #[wasm_bindgen(js_namespace = console)]
-    fn log(s: &str);
-
-    // The `console.log` is quite polymorphic, so we can bind it with multiple
-    // signatures. Note that we need to use `js_name` to ensure we always call
-    // `log` in JS.
-    #[wasm_bindgen(js_namespace = console, js_name = log)]
-    fn log_u32(a: u32);
-
-    // Multiple arguments too!
-    #[wasm_bindgen(js_namespace = console, js_name = log)]
-    fn log_many(a: &str, b: &str);
-}
 
-macro_rules! console_log {
-    // Note that this is using the `log` function imported above during
-    // `bare_bones`
-    ($($t:tt)*) => (log(&format_args!($($t)*).to_string()))
+// moves to `pos` rotated by `angle` (radians) so the text is never drawn
+// upside down, returns the alignment the text should then use
+fn text_frame(r: &mut dyn Renderer, pos: &Point, angle: f64) -> TextAlign {
+    let translate = Transform::translate(pos.x, pos.y);
+    if angle > f64::consts::PI * 0.5 && angle <= f64::consts::PI * 1.5 {
+        // half rotate to flip text
+        r.push_transform(&translate.then(&Transform::rotate(-angle - f64::consts::PI)));
+        TextAlign::Right
+    } else {
+        r.push_transform(&translate.then(&Transform::rotate(-angle))); // why inverse?
+        TextAlign::Left
+    }
 }
 
 impl Wire {
-    fn draw(
-        &self,
-        context: &web_sys::CanvasRenderingContext2d,
-        cmod: &CanvasMod,
-    ) -> Result<(), JsValue> {
+    fn draw(&self, r: &mut dyn Renderer, cmod: &CanvasMod) {
         // draw pos to pos using stroke
         if !self.poss.is_empty() {
             // ensure vec exists
-            context.move_to((self.poss[0].x) * cmod.scale, (self.poss[0].y) * cmod.scale);
+            r.begin_path();
+            r.move_to(self.poss[0].x, self.poss[0].y);
             for point in &self.poss {
-                context.line_to((point.x) * cmod.scale, (point.y) * cmod.scale);
+                r.line_to(point.x, point.y);
             }
+            let color = cmod.theme.resolve(&self.stroke.color, &cmod.theme.wire);
+            r.set_style(&Paint::stroke(color, self.stroke.width));
+            r.stroke();
         }
-
-        Ok(())
     }
 }
 
 impl FillType {
-    fn begin(&self, context: &web_sys::CanvasRenderingContext2d, color: &JsValue) {
-        // todo uses theme instead of color
-        context.stroke();
-        context.set_fill_style(&color);
-        context.begin_path();
+    fn begin(&self, r: &mut dyn Renderer) {
+        r.begin_path();
     }
 
-    fn end(&self, context: &web_sys::CanvasRenderingContext2d) {
+    // fills (body color for background, outline color for outline) then strokes
+    fn end(&self, r: &mut dyn Renderer, cmod: &CanvasMod, stroke: &Stroke) {
+        let mut style = stroke.style(cmod);
         match self {
             FillType::Background => {
-                context.fill();
+                style.fill = cmod.theme.component_body.clone();
+                r.set_style(&style);
+                r.fill();
             }
             FillType::Outline => {
-                context.fill();
-                context.stroke();
-                // todo : does not work? dual rendering?
-            }
-            FillType::None => {
-                context.stroke();
+                style.fill = style.stroke.clone();
+                r.set_style(&style);
+                r.fill();
             }
+            FillType::None => r.set_style(&style),
         }
-        context.begin_path();
+        r.stroke();
+        r.begin_path();
+    }
+}
+
+impl Stroke {
+    fn resolve<'a>(&'a self, cmod: &'a CanvasMod) -> &'a Color {
+        cmod.theme
+            .resolve(&self.color, &cmod.theme.component_outline)
+    }
+
+    fn style(&self, cmod: &CanvasMod) -> Paint {
+        let mut style = Paint::stroke(self.resolve(cmod), self.width);
+        style.dash = match self.format {
+            StrokeFormat::Default | StrokeFormat::Solid => Vec::new(),
+            _ => vec![2.0, 2.0],
+        };
+        style
     }
 }
 
 impl Rect {
-    fn draw(&self, context: &web_sys::CanvasRenderingContext2d, cmod: &CanvasMod) {
+    fn draw(&self, r: &mut dyn Renderer, cmod: &CanvasMod) {
         // draw pos to pos using stroke
-        self.fill.begin(context, &JsValue::from("orange"));
-
-        context.move_to(self.poss.0.x * cmod.scale, self.poss.0.y * cmod.scale);
-        context.rect(
-            (self.poss.0.x) * cmod.scale,
-            (self.poss.0.y) * cmod.scale,
-            (self.poss.1.x - self.poss.0.x) * cmod.scale,
-            (self.poss.1.y - self.poss.0.y) * cmod.scale, //todo : why?
+        self.fill.begin(r);
+        r.rect(
+            self.poss.0.x,
+            self.poss.0.y,
+            self.poss.1.x - self.poss.0.x,
+            self.poss.1.y - self.poss.0.y,
         );
-
-        self.fill.end(context);
+        self.fill.end(r, cmod, &self.stroke);
     }
 }
 
 impl Circ {
-    fn draw(
-        &self,
-        context: &web_sys::CanvasRenderingContext2d,
-        cmod: &CanvasMod,
-    ) -> Result<(), JsValue> {
-        self.fill.begin(context, &JsValue::from("black"));
-
-        // draw pos to pos using stroke
-        context.move_to(
-            (self.pos.x + self.radius) * cmod.scale,
-            self.pos.y * cmod.scale,
-        );
-        context.arc(
-            self.pos.x * cmod.scale,
-            self.pos.y * cmod.scale,
-            self.radius * cmod.scale,
-            0.0,
-            f64::consts::PI * 2.0,
-        )?;
-
-        self.fill.end(context);
-        Ok(())
+    fn draw(&self, r: &mut dyn Renderer, cmod: &CanvasMod) {
+        self.fill.begin(r);
+        r.circle(self.pos.x, self.pos.y, self.radius);
+        self.fill.end(r, cmod, &self.stroke);
     }
 }
 
 impl Junction {
-    fn draw(
-        &self,
-        context: &web_sys::CanvasRenderingContext2d,
-        cmod: &CanvasMod,
-    ) -> Result<(), JsValue> {
+    fn draw(&self, r: &mut dyn Renderer, cmod: &CanvasMod) {
         // todo : move pos based on diam
-        context.move_to(self.pos.x * cmod.scale, self.pos.y * cmod.scale);
-        context.arc(
-            self.pos.x * cmod.scale,
-            self.pos.y * cmod.scale,
-            ((self.diameter + 0.2) * 1.0) * cmod.scale,
-            0.0,
-            f64::consts::PI * 2.0,
-        )?;
-        context.fill();
-        Ok(())
+        r.begin_path();
+        r.circle(self.pos.x, self.pos.y, self.diameter + 0.2);
+        let color = cmod.theme.resolve(&self.color, &cmod.theme.junction);
+        r.set_style(&Paint::fill(color));
+        r.fill();
     }
 }
 
 impl Text {
-    fn draw(
-        &self,
-        context: &web_sys::CanvasRenderingContext2d,
-        cmod: &CanvasMod,
-    ) -> Result<(), JsValue> {
+    fn draw(&self, r: &mut dyn Renderer, cmod: &CanvasMod) {
         let angle = self.pos.a / 180.0 * f64::consts::PI;
-        context.translate(self.pos.x * cmod.scale, self.pos.y * cmod.scale)?;
-        context.set_font(format!("{}px monospace", (1.8 * cmod.scale) as i32).as_str());
-        if angle > f64::consts::PI * 0.5 && angle <= f64::consts::PI * 1.5 {
-            context.rotate(-angle - f64::consts::PI)?; // half rotate to flip text
-            context.set_text_align("right");
-            for (index, newline) in self.text.split("\\n").enumerate() {
-                context.fill_text(newline, 0.0, (1.8 * index as f64) * cmod.scale)?;
-            }
-            context.rotate(f64::consts::PI)?; // finish rotation
-        } else {
-            context.rotate(-angle)?; // why inverse?
-            context.set_text_align("left");
-            for (index, newline) in self.text.split("\\n").enumerate() {
-                context.fill_text(newline, 0.0, (1.8 * index as f64) * cmod.scale)?;
-            }
+        let color = cmod.theme.resolve(&self.effect.color, &cmod.theme.notes);
+        let align = text_frame(r, &self.pos, angle);
+        r.set_style(&Paint::text(color, align, TextBaseline::Alphabetic));
+        for (index, newline) in self.text.split("\\n").enumerate() {
+            r.text(newline, 0.0, DEFAULT_FONT * index as f64);
         }
-        context.rotate(angle)?;
-        context.translate(-((self.pos.x) * cmod.scale), -((self.pos.y) * cmod.scale))?;
-        Ok(())
+        r.pop_transform();
     }
 }
 
 impl Polyline {
-    fn draw(
-        &self,
-        context: &web_sys::CanvasRenderingContext2d,
-        cmod: &CanvasMod,
-    ) -> Result<(), JsValue> {
-        self.fill.begin(context, &JsValue::from("black"));
+    fn draw(&self, r: &mut dyn Renderer, cmod: &CanvasMod) {
+        self.fill.begin(r);
         // draw pos to pos using stroke
         if !self.poss.is_empty() {
-            context.move_to(self.poss[0].x * cmod.scale, self.poss[0].y * cmod.scale);
+            r.move_to(self.poss[0].x, self.poss[0].y);
             for point in &self.poss {
-                match self.stroke.format {
-                    StrokeFormat::Default => {
-                        context.set_line_dash(&js_sys::Array::new())?;
-                    }
-                    _ => {
-                        let x = js_sys::Array::new();
-                        x.push(&JsValue::from_f64(2.0 * cmod.scale));
-                        x.push(&JsValue::from_f64(2.0 * cmod.scale));
-                        context.set_line_dash(&x)?;
-                    }
-                }
-                context.line_to(point.x * cmod.scale, point.y * cmod.scale);
+                r.line_to(point.x, point.y);
             }
-            self.fill.end(context);
+            self.fill.end(r, cmod, &self.stroke);
         }
-        Ok(())
     }
 }
 
 impl Arc {
-    fn draw(
-        &self,
-        context: &web_sys::CanvasRenderingContext2d,
-        cmod: &CanvasMod,
-    ) -> Result<(), JsValue> {
+    fn draw(&self, r: &mut dyn Renderer, cmod: &CanvasMod) {
         // draw pos to pos using stroke
-        self.fill.begin(context, &JsValue::from("black"));
+        self.fill.begin(r);
 
         let line1_angle = f64::atan2(self.poss.1.y - self.poss.0.y, self.poss.1.x - self.poss.0.x)
             + f64::consts::PI / 2.0;
@@ -245,205 +183,221 @@ impl Arc {
         let angle_start = f64::atan2(self.poss.0.y - cent.y, self.poss.0.x - cent.x);
         let angle_stop = f64::atan2(self.poss.2.y - cent.y, self.poss.2.x - cent.x);
 
-        context.move_to(self.poss.0.x * cmod.scale, self.poss.0.y * cmod.scale);
-        context.arc(
-            cent.x * cmod.scale,
-            cent.y * cmod.scale,
-            radius * cmod.scale,
-            angle_start,
-            angle_stop,
-        )?;
-
-        self.fill.end(context);
-        Ok(())
+        r.move_to(self.poss.0.x, self.poss.0.y);
+        r.arc(cent.x, cent.y, radius, angle_start, angle_stop);
+
+        self.fill.end(r, cmod, &self.stroke);
     }
 }
 
 impl Property {
-    fn draw(
-        &self,
-        context: &web_sys::CanvasRenderingContext2d,
-        cmod: &CanvasMod,
-        angle: f64,
-    ) -> Result<(), JsValue> {
+    fn draw(&self, r: &mut dyn Renderer, cmod: &CanvasMod, angle: f64) {
         if self.show {
             // don't continue if hiden
             // todo : inherit from Text rendering
             let angle = (self.pos.a + angle) / 180.0 * f64::consts::PI;
-            context.translate(self.pos.x * cmod.scale, self.pos.y * cmod.scale)?;
-            context.set_font(format!("{}px monospace", (1.8 * cmod.scale) as i32).as_str()); // todo : cache
-
-            if angle > f64::consts::PI * 0.5 && angle <= f64::consts::PI * 1.5 {
-                context.rotate(-angle - f64::consts::PI)?; // half rotate to flip text
-                context.set_text_align("right");
-                context.fill_text(self.value.as_str(), 0.0, (1.8) * cmod.scale)?;
-                context.rotate(f64::consts::PI)?; // finish rotation
-            } else {
-                context.rotate(-angle)?; // why inverse?
-                context.set_text_align("left");
-                context.fill_text(self.value.as_str(), 0.0, (1.8) * cmod.scale)?;
-            }
-            context.rotate(angle)?;
-
-            context.translate(-(self.pos.x * cmod.scale), -(self.pos.y * cmod.scale))?;
+            let color = match self.key.as_str() {
+                "Reference" => &cmod.theme.reference,
+                "Value" => &cmod.theme.value,
+                _ => &cmod.theme.fields,
+            };
+            let color = cmod.theme.resolve(&self.effect.color, color);
+            let align = text_frame(r, &self.pos, angle);
+            r.set_style(&Paint::text(color, align, TextBaseline::Alphabetic));
+            r.text(self.value.as_str(), 0.0, DEFAULT_FONT);
+            r.pop_transform();
         }
-
-        Ok(())
     }
 }
 
 impl Pin {
-    fn draw(
-        &self,
-        context: &web_sys::CanvasRenderingContext2d,
-        cmod: &CanvasMod,
-    ) -> Result<(), JsValue> {
+    fn draw(&self, r: &mut dyn Renderer, cmod: &CanvasMod) {
         let angle = (self.pos.a) / 180.0 * f64::consts::PI;
-        context.translate(self.pos.x * cmod.scale, self.pos.y * cmod.scale)?;
-        context.rotate(angle)?;
-        context.move_to(0.0, 0.0);
-        context.line_to(self.len * cmod.scale, 0.0);
-        context.rotate(-angle)?;
-        context.translate(-(self.pos.x * cmod.scale), -(self.pos.y * cmod.scale))?;
-        Ok(())
+        r.push_transform(
+            &Transform::translate(self.pos.x, self.pos.y).then(&Transform::rotate(angle)),
+        );
+        r.begin_path();
+        r.move_to(0.0, 0.0);
+        r.line_to(self.len, 0.0);
+        r.set_style(&Paint::stroke(&cmod.theme.pin, 0.0));
+        r.stroke();
+        r.pop_transform();
     }
 }
 
 impl Symbol {
-    fn draw(
-        &self,
-        context: &web_sys::CanvasRenderingContext2d,
-        cmod: &CanvasMod,
-    ) -> Result<(), JsValue> {
+    fn draw(&self, r: &mut dyn Renderer, cmod: &CanvasMod) {
         for rect in &self.rects {
-            rect.draw(context, cmod);
+            rect.draw(r, cmod);
         }
         for circ in &self.circs {
-            circ.draw(context, cmod)?;
+            circ.draw(r, cmod);
         }
         for line in &self.lines {
-            line.draw(context, cmod)?;
+            line.draw(r, cmod);
         }
         for arc in &self.arcs {
-            arc.draw(context, cmod)?;
+            arc.draw(r, cmod);
         }
         for pin in &self.pins {
-            pin.draw(context, cmod)?;
+            pin.draw(r, cmod);
         }
-        Ok(())
     }
 }
 
-impl SymbolTemp {
-    fn draw(
-        &self,
-        context: &web_sys::CanvasRenderingContext2d,
-        cmod: &CanvasMod,
-    ) -> Result<(), JsValue> {
-        for symb in &self.symbs {
-            symb.draw(context, cmod)?;
-        }
-        Ok(())
+impl SymbolInst {
+    // library space (y up) to sheet space, see `SymbolInst::transform`
+    pub fn matrix(&self) -> Transform {
+        let angle = (self.pos.a) / 180.0 * f64::consts::PI;
+        Transform::translate(self.pos.x, self.pos.y)
+            .then(&Transform::scale(
+                if self.mirror.0 { -1.0 } else { 1.0 },
+                if self.mirror.1 { 1.0 } else { -1.0 },
+            ))
+            .then(&Transform::rotate(angle))
     }
-}
 
-impl SymbolInst {
-    fn draw(
-        &self,
-        context: &web_sys::CanvasRenderingContext2d,
-        cmod: &CanvasMod,
-    ) -> Result<(), JsValue> {
+    fn draw(&self, r: &mut dyn Renderer, cmod: &CanvasMod) {
         if self.parent.is_some() {
-            let angle = (self.pos.a) / 180.0 * f64::consts::PI;
-            context.translate(self.pos.x * cmod.scale, self.pos.y * cmod.scale)?;
-            context.scale(
-                -(self.mirror.0 as i32 as f64 * 2.0 - 1.0),
-                (self.mirror.1 as i32 as f64 * 2.0 - 1.0),
-            )?;
-            context.rotate(angle)?;
-            // let mut cmod = cmod.clone();
-            // cmod.flip = (cmod.flip.0 ^ self.mirror.0, cmod.flip.1 ^ self.mirror.1);
-            self.parent.as_ref().unwrap().draw(context, &cmod)?;
-
-            context.rotate(-angle)?;
-            context.scale(
-                -(self.mirror.0 as i32 as f64 * 2.0 - 1.0),
-                (self.mirror.1 as i32 as f64 * 2.0 - 1.0),
-            )?;
-            context.translate(-(self.pos.x * cmod.scale), -(self.pos.y * cmod.scale))?;
+            r.push_transform(&self.matrix());
+            for symb in self.symbols() {
+                symb.draw(r, cmod);
+            }
+            r.pop_transform();
 
             // apparently properties are absolute compared to their parent symbol?
-            // todo collate template props
-            for prop in &self.props {
-                prop.draw(context, &cmod, self.pos.a)?;
+            for prop in &self.fields() {
+                prop.draw(r, cmod, self.pos.a);
             }
         }
-        Ok(())
     }
 }
 
 impl Label {
-    fn draw(
-        &self,
-        context: &web_sys::CanvasRenderingContext2d,
-        cmod: &CanvasMod,
-    ) -> Result<(), JsValue> {
+    fn draw(&self, r: &mut dyn Renderer, cmod: &CanvasMod) {
         // draws an label based on label type
         // todo : type based rendering
         let size = 1.0; // todo global size?
         let angle = (self.pos.a) / 180.0 * f64::consts::PI;
-        context.translate((self.pos.x) * cmod.scale, (self.pos.y) * cmod.scale)?;
-        context.rotate(-angle)?; // why inverse?
+        let color = match self.shape {
+            Style::Heir => &cmod.theme.hierarchical_label,
+            Style::Global => &cmod.theme.global_label,
+            Style::Local => &cmod.theme.labels,
+            Style::Noconn => &cmod.theme.no_connect,
+        };
+        let color = cmod.theme.resolve(&self.effect.color, color);
+        let flipped = angle > f64::consts::PI * 0.5 && angle <= f64::consts::PI * 1.5;
+        let mut style = Paint::stroke(color, 0.0);
+        style.fill = color.clone();
+        style.align = if flipped {
+            TextAlign::Right
+        } else {
+            TextAlign::Left
+        };
+        r.push_transform(
+            &Transform::translate(self.pos.x, self.pos.y).then(&Transform::rotate(-angle)),
+        ); // why inverse?
+        r.begin_path();
         match self.shape {
-            Style::Heir => {
-                context.set_font(format!("{}px monospace", (1.8 * cmod.scale) as i32).as_str());
-                context.set_text_baseline("middle");
-                if angle > f64::consts::PI * 0.5 && angle <= f64::consts::PI * 1.5 {
-                    context.rotate(-f64::consts::PI)?; // half rotate to flip text
-                    context.set_text_align("right");
-                    context.fill_text(self.id.as_str(), -(size * 2.5) * cmod.scale, 0.0)?;
-                    context.rotate(f64::consts::PI)?; // finish rotation
+            Style::Heir | Style::Global => {
+                style.baseline = TextBaseline::Middle;
+                r.set_style(&style);
+                if flipped {
+                    r.push_transform(&Transform::rotate(-f64::consts::PI)); // half rotate to flip text
+                    r.text(self.id.as_str(), -(size * 2.5), 0.0);
+                    r.pop_transform();
                 } else {
-                    context.set_text_align("left");
-                    context.fill_text(self.id.as_str(), (size * 2.5) * cmod.scale, 0.0)?;
+                    r.text(self.id.as_str(), size * 2.5, 0.0);
                 }
-                context.set_text_baseline("bottom");
                 // draw frame
-                context.move_to(0.0, 0.0);
-                context.line_to((size) * cmod.scale, (size) * cmod.scale);
-                context.line_to((size * 2.0) * cmod.scale, (size) * cmod.scale);
-                context.line_to((size * 2.0) * cmod.scale, -(size) * cmod.scale);
-                context.line_to((size) * cmod.scale, -(size) * cmod.scale);
-                context.line_to(0.0, 0.0);
+                r.move_to(0.0, 0.0);
+                r.line_to(size, size);
+                r.line_to(size * 2.0, size);
+                r.line_to(size * 2.0, -size);
+                r.line_to(size, -size);
+                r.line_to(0.0, 0.0);
             }
             Style::Noconn => {
                 // draws an "x"
-                let size = 1.0;
-                context.move_to(-size * cmod.scale, -size * cmod.scale);
-                context.line_to(size * cmod.scale, size * cmod.scale);
-                context.move_to(-size * cmod.scale, size * cmod.scale);
-                context.line_to(size * cmod.scale, -size * cmod.scale);
+                r.set_style(&style);
+                r.move_to(-size, -size);
+                r.line_to(size, size);
+                r.move_to(-size, size);
+                r.line_to(size, -size);
+            }
+            Style::Local => {
+                r.set_style(&style);
+                if flipped {
+                    r.push_transform(&Transform::rotate(-f64::consts::PI)); // half rotate to flip text
+                    r.text(self.id.as_str(), 0.0, 0.0);
+                    r.pop_transform();
+                } else {
+                    r.text(self.id.as_str(), 0.0, 0.0);
+                }
             }
-            _ => {}
         }
-        context.rotate(angle)?;
-        context.translate(-((self.pos.x) * cmod.scale), -((self.pos.y) * cmod.scale))?;
-        Ok(())
+        r.stroke();
+        r.pop_transform();
     }
 }
 
-impl Page {
-    fn draw(
-        &self,
-        context: &web_sys::CanvasRenderingContext2d,
-        cmod: &CanvasMod,
-    ) -> Result<(), JsValue> {
-        let (size, margin) = match self {
-            Page::A4 => {
-                // size // margin (tlbr)
-                ((297.0, 210.0), (10.0, 10.0, 10.0, 10.0))
+impl Sheet {
+    fn draw(&self, r: &mut dyn Renderer, cmod: &CanvasMod) {
+        r.begin_path();
+        r.rect(self.pos.x, self.pos.y, self.size.0, self.size.1);
+        let mut style = Paint::stroke(
+            cmod.theme.resolve(&self.stroke.color, &cmod.theme.sheet),
+            self.stroke.width,
+        );
+        style.fill = cmod
+            .theme
+            .resolve(&self.fill, &cmod.theme.sheet_background)
+            .clone();
+        r.set_style(&style);
+        r.fill();
+        r.stroke();
+
+        // name sits above the box, file name below it
+        for prop in &self.props {
+            if !prop.show {
+                continue;
             }
-        };
+            let (color, baseline) = match prop.key.as_str() {
+                "Sheetname" | "Sheet name" => (&cmod.theme.sheet_name, TextBaseline::Bottom),
+                "Sheetfile" | "Sheet file" => (&cmod.theme.sheet_filename, TextBaseline::Top),
+                _ => (&cmod.theme.sheet_fields, TextBaseline::Alphabetic),
+            };
+            let color = cmod.theme.resolve(&prop.effect.color, color);
+            let align = text_frame(r, &prop.pos, prop.pos.a / 180.0 * f64::consts::PI);
+            r.set_style(&Paint::text(color, align, baseline));
+            r.text(&prop.value, 0.0, 0.0);
+            r.pop_transform();
+        }
+
+        // pin names run from the edge into the box
+        for pin in &self.pins {
+            let angle = (pin.pos.a + 180.0) % 360.0 / 180.0 * f64::consts::PI;
+            let color = cmod
+                .theme
+                .resolve(&pin.effect.color, &cmod.theme.sheet_label);
+            let align = text_frame(r, &pin.pos, angle);
+            r.set_style(&Paint::text(color, align, TextBaseline::Middle));
+            let offset = if align == TextAlign::Right { -1.0 } else { 1.0 };
+            r.text(&pin.name, offset, 0.0);
+            r.pop_transform();
+        }
+    }
+}
+
+impl Page {
+    fn draw(&self, r: &mut dyn Renderer, cmod: &CanvasMod, portrait: bool) {
+        let size = self.size(portrait);
+        let margin = (10.0, 10.0, 10.0, 10.0); // tlbr
+                                               // the frame reuses item drawing, recolored to the drawing sheet
+        let mut cmod = cmod.clone();
+        cmod.theme.component_outline = cmod.theme.drawing_sheet.clone();
+        cmod.theme.notes = cmod.theme.drawing_sheet.clone();
+        let cmod = &cmod;
         // draw full page size
         Rect {
             poss: (
@@ -460,9 +414,9 @@ impl Page {
             ),
             stroke: Stroke::blank(),
             fill: FillType::None,
-            uuid: "".to_string(),
+            uuid: Uuid::nil(),
         }
-        .draw(context, cmod);
+        .draw(r, cmod);
         // draw page margin
         Rect {
             poss: (
@@ -479,9 +433,9 @@ impl Page {
             ),
             stroke: Stroke::blank(),
             fill: FillType::None,
-            uuid: "".to_string(),
+            uuid: Uuid::nil(),
         }
-        .draw(context, cmod);
+        .draw(r, cmod);
         Rect {
             poss: (
                 Point {
@@ -497,9 +451,9 @@ impl Page {
             ),
             stroke: Stroke::blank(),
             fill: FillType::None,
-            uuid: "".to_string(),
+            uuid: Uuid::nil(),
         }
-        .draw(context, cmod);
+        .draw(r, cmod);
         // draw "chess" grid
         let inc = 50;
         for index in 0..(size.0 / inc as f64) as u32 + 1 {
@@ -511,31 +465,32 @@ impl Page {
                 Text {
                     text: (index + 1).to_string(),
                     pos: Point {
-                        x: x,
+                        x,
                         y: y + 1.7,
                         a: 0.0,
                     },
-                    uuid: "".to_string(),
+                    effect: Effect::blank(),
+                    uuid: Uuid::nil(),
                 }
-                .draw(context, cmod);
+                .draw(r, cmod);
                 let x = x + 25.0;
                 if x > size.0 + margin.1 - margin.3 {
                     continue;
                 }
                 Rect {
                     poss: (
-                        Point { x: x, y: y, a: 0.0 },
+                        Point { x, y, a: 0.0 },
                         Point {
-                            x: x,
+                            x,
                             y: y + 2.0,
                             a: 0.0,
                         },
                     ),
                     stroke: Stroke::blank(),
                     fill: FillType::None,
-                    uuid: "".to_string(),
+                    uuid: Uuid::nil(),
                 }
-                .draw(context, cmod);
+                .draw(r, cmod);
             }
         }
         for index in 0..(size.1 / inc as f64) as u32 + 1 {
@@ -548,74 +503,221 @@ impl Page {
                     text: ((index + 97) as u8 as char).to_string(),
                     pos: Point {
                         x: x + 0.3,
-                        y: y,
+                        y,
                         a: 0.0,
                     },
-                    uuid: "".to_string(),
+                    effect: Effect::blank(),
+                    uuid: Uuid::nil(),
                 }
-                .draw(context, cmod);
+                .draw(r, cmod);
                 let y = y + 25.0;
                 if y > size.1 + margin.0 - margin.2 {
                     continue;
                 }
                 Rect {
                     poss: (
-                        Point { x: x, y: y, a: 0.0 },
+                        Point { x, y, a: 0.0 },
                         Point {
                             x: x + 2.0,
-                            y: y,
+                            y,
                             a: 0.0,
                         },
                     ),
                     stroke: Stroke::blank(),
                     fill: FillType::None,
-                    uuid: "".to_string(),
+                    uuid: Uuid::nil(),
                 }
-                .draw(context, cmod);
+                .draw(r, cmod);
             }
         }
-        Ok(())
+    }
+}
+
+impl ErcViolation {
+    fn draw(&self, r: &mut dyn Renderer, cmod: &CanvasMod) {
+        // arrow marker with its tip on the violation
+        let size = 1.5;
+        let color = match self.severity {
+            Severity::Error => &cmod.theme.erc_error,
+            Severity::Warning => &cmod.theme.erc_warning,
+        };
+        r.begin_path();
+        r.move_to(self.pos.x, self.pos.y);
+        r.line_to(self.pos.x + size * 0.5, self.pos.y - size);
+        r.line_to(self.pos.x + size, self.pos.y - size * 0.5);
+        r.close_path();
+        r.set_style(&Paint::fill(color));
+        r.fill();
     }
 }
 
 impl Schematic {
-    pub fn draw(&self, canvas: &web_sys::HtmlCanvasElement, scale: f64) -> Result<(), JsValue> {
-        canvas.set_height((1080.0 * scale) as u32);
-        canvas.set_width((1080.0 * 1.414 * scale) as u32);
-        let context = &canvas
-            .get_context("2d")
-            .unwrap()
-            .unwrap()
-            .dyn_into::<web_sys::CanvasRenderingContext2d>()
-            .unwrap();
-        context.begin_path();
+    // extent of one item as drawn, in mm
+    pub fn item_bounds(&self, item: ItemRef) -> Option<(Point, Point)> {
+        let r = &mut Bounds::new();
+        let cmod = &CanvasMod::new();
+        match item {
+            ItemRef::Wire(index) => self.wires[index].draw(r, cmod),
+            ItemRef::Junction(index) => self.juncs[index].draw(r, cmod),
+            ItemRef::Text(index) => self.texts[index].draw(r, cmod),
+            ItemRef::Polyline(index) => self.polys[index].draw(r, cmod),
+            ItemRef::Label(index) => self.labels[index].draw(r, cmod),
+            ItemRef::Symbol(index) => self.symbs[index].draw(r, cmod),
+            ItemRef::Sheet(index) => self.sheets[index].draw(r, cmod),
+        }
+        r.area()
+    }
+
+    // extent of everything on the sheet but the frame, none when it's empty
+    pub fn content_bounds(&self) -> Option<(Point, Point)> {
+        let r = &mut Bounds::new();
+        self.render(r, &CanvasMod::new().theme, &[]);
+        r.area()
+    }
+
+    // the page frame, from its corner at the origin
+    pub fn page_bounds(&self) -> (Point, Point) {
+        let (width, height) = self.page_size();
+        (Point::new(0.0, 0.0), Point::new(width, height))
+    }
+
+    // extent of a symbol by reference, every unit of it
+    pub fn symbol_bounds(&self, reference: &str) -> Option<(Point, Point)> {
+        let r = &mut Bounds::new();
+        let cmod = &CanvasMod::new();
+        for symb in &self.symbs {
+            if symb.prop("Reference") == Some(reference) {
+                symb.draw(r, cmod);
+            }
+        }
+        r.area()
+    }
+
+    // draws items again on top in the theme's highlight color, for a
+    // highlighted net or search hits
+    pub fn render_highlight(&self, r: &mut dyn Renderer, theme: &Theme, items: &[ItemRef]) {
         let cmod = &CanvasMod {
-            scale: scale * 4.0,
+            scale: 1.0,
             flip: (false, false),
-            theme: Theme::new(),
-        }; //todo fix scaling
-        self.page.draw(context, cmod)?;
+            theme: theme.highlighted(),
+        };
+        r.begin_group(&Group::new("highlight"));
+        for item in items {
+            match *item {
+                ItemRef::Wire(index) => self.wires[index].draw(r, cmod),
+                ItemRef::Junction(index) => self.juncs[index].draw(r, cmod),
+                ItemRef::Text(index) => self.texts[index].draw(r, cmod),
+                ItemRef::Polyline(index) => self.polys[index].draw(r, cmod),
+                ItemRef::Label(index) => self.labels[index].draw(r, cmod),
+                ItemRef::Symbol(index) => self.symbs[index].draw(r, cmod),
+                ItemRef::Sheet(index) => self.sheets[index].draw(r, cmod),
+            }
+        }
+        r.end_group();
+    }
+
+    // draws the page frame in mm
+    pub fn render_frame(&self, r: &mut dyn Renderer, theme: &Theme) {
+        let cmod = &CanvasMod {
+            scale: 1.0,
+            flip: (false, false),
+            theme: theme.clone(),
+        };
+        r.begin_group(&Group::new("frame"));
+        self.page.draw(r, cmod, self.portrait);
+        r.end_group();
+    }
 
+    // draws every item of the sheet in mm, grouped by item type with each
+    // item tagged by its uuid, backends scale to their own units
+    pub fn render(&self, r: &mut dyn Renderer, theme: &Theme, markers: &[ErcViolation]) {
+        let cmod = &CanvasMod {
+            scale: 1.0,
+            flip: (false, false),
+            theme: theme.clone(),
+        };
+
+        r.begin_group(&Group::new("symbols"));
         for symb in &self.symbs {
-            symb.draw(context, cmod)?;
+            let mut group = Group::item("symbol", &symb.uuid).with("lib-id", &symb.id);
+            for key in ["Reference", "Value"] {
+                if let Some(value) = symb.prop(key) {
+                    group = group.with(&key.to_lowercase(), value);
+                }
+            }
+            r.begin_group(&group);
+            symb.draw(r, cmod);
+            r.end_group();
+        }
+        r.end_group();
+
+        r.begin_group(&Group::new("sheets"));
+        for sheet in &self.sheets {
+            let group = Group::item("sheet", &sheet.uuid)
+                .with("name", sheet.name())
+                .with("file", sheet.file());
+            r.begin_group(&group);
+            sheet.draw(r, cmod);
+            r.end_group();
         }
+        r.end_group();
+
+        r.begin_group(&Group::new("wires"));
         for wire in &self.wires {
-            wire.draw(context, cmod)?;
+            r.begin_group(&Group::item("wire", &wire.uuid));
+            wire.draw(r, cmod);
+            r.end_group();
         }
+        r.end_group();
+
+        r.begin_group(&Group::new("junctions"));
         for junc in &self.juncs {
-            junc.draw(context, cmod)?;
+            r.begin_group(&Group::item("junction", &junc.uuid));
+            junc.draw(r, cmod);
+            r.end_group();
         }
+        r.end_group();
+
+        r.begin_group(&Group::new("texts"));
         for text in &self.texts {
-            text.draw(context, cmod)?;
+            r.begin_group(&Group::item("text", &text.uuid));
+            text.draw(r, cmod);
+            r.end_group();
         }
+        r.end_group();
+
+        r.begin_group(&Group::new("polylines"));
         for poly in &self.polys {
-            poly.draw(context, cmod)?;
+            r.begin_group(&Group::item("polyline", &poly.uuid));
+            poly.draw(r, cmod);
+            r.end_group();
         }
+        r.end_group();
+
+        r.begin_group(&Group::new("labels"));
         for label in &self.labels {
-            label.draw(context, cmod)?;
+            let class = match label.shape {
+                Style::Heir => "hierarchical-label",
+                Style::Global => "global-label",
+                Style::Local => "label",
+                Style::Noconn => "no-connect",
+            };
+            let mut group = Group::item(class, &label.uuid);
+            if !matches!(label.shape, Style::Noconn) {
+                group = group.with("name", &label.id);
+            }
+            r.begin_group(&group);
+            label.draw(r, cmod);
+            r.end_group();
         }
+        r.end_group();
 
-        context.stroke();
-        Ok(())
+        if !markers.is_empty() {
+            r.begin_group(&Group::new("erc"));
+            for marker in markers {
+                marker.draw(r, cmod);
+            }
+            r.end_group();
+        }
     }
 }
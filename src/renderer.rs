@@ -0,0 +1,367 @@
+use crate::font;
+use crate::schematic::Point;
+use crate::theme::Color;
+use crate::uuid::Uuid;
+use std::f64;
+
+// 2d affine transform, maps (x, y) to (a*x + c*y + e, b*x + d*y + f)
+// like the canvas / svg / pdf matrix
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub e: f64,
+    pub f: f64,
+}
+
+impl Transform {
+    pub fn identity() -> Transform {
+        Transform {
+            a: 1.0,
+            b: 0.0,
+            c: 0.0,
+            d: 1.0,
+            e: 0.0,
+            f: 0.0,
+        }
+    }
+
+    pub fn translate(x: f64, y: f64) -> Transform {
+        Transform {
+            e: x,
+            f: y,
+            ..Transform::identity()
+        }
+    }
+
+    pub fn scale(x: f64, y: f64) -> Transform {
+        Transform {
+            a: x,
+            d: y,
+            ..Transform::identity()
+        }
+    }
+
+    // radians, clockwise on screen (y down)
+    pub fn rotate(angle: f64) -> Transform {
+        let (sin, cos) = angle.sin_cos();
+        Transform {
+            a: cos,
+            b: sin,
+            c: -sin,
+            d: cos,
+            ..Transform::identity()
+        }
+    }
+
+    // applies `other` first, then self (like chained canvas calls self; other)
+    pub fn then(&self, other: &Transform) -> Transform {
+        Transform {
+            a: self.a * other.a + self.c * other.b,
+            b: self.b * other.a + self.d * other.b,
+            c: self.a * other.c + self.c * other.d,
+            d: self.b * other.c + self.d * other.d,
+            e: self.a * other.e + self.c * other.f + self.e,
+            f: self.b * other.e + self.d * other.f + self.f,
+        }
+    }
+
+    pub fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        (
+            self.a * x + self.c * y + self.e,
+            self.b * x + self.d * y + self.f,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextBaseline {
+    Alphabetic,
+    Top,
+    Middle,
+    Bottom,
+}
+
+// everything `fill`, `stroke` and `text` need, all lengths in mm
+#[derive(Debug, Clone)]
+pub struct Paint {
+    pub stroke: Color,
+    pub fill: Color,
+    pub width: f64,
+    pub dash: Vec<f64>,
+    pub font_size: f64,
+    pub bold: bool,
+    pub italic: bool,
+    pub align: TextAlign,
+    pub baseline: TextBaseline,
+}
+
+impl Paint {
+    pub fn blank() -> Paint {
+        Paint {
+            stroke: Color::rgb(0, 0, 0),
+            fill: Color::rgb(0, 0, 0),
+            width: DEFAULT_WIDTH,
+            dash: Vec::<f64>::new(),
+            font_size: DEFAULT_FONT,
+            bold: false,
+            italic: false,
+            align: TextAlign::Left,
+            baseline: TextBaseline::Alphabetic,
+        }
+    }
+
+    pub fn stroke(color: &Color, width: f64) -> Paint {
+        Paint {
+            stroke: color.clone(),
+            width: if width > 0.0 { width } else { DEFAULT_WIDTH },
+            ..Paint::blank()
+        }
+    }
+
+    pub fn fill(color: &Color) -> Paint {
+        Paint {
+            fill: color.clone(),
+            ..Paint::blank()
+        }
+    }
+
+    pub fn text(color: &Color, align: TextAlign, baseline: TextBaseline) -> Paint {
+        Paint {
+            fill: color.clone(),
+            align,
+            baseline,
+            ..Paint::blank()
+        }
+    }
+}
+
+// kicad's default line width (6 mil)
+pub const DEFAULT_WIDTH: f64 = 0.1524;
+pub const DEFAULT_FONT: f64 = 1.8;
+
+// a named set of items, backends that keep structure (svg) use it to tag
+// what they draw, the rest ignore it
+#[derive(Debug, Clone)]
+pub struct Group {
+    pub class: String,
+    pub id: Option<Uuid>,
+    pub data: Vec<(String, String)>, // extra attributes, key without "data-"
+}
+
+impl Group {
+    pub fn new(class: &str) -> Group {
+        Group {
+            class: class.to_string(),
+            id: None,
+            data: Vec::<(String, String)>::new(),
+        }
+    }
+
+    pub fn item(class: &str, id: &Uuid) -> Group {
+        Group {
+            id: if id.is_nil() { None } else { Some(*id) },
+            ..Group::new(class)
+        }
+    }
+
+    pub fn with(mut self, key: &str, value: &str) -> Group {
+        self.data.push((key.to_string(), value.to_string()));
+        self
+    }
+}
+
+// a drawing backend, paths are built with move_to .. close_path and
+// painted with fill / stroke using the last style set
+pub trait Renderer {
+    fn begin_path(&mut self);
+    fn move_to(&mut self, x: f64, y: f64);
+    fn line_to(&mut self, x: f64, y: f64);
+    // angles in radians, clockwise from +x on screen
+    fn arc(&mut self, x: f64, y: f64, radius: f64, start: f64, end: f64);
+    fn rect(&mut self, x: f64, y: f64, width: f64, height: f64);
+    fn close_path(&mut self);
+    fn fill(&mut self);
+    fn stroke(&mut self);
+    fn text(&mut self, text: &str, x: f64, y: f64);
+    fn push_transform(&mut self, transform: &Transform);
+    fn pop_transform(&mut self);
+    fn set_style(&mut self, style: &Paint);
+
+    fn begin_group(&mut self, _group: &Group) {}
+    fn end_group(&mut self) {}
+
+    fn circle(&mut self, x: f64, y: f64, radius: f64) {
+        self.move_to(x + radius, y);
+        self.arc(x, y, radius, 0.0, f64::consts::PI * 2.0);
+    }
+}
+
+// canvas style arc as its start point and cubic beziers (c1, c2, end) of at
+// most a quarter turn each, for backends without arcs of their own
+pub fn arc_beziers(
+    x: f64,
+    y: f64,
+    radius: f64,
+    start: f64,
+    end: f64,
+) -> ((f64, f64), Vec<[f64; 6]>) {
+    let tau = f64::consts::PI * 2.0;
+    // clockwise, a sweep of a full turn or more is a circle
+    let sweep = if end - start >= tau {
+        tau
+    } else {
+        (end - start).rem_euclid(tau)
+    };
+    let steps = (sweep / (f64::consts::PI / 2.0)).ceil().max(1.0) as usize;
+    let step = sweep / steps as f64;
+    let k = 4.0 / 3.0 * (step / 4.0).tan() * radius;
+    let mut curves = Vec::<[f64; 6]>::new();
+    for index in 0..steps {
+        let a = start + step * index as f64;
+        let b = a + step;
+        let (sa, ca) = a.sin_cos();
+        let (sb, cb) = b.sin_cos();
+        curves.push([
+            x + radius * ca - k * sa,
+            y + radius * sa + k * ca,
+            x + radius * cb + k * sb,
+            y + radius * sb - k * cb,
+            x + radius * cb,
+            y + radius * sb,
+        ]);
+    }
+    ((x + radius * start.cos(), y + radius * start.sin()), curves)
+}
+
+// where the baseline of `text` starts once aligned, for backends that place
+// text themselves (monospace metrics of the built-in font)
+pub fn text_origin(style: &Paint, text: &str, x: f64, y: f64) -> (f64, f64) {
+    let size = style.font_size;
+    let width = font::width(text, size);
+    let x = match style.align {
+        TextAlign::Left => x,
+        TextAlign::Center => x - width / 2.0,
+        TextAlign::Right => x - width,
+    };
+    // y is down, so a baseline below the top is a larger y
+    let y = match style.baseline {
+        TextBaseline::Alphabetic => y,
+        TextBaseline::Top => y + font::CAP_HEIGHT * size,
+        TextBaseline::Middle => y + font::CAP_HEIGHT / 2.0 * size,
+        TextBaseline::Bottom => y - font::DESCENT * size,
+    };
+    (x, y)
+}
+
+// records the extent of everything drawn, ignoring line widths
+pub struct Bounds {
+    pub min: Point,
+    pub max: Point,
+    pub empty: bool,
+    current: Transform,
+    stack: Vec<Transform>,
+    style: Paint,
+}
+
+impl Bounds {
+    pub fn new() -> Bounds {
+        Bounds {
+            min: Point::new(f64::MAX, f64::MAX),
+            max: Point::new(f64::MIN, f64::MIN),
+            empty: true,
+            current: Transform::identity(),
+            stack: Vec::<Transform>::new(),
+            style: Paint::blank(),
+        }
+    }
+
+    pub fn add(&mut self, x: f64, y: f64) {
+        let (x, y) = self.current.apply(x, y);
+        self.min = Point::new(self.min.x.min(x), self.min.y.min(y));
+        self.max = Point::new(self.max.x.max(x), self.max.y.max(y));
+        self.empty = false;
+    }
+
+    pub fn area(&self) -> Option<(Point, Point)> {
+        if self.empty {
+            None
+        } else {
+            Some((self.min.clone(), self.max.clone()))
+        }
+    }
+}
+
+impl Default for Bounds {
+    fn default() -> Bounds {
+        Bounds::new()
+    }
+}
+
+impl Renderer for Bounds {
+    fn begin_path(&mut self) {}
+
+    fn move_to(&mut self, x: f64, y: f64) {
+        self.add(x, y);
+    }
+
+    fn line_to(&mut self, x: f64, y: f64) {
+        self.add(x, y);
+    }
+
+    fn arc(&mut self, x: f64, y: f64, radius: f64, start: f64, end: f64) {
+        let (start, curves) = arc_beziers(x, y, radius, start, end);
+        self.add(start.0, start.1);
+        // a bezier stays inside its control points
+        for curve in curves {
+            for point in curve.chunks(2) {
+                self.add(point[0], point[1]);
+            }
+        }
+    }
+
+    fn rect(&mut self, x: f64, y: f64, width: f64, height: f64) {
+        self.add(x, y);
+        self.add(x + width, y + height);
+        self.add(x + width, y);
+        self.add(x, y + height);
+    }
+
+    fn close_path(&mut self) {}
+    fn fill(&mut self) {}
+    fn stroke(&mut self) {}
+
+    fn text(&mut self, text: &str, x: f64, y: f64) {
+        let size = self.style.font_size;
+        let (x, y) = text_origin(&self.style, text, x, y);
+        let width = font::width(text, size);
+        self.rect(
+            x,
+            y - font::CAP_HEIGHT * size,
+            width,
+            (font::CAP_HEIGHT + font::DESCENT) * size,
+        );
+    }
+
+    fn push_transform(&mut self, transform: &Transform) {
+        self.stack.push(self.current);
+        self.current = self.current.then(transform);
+    }
+
+    fn pop_transform(&mut self) {
+        self.current = self.stack.pop().unwrap_or_else(Transform::identity);
+    }
+
+    fn set_style(&mut self, style: &Paint) {
+        self.style = style.clone();
+    }
+}
@@ -1,32 +1,19 @@
 use std::collections::HashMap;
 use std::f64;
-use crate::theme::Theme;
+use crate::theme::{Color, Theme};
+use crate::sexpr::Document;
+use crate::spatial::SpatialIndex;
 
-use wasm_bindgen::prelude::*;
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseSchematicError(pub(crate) String);
 
-#[wasm_bindgen]
-extern "C" {
-    // Use `js_namespace` here to bind `console.log(..)` instead of just
-    // `log(..)`
-    #[wasm_bindgen(js_namespace = console)]
-    fn log(s: &str);
-
-    // The `console.log` is quite polymorphic, so we can bind it with multiple
-    // signatures. Note that we need to use `js_name` to ensure we always call
-    // `log` in JS.
-    #[wasm_bindgen(js_namespace = console, js_name = log)]
-    fn log_u32(a: u32);
-
-    // Multiple arguments too!
-    #[wasm_bindgen(js_namespace = console, js_name = log)]
-    fn log_many(a: &str, b: &str);
+impl std::fmt::Display for ParseSchematicError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "invalid schematic: {}", self.0)
+    }
 }
 
-macro_rules! console_log {
-    // Note that this is using the `log` function imported above during
-    // `bare_bones`
-    ($($t:tt)*) => (log(&format_args!($($t)*).to_string()))
-}
+impl std::error::Error for ParseSchematicError {}
 
 #[derive(Debug, Clone)]
 pub struct CanvasMod {
@@ -45,10 +32,18 @@ impl CanvasMod {
     }
 }
 
+impl Default for CanvasMod {
+    fn default() -> CanvasMod {
+        CanvasMod::new()
+    }
+}
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Point {
     pub x: f64,
     pub y: f64,
+    #[cfg_attr(feature = "serde", serde(rename = "angle"))]
     pub a: f64,
 }
 
@@ -63,6 +58,7 @@ impl Point {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum StrokeFormat {
     Dash,
     DashDot,
@@ -73,10 +69,11 @@ pub enum StrokeFormat {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Stroke {
     pub width: f64,
     pub format: StrokeFormat,
-    pub color: (u8, u8, u8, u8), // todo real color obj
+    pub color: Option<Color>, // none = theme color
 }
 
 impl Stroke {
@@ -84,18 +81,20 @@ impl Stroke {
         Stroke {
             width: 0.0,
             format: StrokeFormat::Default,
-            color: (0, 0, 0, 0),
+            color: None,
         }
     }
 }
 
-type UUID = String; // todo : real uuid obj
+pub use crate::uuid::Uuid;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Wire {
+    #[cfg_attr(feature = "serde", serde(rename = "points"))]
     pub poss: Vec<Point>,
     pub stroke: Stroke,
-    pub uuid: UUID,
+    pub uuid: Uuid,
 }
 
 impl Wire {
@@ -103,12 +102,13 @@ impl Wire {
         Wire {
             poss: Vec::<Point>::new(),
             stroke: Stroke::blank(),
-            uuid: "".to_string(),
+            uuid: Uuid::nil(),
         }
     }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FillType {
     None,
     Outline,
@@ -116,11 +116,13 @@ pub enum FillType {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Rect {
+    #[cfg_attr(feature = "serde", serde(rename = "corners"))]
     pub poss: (Point, Point),
     pub stroke: Stroke,
     pub fill: FillType,
-    pub uuid: UUID,
+    pub uuid: Uuid,
 }
 
 impl Rect {
@@ -129,18 +131,19 @@ impl Rect {
             poss: (Point::blank(), Point::blank()),
             stroke: Stroke::blank(),
             fill: FillType::None,
-            uuid: "".to_string(),
+            uuid: Uuid::nil(),
         }
     }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Circ {
     pub pos: Point,
     pub radius: f64,
     pub stroke: Stroke,
     pub fill: FillType,
-    pub uuid: UUID,
+    pub uuid: Uuid,
 }
 
 impl Circ {
@@ -150,17 +153,18 @@ impl Circ {
             radius: 0.0,
             stroke: Stroke::blank(),
             fill: FillType::None,
-            uuid: "".to_string(),
+            uuid: Uuid::nil(),
         }
     }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Junction {
     pub pos: Point,
     pub diameter: f64,
-    pub color: (u8, u8, u8, u8),
-    pub uuid: UUID,
+    pub color: Option<Color>, // none = theme color
+    pub uuid: Uuid,
 }
 
 impl Junction {
@@ -172,18 +176,19 @@ impl Junction {
                 a: 0.0,
             },
             diameter: 1.0,
-            color: (0, 0, 0, 0),
-            uuid: "".to_string(),
+            color: None,
+            uuid: Uuid::nil(),
         }
     }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Text {
     pub text: String,
     pub pos: Point,
-    // todo : effect
-    pub uuid: UUID,
+    pub effect: Effect,
+    pub uuid: Uuid,
 }
 
 impl Text {
@@ -195,12 +200,14 @@ impl Text {
                 y: 0.0,
                 a: 0.0,
             },
-            uuid: "".to_string(),
+            effect: Effect::blank(),
+            uuid: Uuid::nil(),
         }
     }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Effect {
     pub font_name: String,
     pub size: (f64, f64),
@@ -210,6 +217,7 @@ pub struct Effect {
     pub line_spacing: f64,
     pub justify: (bool, bool, bool),
     pub hide: bool,
+    pub color: Option<Color>, // none = theme color
 }
 
 impl Effect {
@@ -223,16 +231,19 @@ impl Effect {
             line_spacing: 0.0,
             justify: (false, false, false),
             hide: false,
+            color: None,
         }
     }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Polyline {
+    #[cfg_attr(feature = "serde", serde(rename = "points"))]
     pub poss: Vec<Point>,
     pub stroke: Stroke,
     pub fill: FillType,
-    pub uuid: UUID,
+    pub uuid: Uuid,
 }
 
 impl Polyline {
@@ -241,17 +252,19 @@ impl Polyline {
             poss: Vec::<Point>::new(),
             stroke: Stroke::blank(),
             fill: FillType::None,
-            uuid: "".to_string(),
+            uuid: Uuid::nil(),
         }
     }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Arc {
+    #[cfg_attr(feature = "serde", serde(rename = "points"))]
     pub poss: (Point, Point, Point), /* start, mid, end*/
     pub stroke: Stroke,
     pub fill: FillType,
-    pub uuid: UUID,
+    pub uuid: Uuid,
 } // todo
 
 impl Arc {
@@ -260,12 +273,13 @@ impl Arc {
             poss: (Point::blank(), Point::blank(), Point::blank()),
             stroke: Stroke::blank(),
             fill: FillType::None,
-            uuid: "".to_string(),
+            uuid: Uuid::nil(),
         }
     }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Property {
     pub key: String,
     pub value: String,
@@ -289,30 +303,70 @@ impl Property {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PinType {
+    Input,
+    Output,
+    Bidirectional,
+    TriState,
+    Passive,
+    Free,
+    Unspecified,
+    PowerIn,
+    PowerOut,
+    OpenCollector,
+    OpenEmitter,
+    NoConnect,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PinStyle {
+    Line,
+    Inverted,
+    Clock,
+    InvertedClock,
+    InputLow,
+    ClockLow,
+    OutputLow,
+    EdgeClockHigh,
+    NonLogic,
+}
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pin {
-    // type "passive"
-    // type2? "line"
+    pub kind: PinType,
+    pub style: PinStyle,
     pub pos: Point,
+    #[cfg_attr(feature = "serde", serde(rename = "length"))]
     pub len: f64,
     pub name: (String, Effect),
-    pub numb: (i32, Effect),
+    #[cfg_attr(feature = "serde", serde(rename = "number"))]
+    pub numb: (String, Effect),
+    pub hide: bool,
 }
 
 impl Pin {
     pub fn blank() -> Pin {
         Pin {
+            kind: PinType::Unspecified,
+            style: PinStyle::Line,
             pos: Point::blank(),
             len: 0.0,
             name: ("".to_string(), Effect::blank()),
-            numb: (0i32, Effect::blank()),
+            numb: ("".to_string(), Effect::blank()),
+            hide: false,
         }
     }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Symbol {
     pub id: String,
+    pub unit: i32, // 0 = common to all units
     pub lines: Vec<Polyline>,
     pub arcs: Vec<Arc>,
     pub pins: Vec<Pin>,
@@ -324,6 +378,7 @@ impl Symbol {
     pub fn blank() -> Symbol {
         Symbol {
             id: "".to_string(),
+            unit: 0,
             lines: Vec::<Polyline>::new(),
             arcs: Vec::<Arc>::new(),
             pins: Vec::<Pin>::new(),
@@ -334,34 +389,43 @@ impl Symbol {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SymbolTemp {
     pub id: String,
+    pub power: bool,
     pub props: Vec<Property>,
     pub pos: Point, // todo : no pos on template
+    #[cfg_attr(feature = "serde", serde(rename = "units"))]
     pub symbs: Vec<Symbol>,
-    pub uuid: UUID,
+    pub uuid: Uuid,
 }
 
 impl SymbolTemp {
     pub fn blank() -> SymbolTemp {
         SymbolTemp {
             id: "".to_string(),
+            power: false,
             props: Vec::<Property>::new(),
             pos: Point::blank(), // todo : not a thing
             symbs: Vec::<Symbol>::new(),
-            uuid: "".to_string(),
+            uuid: Uuid::nil(),
         }
     }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SymbolInst {
+    #[cfg_attr(feature = "serde", serde(rename = "lib_id"))]
     pub id: String,
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub parent: Option<SymbolTemp>,
     pub props: Vec<Property>,
     pub pos: Point,
     pub mirror: (bool, bool),
-    pub uuid: UUID,
+    pub unit: i32,
+    pub in_bom: bool,
+    pub uuid: Uuid,
 }
 
 impl SymbolInst {
@@ -372,12 +436,15 @@ impl SymbolInst {
             props: Vec::<Property>::new(),
             pos: Point::blank(),
             mirror: (false, false),
-            uuid: "".to_string(),
+            unit: 1,
+            in_bom: true,
+            uuid: Uuid::nil(),
         }
     }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Style {
     Heir,
     Global,
@@ -386,12 +453,15 @@ pub enum Style {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Label {
+    #[cfg_attr(feature = "serde", serde(rename = "text"))]
     pub id: String,
+    #[cfg_attr(feature = "serde", serde(rename = "kind"))]
     pub shape: Style,
     pub pos: Point,
     pub effect: Effect,
-    pub uuid: UUID,
+    pub uuid: Uuid,
 }
 
 impl Label {
@@ -401,33 +471,126 @@ impl Label {
             shape: Style::Heir,
             pos: Point::blank(),
             effect: Effect::blank(),
-            uuid: "".to_string(),
+            uuid: Uuid::nil(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SheetPin {
+    pub name: String,
+    pub pos: Point,
+    pub effect: Effect,
+    pub uuid: Uuid,
+}
+
+impl SheetPin {
+    pub fn blank() -> SheetPin {
+        SheetPin {
+            name: "".to_string(),
+            pos: Point::blank(),
+            effect: Effect::blank(),
+            uuid: Uuid::nil(),
+        }
+    }
+}
+
+// a hierarchical sheet symbol, pointing at another schematic file
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sheet {
+    pub pos: Point,
+    pub size: (f64, f64),
+    pub stroke: Stroke,
+    pub fill: Option<Color>,
+    pub props: Vec<Property>,
+    pub pins: Vec<SheetPin>,
+    pub uuid: Uuid,
+}
+
+impl Sheet {
+    pub fn blank() -> Sheet {
+        Sheet {
+            pos: Point::blank(),
+            size: (0.0, 0.0),
+            stroke: Stroke::blank(),
+            fill: None,
+            props: Vec::<Property>::new(),
+            pins: Vec::<SheetPin>::new(),
+            uuid: Uuid::nil(),
         }
     }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Page {
-    A4
+    A5,
+    A4,
+    A3,
+    A2,
+    A1,
+    A0,
+    A,
+    B,
+    C,
+    D,
+    E,
+    USLetter,
+    USLegal,
+    USLedger,
+    User(f64, f64),
+}
+
+// points at an item inside a schematic by index
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ItemRef {
+    Wire(usize),
+    Junction(usize),
+    Text(usize),
+    Polyline(usize),
+    Label(usize),
+    Symbol(usize),
+    Sheet(usize),
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Schematic {
+    pub uuid: Uuid,
     pub wires: Vec<Wire>,
+    #[cfg_attr(feature = "serde", serde(rename = "junctions"))]
     pub juncs: Vec<Junction>,
     pub texts: Vec<Text>,
+    #[cfg_attr(feature = "serde", serde(rename = "polylines"))]
     pub polys: Vec<Polyline>,
     pub labels: Vec<Label>,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::json::sorted"))]
     pub lib: HashMap<String, SymbolTemp>,
+    #[cfg_attr(feature = "serde", serde(rename = "symbols"))]
     pub symbs: Vec<SymbolInst>,
+    pub sheets: Vec<Sheet>,
     //
     pub version: i32,
     pub page: Page,
+    pub portrait: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub index: HashMap<Uuid, ItemRef>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub spatial: SpatialIndex,
+    // the parsed file, kept so writing it back preserves what isn't modelled
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub source: Option<Document>,
+    // problems the parser worked around, like ids that aren't uuids
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub warnings: Vec<String>,
 }
 
 impl Schematic {
     pub fn blank() -> Schematic {
         Schematic {
+            uuid: Uuid::nil(),
             wires: Vec::<Wire>::new(),
             juncs: Vec::<Junction>::new(),
             texts: Vec::<Text>::new(),
@@ -435,13 +598,82 @@ impl Schematic {
             labels: Vec::<Label>::new(),
             lib: HashMap::<String, SymbolTemp>::new(),
             symbs: Vec::<SymbolInst>::new(),
+            sheets: Vec::<Sheet>::new(),
             version: 0i32,
             page: Page::A4,
+            portrait: false,
+            index: HashMap::<Uuid, ItemRef>::new(),
+            spatial: SpatialIndex::new(),
+            source: None,
+            warnings: Vec::<String>::new(),
         }
     }
 
+    // fails on truncated or malformed files and on symbols missing from
+    // lib_symbols
+    pub fn parse(file: &str) -> Result<Schematic, ParseSchematicError> {
+        let sexp = match symbolic_expressions::parser::parse_str(file) {
+            Ok(sexp) => sexp,
+            // our own parser gives the byte offset
+            Err(err) => return Err(ParseSchematicError(match Document::parse(file) {
+                Err(offset) => offset.to_string(),
+                Ok(_) => err.to_string(),
+            })),
+        };
+        let mut schem = Schematic::try_from_sexp(&sexp)?;
+        schem.source = Document::parse(file).ok();
+        Ok(schem)
+    }
+
+    // panics on malformed files, see `parse`
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(file: &str) -> Schematic {
-        let sexp = &symbolic_expressions::parser::parse_str(file).unwrap();
-        Schematic::from_sexp(sexp)
-    }    
+        Schematic::parse(file).unwrap()
+    }
+
+    // rebuilds the uuid lookup and the spatial index, must be called after
+    // items are added or removed
+    pub fn reindex(&mut self) {
+        let mut index = HashMap::<Uuid, ItemRef>::new();
+        for (i, item) in self.wires.iter().enumerate() {
+            index.insert(item.uuid, ItemRef::Wire(i));
+        }
+        for (i, item) in self.juncs.iter().enumerate() {
+            index.insert(item.uuid, ItemRef::Junction(i));
+        }
+        for (i, item) in self.texts.iter().enumerate() {
+            index.insert(item.uuid, ItemRef::Text(i));
+        }
+        for (i, item) in self.polys.iter().enumerate() {
+            index.insert(item.uuid, ItemRef::Polyline(i));
+        }
+        for (i, item) in self.labels.iter().enumerate() {
+            index.insert(item.uuid, ItemRef::Label(i));
+        }
+        for (i, item) in self.symbs.iter().enumerate() {
+            index.insert(item.uuid, ItemRef::Symbol(i));
+        }
+        for (i, item) in self.sheets.iter().enumerate() {
+            index.insert(item.uuid, ItemRef::Sheet(i));
+        }
+        index.remove(&Uuid::nil());
+        self.index = index;
+        self.spatial = self.build_spatial_index();
+    }
+
+    pub fn find_by_uuid(&self, uuid: &Uuid) -> Option<ItemRef> {
+        self.index.get(uuid).copied()
+    }
+
+    pub fn item_uuid(&self, item: ItemRef) -> Uuid {
+        match item {
+            ItemRef::Wire(i) => self.wires[i].uuid,
+            ItemRef::Junction(i) => self.juncs[i].uuid,
+            ItemRef::Text(i) => self.texts[i].uuid,
+            ItemRef::Polyline(i) => self.polys[i].uuid,
+            ItemRef::Label(i) => self.labels[i].uuid,
+            ItemRef::Symbol(i) => self.symbs[i].uuid,
+            ItemRef::Sheet(i) => self.sheets[i].uuid,
+        }
+    }
 }
@@ -0,0 +1,382 @@
+use crate::schematic::*;
+use std::cmp::Ordering;
+
+// what `Schematic::search` looks through
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+    pub references: bool,
+    pub values: bool,
+    pub fields: bool, // every other symbol and sheet field
+    pub labels: bool, // labels and sheet pins
+    pub nets: bool,   // netlist names, "Net-(R1-Pad1)" for unnamed nets
+    pub text: bool,
+    pub case_sensitive: bool,
+}
+
+impl SearchOptions {
+    pub fn new() -> SearchOptions {
+        SearchOptions {
+            references: true,
+            values: true,
+            fields: true,
+            labels: true,
+            nets: true,
+            text: true,
+            case_sensitive: false,
+        }
+    }
+}
+
+impl Default for SearchOptions {
+    fn default() -> SearchOptions {
+        SearchOptions::new()
+    }
+}
+
+// one item that matched, the best of its texts
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SearchHit {
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub item: ItemRef,
+    pub uuid: Uuid,
+    pub field: String, // "Reference", "Value", another field, "Label", "Pin", "Net" or "Text"
+    pub text: String,
+    pub pos: Point, // the item's anchor
+    pub bounds: Option<(Point, Point)>,
+    pub score: u32, // lower is better: how well it matched, then what
+}
+
+// how well a text matched, best first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Fit {
+    Exact,
+    Prefix,
+    Within,
+}
+
+// what matched, most wanted first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Kind {
+    Reference,
+    Value,
+    Label,
+    Net,
+    Field,
+    Text,
+}
+
+// a query with * and ? matches whole texts, anything else matches within
+// them
+struct Matcher {
+    pattern: String,
+    glob: bool,
+    case_sensitive: bool,
+}
+
+impl Matcher {
+    fn new(query: &str, case_sensitive: bool) -> Matcher {
+        Matcher {
+            pattern: match case_sensitive {
+                true => query.to_string(),
+                false => query.to_lowercase(),
+            },
+            glob: query.contains(['*', '?']),
+            case_sensitive,
+        }
+    }
+
+    fn fit(&self, text: &str) -> Option<Fit> {
+        let text = match self.case_sensitive {
+            true => text.to_string(),
+            false => text.to_lowercase(),
+        };
+        if text == self.pattern {
+            return Some(Fit::Exact);
+        }
+        if self.glob {
+            let pattern = self.pattern.chars().collect::<Vec<_>>();
+            let text = text.chars().collect::<Vec<_>>();
+            return match glob(&pattern, &text) {
+                true => Some(Fit::Prefix),
+                false => None,
+            };
+        }
+        if text.starts_with(&self.pattern) {
+            Some(Fit::Prefix)
+        } else if text.contains(&self.pattern) {
+            Some(Fit::Within)
+        } else {
+            None
+        }
+    }
+}
+
+// * is any run of characters, ? any one
+fn glob(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => (0..=text.len()).any(|skip| glob(rest, &text[skip..])),
+        Some((c, rest)) => match text.split_first() {
+            Some((t, text)) if *c == '?' || c == t => glob(rest, text),
+            _ => false,
+        },
+    }
+}
+
+fn field(prop: &Property) -> (Kind, &str, &str) {
+    let kind = match prop.key.as_str() {
+        "Reference" => Kind::Reference,
+        "Value" => Kind::Value,
+        _ => Kind::Field,
+    };
+    (kind, prop.key.as_str(), prop.value.as_str())
+}
+
+// "R2" before "R10"
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let split = |text: &str| {
+        let head = text.trim_end_matches(|c: char| c.is_ascii_digit());
+        let number = text[head.len()..].parse::<u64>().unwrap_or(0);
+        (head.to_string(), number)
+    };
+    split(a).cmp(&split(b)).then_with(|| a.cmp(b))
+}
+
+impl Schematic {
+    // items with a text matching the query, best first and then in
+    // reference order
+    pub fn search(&self, query: &str, options: &SearchOptions) -> Vec<SearchHit> {
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let matcher = Matcher::new(query, options.case_sensitive);
+        let mut hits = Vec::<SearchHit>::new();
+        let hit = |item: ItemRef, pos: &Point, texts: Vec<(Kind, &str, &str)>| {
+            let (fit, kind, field, text) = texts
+                .into_iter()
+                .filter_map(|(kind, field, text)| Some((matcher.fit(text)?, kind, field, text)))
+                .min_by_key(|(fit, kind, _, _)| (*fit, *kind))?;
+            Some(SearchHit {
+                item,
+                uuid: self.item_uuid(item),
+                field: field.to_string(),
+                text: text.to_string(),
+                pos: pos.clone(),
+                bounds: self.item_bounds(item),
+                score: fit as u32 * 6 + kind as u32,
+            })
+        };
+        let mut add = |item: ItemRef, pos: &Point, texts: Vec<(Kind, &str, &str)>| {
+            hits.extend(hit(item, pos, texts));
+        };
+
+        let wanted = |(kind, _, _): &(Kind, &str, &str)| match kind {
+            Kind::Reference => options.references,
+            Kind::Value => options.values,
+            Kind::Label => options.labels,
+            Kind::Net => options.nets,
+            Kind::Field => options.fields,
+            Kind::Text => options.text,
+        };
+        for (index, symb) in self.symbs.iter().enumerate() {
+            let texts = symb.props.iter().map(field).filter(wanted).collect();
+            add(ItemRef::Symbol(index), &symb.pos, texts);
+        }
+        for (index, sheet) in self.sheets.iter().enumerate() {
+            let mut texts = sheet.props.iter().map(field).collect::<Vec<_>>();
+            for pin in &sheet.pins {
+                texts.push((Kind::Label, "Pin", pin.name.as_str()));
+            }
+            texts.retain(wanted);
+            add(ItemRef::Sheet(index), &sheet.pos, texts);
+        }
+        if options.labels {
+            for (index, label) in self.labels.iter().enumerate() {
+                if !matches!(label.shape, Style::Noconn) {
+                    let texts = vec![(Kind::Label, "Label", label.id.as_str())];
+                    add(ItemRef::Label(index), &label.pos, texts);
+                }
+            }
+        }
+        if options.text {
+            for (index, text) in self.texts.iter().enumerate() {
+                let texts = vec![(Kind::Text, "Text", text.text.as_str())];
+                add(ItemRef::Text(index), &text.pos, texts);
+            }
+        }
+        if options.nets {
+            let netlist = self.netlist();
+            for net in &netlist.nets {
+                // a pin on its own isn't much of a net
+                if net.pins.len() < 2 && net.wires.is_empty() && net.labels.is_empty() {
+                    continue;
+                }
+                // found at a wire, a label or else a pin of it, framed whole
+                let (item, pos) = match (net.wires.first(), net.labels.first(), net.pins.first()) {
+                    (Some(wire), _, _) => (ItemRef::Wire(*wire), &self.wires[*wire].poss[0]),
+                    (None, Some(label), _) => (ItemRef::Label(*label), &self.labels[*label].pos),
+                    (None, None, Some(pin)) => (ItemRef::Symbol(pin.symb), &pin.pos),
+                    _ => continue,
+                };
+                let texts = vec![(Kind::Net, "Net", net.name.as_str())];
+                if let Some(found) = hit(item, pos, texts) {
+                    let bounds = self
+                        .net_items(net)
+                        .into_iter()
+                        .filter_map(|item| self.item_bounds(item))
+                        .reduce(|a, b| {
+                            (
+                                Point::new(a.0.x.min(b.0.x), a.0.y.min(b.0.y)),
+                                Point::new(a.1.x.max(b.1.x), a.1.y.max(b.1.y)),
+                            )
+                        });
+                    hits.push(SearchHit {
+                        bounds: bounds.or(found.bounds),
+                        ..found
+                    });
+                }
+            }
+        }
+
+        hits.sort_by(|a, b| {
+            a.score
+                .cmp(&b.score)
+                .then_with(|| natural_cmp(&a.text, &b.text))
+                .then_with(|| a.pos.y.total_cmp(&b.pos.y))
+                .then_with(|| a.pos.x.total_cmp(&b.pos.x))
+        });
+        hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::netlist::tests::{label, part, sheet, symbol, wire};
+
+    fn globs(pattern: &str, text: &str) -> bool {
+        let chars = |text: &str| text.chars().collect::<Vec<_>>();
+        glob(&chars(pattern), &chars(text))
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(globs("R*", "R10"));
+        assert!(globs("R*", "R"));
+        assert!(globs("*", ""));
+        assert!(globs("R?", "R1"));
+        assert!(!globs("R?", "R10"));
+        assert!(!globs("R?", "R"));
+        assert!(globs("*V3*", "+3V3_A"));
+        assert!(globs("a*b?c*", "a-b-bxc"));
+        assert!(!globs("a*b?c", "a-b-bxcd"));
+
+        let matcher = Matcher::new("u*", false);
+        assert_eq!(matcher.fit("U12"), Some(Fit::Prefix));
+        assert_eq!(matcher.fit("XU1"), None);
+        assert_eq!(Matcher::new("u1", false).fit("U1"), Some(Fit::Exact));
+        assert_eq!(Matcher::new("u1", true).fit("U1"), None);
+        assert_eq!(Matcher::new("V3", false).fit("+3V3"), Some(Fit::Within));
+    }
+
+    fn design() -> Schematic {
+        let lib = vec![part("R", "passive", false, false)];
+        let text = "(text \"see R1\" (at 0 40 0) (effects (font (size 1.27 1.27)))
+            (uuid 00000000-0000-4000-8000-000000000099))";
+        sheet(
+            &lib,
+            &[
+                symbol("R", "R10", "1k", 0.0, 0.0, 1),
+                symbol("R", "R2", "R1", 10.0, 0.0, 2),
+                symbol("R", "R1", "10k", 20.0, 0.0, 3),
+                label("label", "R1_EN", 30.0, 0.0, 4),
+                text.to_string(),
+            ],
+        )
+    }
+
+    fn found(schem: &Schematic, query: &str, options: &SearchOptions) -> Vec<String> {
+        schem
+            .search(query, options)
+            .into_iter()
+            .map(|hit| format!("{}:{}", hit.field, hit.text))
+            .collect()
+    }
+
+    #[test]
+    fn ranking() {
+        let schem = design();
+        let options = SearchOptions::new();
+        // exact before prefix before within, then references, values,
+        // labels, nets and text, then in reference order
+        assert_eq!(
+            found(&schem, "r1", &options),
+            [
+                "Reference:R1",
+                "Value:R1",
+                "Reference:R10",
+                "Label:R1_EN",
+                "Net:R1_EN",
+                "Text:see R1"
+            ]
+        );
+        assert_eq!(
+            found(&schem, "R*", &options),
+            [
+                "Reference:R1",
+                "Reference:R2",
+                "Reference:R10",
+                "Label:R1_EN",
+                "Net:R1_EN"
+            ]
+        );
+        assert_eq!(found(&schem, "", &options), Vec::<String>::new());
+
+        let options = SearchOptions {
+            references: false,
+            case_sensitive: true,
+            ..SearchOptions::new()
+        };
+        assert_eq!(
+            found(&schem, "R1", &options),
+            ["Value:R1", "Label:R1_EN", "Net:R1_EN", "Text:see R1"]
+        );
+        assert!(found(&schem, "r1", &options).is_empty());
+    }
+
+    #[test]
+    fn net_names() {
+        let lib = vec![part("R", "passive", false, false)];
+        let schem = sheet(
+            &lib,
+            &[
+                symbol("R", "R2", "1k", 10.0, 0.0, 1),
+                symbol("R", "R1", "1k", 0.0, 0.0, 2),
+                wire((0.0, 0.0), (10.0, 0.0), 3),
+                symbol("R", "R3", "1k", 50.0, 0.0, 4),
+            ],
+        );
+        let options = SearchOptions::new();
+        // unnamed nets go by their first pin, a lone pin isn't a net
+        assert_eq!(found(&schem, "*pad1*", &options), ["Net:Net-(R1-Pad1)"]);
+        let hit = &schem.search("Net-(R1-Pad1)", &options)[0];
+        assert_eq!(hit.item, ItemRef::Wire(0));
+        assert_eq!(hit.uuid, schem.wires[0].uuid);
+        let (min, max) = hit.bounds.clone().unwrap();
+        assert!(min.x <= 0.0 && max.x >= 10.0);
+
+        let options = SearchOptions {
+            nets: false,
+            ..SearchOptions::new()
+        };
+        assert!(found(&schem, "*pad1*", &options).is_empty());
+    }
+
+    #[test]
+    fn natural_order() {
+        let mut references = vec!["R10", "C1", "R2", "R1", "U1A", "R", "R02"];
+        references.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(references, ["C1", "R", "R1", "R02", "R2", "R10", "U1A"]);
+    }
+}
@@ -0,0 +1,428 @@
+// lossless s-expression tree, every node keeps the whitespace before it and
+// atoms keep their source text (quotes and escapes included), so writing an
+// untouched tree gives back the exact bytes it was parsed from
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Atom {
+        space: String,
+        text: String,
+    },
+    List {
+        space: String,
+        items: Vec<Node>,
+        end: String, // whitespace before the ')'
+    },
+}
+
+// a whole file, the root list and whatever follows it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    pub root: Node,
+    pub tail: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseSexprError(pub usize); // byte offset
+
+impl std::fmt::Display for ParseSexprError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "malformed s-expression at byte {}", self.0)
+    }
+}
+
+impl std::error::Error for ParseSexprError {}
+
+fn is_space(c: char) -> bool {
+    c == ' ' || c == '\t' || c == '\n' || c == '\r'
+}
+
+// strings are kept escaped the way kicad writes them (a newline stays "\n"),
+// like the rest of the model, quoting only escapes what isn't yet
+pub fn quote(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                out.push(c);
+                out.extend(chars.next());
+            }
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// kicad's number format: at most 4 decimals (its internal units), no
+// trailing zeros and no "-0"
+pub fn number(value: f64) -> String {
+    let text = format!("{:.4}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    match text {
+        "-0" | "" => "0".to_string(),
+        _ => text.to_string(),
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn space(&mut self) -> String {
+        let rest = &self.text[self.pos..];
+        let len = rest.len() - rest.trim_start_matches(is_space).len();
+        self.pos += len;
+        rest[..len].to_string()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn node(&mut self, space: String) -> Result<Node, ParseSexprError> {
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let mut items = Vec::<Node>::new();
+                loop {
+                    let before = self.space();
+                    match self.peek() {
+                        Some(')') => {
+                            self.pos += 1;
+                            return Ok(Node::List {
+                                space,
+                                items,
+                                end: before,
+                            });
+                        }
+                        Some(_) => items.push(self.node(before)?),
+                        None => return Err(ParseSexprError(self.pos)),
+                    }
+                }
+            }
+            Some(')') | None => Err(ParseSexprError(self.pos)),
+            Some(_) => Ok(Node::Atom {
+                space,
+                text: self.atom()?.to_string(),
+            }),
+        }
+    }
+
+    fn atom(&mut self) -> Result<&'a str, ParseSexprError> {
+        let start = self.pos;
+        let rest = &self.text[start..];
+        let len = if rest.starts_with('"') {
+            let mut escaped = false;
+            let (close, _) = rest
+                .char_indices()
+                .skip(1)
+                .find(|(_, c)| {
+                    let found = *c == '"' && !escaped;
+                    escaped = *c == '\\' && !escaped;
+                    found
+                })
+                .ok_or(ParseSexprError(start))?;
+            close + 1
+        } else {
+            rest.find(|c: char| is_space(c) || c == '(' || c == ')' || c == '"')
+                .unwrap_or(rest.len())
+        };
+        self.pos += len;
+        Ok(&rest[..len])
+    }
+}
+
+impl Node {
+    pub fn atom(text: &str) -> Node {
+        Node::Atom {
+            space: " ".to_string(),
+            text: text.to_string(),
+        }
+    }
+
+    pub fn string(text: &str) -> Node {
+        Node::atom(&quote(text))
+    }
+
+    pub fn number(value: f64) -> Node {
+        Node::atom(&number(value))
+    }
+
+    // a single node from text, like `(at 1 2 0)`
+    pub fn parse(text: &str) -> Result<Node, ParseSexprError> {
+        let mut parser = Parser { text, pos: 0 };
+        let space = parser.space();
+        let node = parser.node(space)?;
+        parser.space();
+        match parser.pos == text.len() {
+            true => Ok(node),
+            false => Err(ParseSexprError(parser.pos)),
+        }
+    }
+
+    pub fn space(&self) -> &str {
+        match self {
+            Node::Atom { space, .. } | Node::List { space, .. } => space,
+        }
+    }
+
+    pub fn set_space(&mut self, value: &str) {
+        match self {
+            Node::Atom { space, .. } | Node::List { space, .. } => *space = value.to_string(),
+        }
+    }
+
+    pub fn is_list(&self) -> bool {
+        matches!(self, Node::List { .. })
+    }
+
+    pub fn items(&self) -> &[Node] {
+        match self {
+            Node::List { items, .. } => items,
+            Node::Atom { .. } => &[],
+        }
+    }
+
+    // panics on atoms
+    pub fn items_mut(&mut self) -> &mut Vec<Node> {
+        match self {
+            Node::List { items, .. } => items,
+            Node::Atom { .. } => panic!("not a list"),
+        }
+    }
+
+    // source text of an atom
+    pub fn raw(&self) -> Option<&str> {
+        match self {
+            Node::Atom { text, .. } => Some(text),
+            Node::List { .. } => None,
+        }
+    }
+
+    // value of an atom without its quotes, still escaped
+    pub fn text(&self) -> Option<String> {
+        let raw = self.raw()?;
+        match raw.starts_with('"') {
+            true => Some(raw[1..raw.len() - 1].to_string()),
+            false => Some(raw.to_string()),
+        }
+    }
+
+    pub fn to_f64(&self) -> Option<f64> {
+        self.raw()?.parse::<f64>().ok()
+    }
+
+    // first atom of a list, `wire` for `(wire ...)`
+    pub fn head(&self) -> Option<&str> {
+        self.items().first()?.raw()
+    }
+
+    pub fn child(&self, head: &str) -> Option<&Node> {
+        self.items().iter().find(|item| item.head() == Some(head))
+    }
+
+    pub fn child_mut(&mut self, head: &str) -> Option<&mut Node> {
+        match self {
+            Node::List { items, .. } => items.iter_mut().find(|item| item.head() == Some(head)),
+            Node::Atom { .. } => None,
+        }
+    }
+
+    // text of the atom after the head, `x` for `(lib_id "x")`
+    pub fn value(&self, head: &str) -> Option<String> {
+        self.child(head)?.items().get(1)?.text()
+    }
+
+    // replaces an atom's text only when its value changed, so untouched
+    // values keep their source form
+    pub fn set_text(&mut self, value: &str) {
+        if self.text().as_deref() == Some(value) {
+            return;
+        }
+        let quoted = self.raw().map(|raw| raw.starts_with('"')).unwrap_or(true);
+        let text = match quoted {
+            true => quote(value),
+            false => value.to_string(),
+        };
+        let space = self.space().to_string();
+        *self = Node::Atom { space, text };
+    }
+
+    pub fn set_string(&mut self, value: &str) {
+        if self.text().as_deref() == Some(value) && self.raw().unwrap().starts_with('"') {
+            return;
+        }
+        let space = self.space().to_string();
+        *self = Node::Atom {
+            space,
+            text: quote(value),
+        };
+    }
+
+    pub fn set_f64(&mut self, value: f64) {
+        if let Some(old) = self.to_f64() {
+            if (old - value).abs() < 1e-9 {
+                return;
+            }
+        }
+        let space = self.space().to_string();
+        *self = Node::Atom {
+            space,
+            text: number(value),
+        };
+    }
+
+    pub fn write(&self, out: &mut String) {
+        match self {
+            Node::Atom { space, text } => {
+                out.push_str(space);
+                out.push_str(text);
+            }
+            Node::List { space, items, end } => {
+                out.push_str(space);
+                out.push('(');
+                for item in items {
+                    item.write(out);
+                }
+                out.push_str(end);
+                out.push(')');
+            }
+        }
+    }
+
+    // single line form, `(a (b c))`
+    pub fn compact(&self) -> Node {
+        match self {
+            Node::Atom { text, .. } => Node::atom(text),
+            Node::List { items, .. } => {
+                let mut items = items.iter().map(Node::compact).collect::<Vec<_>>();
+                if let Some(first) = items.first_mut() {
+                    first.set_space("");
+                }
+                Node::List {
+                    space: " ".to_string(),
+                    items,
+                    end: String::new(),
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Node {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut out = String::new();
+        self.write(&mut out);
+        f.write_str(&out)
+    }
+}
+
+impl Document {
+    pub fn parse(text: &str) -> Result<Document, ParseSexprError> {
+        let mut parser = Parser { text, pos: 0 };
+        let space = parser.space();
+        let root = parser.node(space)?;
+        let tail = parser.space();
+        match parser.pos == text.len() {
+            true => Ok(Document { root, tail }),
+            false => Err(ParseSexprError(parser.pos)),
+        }
+    }
+
+    pub fn write(&self) -> String {
+        let mut out = String::new();
+        self.root.write(&mut out);
+        out.push_str(&self.tail);
+        out
+    }
+}
+
+// kicad 8's layout (its `Prettify`): tab indents, every list on its own line
+// except runs of (xy) points, a list that held a list or wrapped closes on a
+// line of its own
+pub fn prettify(node: &Node, depth: usize) -> Node {
+    let mut text = String::new();
+    let mut state = Pretty {
+        depth,
+        column: depth,
+        in_xy: false,
+        multi_line: false,
+        last: None,
+    };
+    state.write(&node.compact(), &mut text);
+    let mut node = Node::parse(&text).unwrap();
+    node.set_space(&format!("\n{}", "\t".repeat(depth)));
+    node
+}
+
+struct Pretty {
+    depth: usize,
+    column: usize,
+    in_xy: bool,
+    multi_line: bool,
+    last: Option<char>,
+}
+
+impl Pretty {
+    const XY_COLUMN_LIMIT: usize = 99;
+    const WRAP_COLUMN: usize = 72;
+
+    fn newline(&mut self, out: &mut String) {
+        out.push('\n');
+        out.push_str(&"\t".repeat(self.depth));
+        self.column = self.depth;
+    }
+
+    fn write(&mut self, node: &Node, out: &mut String) {
+        match node {
+            Node::Atom { text, .. } => {
+                if self.last.is_some() && self.last != Some('(') {
+                    if self.in_xy || self.column < Pretty::WRAP_COLUMN {
+                        out.push(' ');
+                        self.column += 1;
+                    } else {
+                        self.newline(out);
+                        self.multi_line = true;
+                    }
+                }
+                out.push_str(text);
+                self.column += text.chars().count();
+                self.last = text.chars().last();
+            }
+            Node::List { items, .. } => {
+                let is_xy = node.head() == Some("xy");
+                if self.last.is_none() {
+                    // the node itself, placed by the caller
+                } else if self.in_xy && is_xy && self.column < Pretty::XY_COLUMN_LIMIT {
+                    out.push(' ');
+                    self.column += 1;
+                } else {
+                    self.newline(out);
+                }
+                out.push('(');
+                self.column += 1;
+                self.last = Some('(');
+                self.in_xy = is_xy;
+                self.depth += 1;
+                for item in items {
+                    self.write(item, out);
+                }
+                self.depth -= 1;
+                if self.last == Some(')') || self.multi_line {
+                    self.newline(out);
+                    self.multi_line = false;
+                }
+                out.push(')');
+                self.column += 1;
+                self.last = Some(')');
+            }
+        }
+    }
+}
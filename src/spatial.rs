@@ -0,0 +1,335 @@
+use crate::schematic::*;
+use std::collections::HashMap;
+use std::ops::Range;
+
+// children per node of the tree
+const NODE_SIZE: usize = 8;
+
+// an axis aligned box in mm, inside out when empty so it meets nothing
+#[derive(Debug, Clone, Copy)]
+struct Area {
+    min: (f64, f64),
+    max: (f64, f64),
+}
+
+impl Area {
+    const EMPTY: Area = Area {
+        min: (f64::INFINITY, f64::INFINITY),
+        max: (f64::NEG_INFINITY, f64::NEG_INFINITY),
+    };
+
+    fn new(bounds: Option<(Point, Point)>) -> Area {
+        match bounds {
+            Some((min, max)) => Area {
+                min: (min.x, min.y),
+                max: (max.x, max.y),
+            },
+            None => Area::EMPTY,
+        }
+    }
+
+    fn union(&self, other: &Area) -> Area {
+        Area {
+            min: (self.min.0.min(other.min.0), self.min.1.min(other.min.1)),
+            max: (self.max.0.max(other.max.0), self.max.1.max(other.max.1)),
+        }
+    }
+
+    fn intersects(&self, other: &Area) -> bool {
+        self.min.0 <= other.max.0
+            && other.min.0 <= self.max.0
+            && self.min.1 <= other.max.1
+            && other.min.1 <= self.max.1
+    }
+
+    // nan when empty, those sort last
+    fn center(&self) -> (f64, f64) {
+        (
+            (self.min.0 + self.max.0) / 2.0,
+            (self.min.1 + self.max.1) / 2.0,
+        )
+    }
+}
+
+// a packed r-tree over item bounds. leaves are sorted into tiles (sort tile
+// recursive) so neighbours share nodes, and each level above boxes NODE_SIZE
+// nodes of the one below. items changed in place update their box, queries
+// stay right though the tiles loosen until the next rebuild
+#[derive(Debug, Clone, Default)]
+pub struct SpatialIndex {
+    items: Vec<ItemRef>,
+    levels: Vec<Vec<Area>>,         // leaves first, then up to the root
+    slots: HashMap<ItemRef, usize>, // item -> leaf
+}
+
+impl SpatialIndex {
+    pub fn new() -> SpatialIndex {
+        SpatialIndex {
+            items: Vec::<ItemRef>::new(),
+            levels: Vec::<Vec<Area>>::new(),
+            slots: HashMap::<ItemRef, usize>::new(),
+        }
+    }
+
+    // items with their bounds in mm, none for items that draw nothing
+    pub fn build(entries: Vec<(ItemRef, Option<(Point, Point)>)>) -> SpatialIndex {
+        let mut entries = entries
+            .into_iter()
+            .map(|(item, bounds)| (item, Area::new(bounds)))
+            .collect::<Vec<_>>();
+        // vertical slices by x, each sorted by y into leaves
+        let leaves = entries.len().div_ceil(NODE_SIZE);
+        let slice = ((leaves as f64).sqrt().ceil() as usize).max(1) * NODE_SIZE;
+        entries.sort_by(|a, b| a.1.center().0.total_cmp(&b.1.center().0));
+        for chunk in entries.chunks_mut(slice) {
+            chunk.sort_by(|a, b| a.1.center().1.total_cmp(&b.1.center().1));
+        }
+
+        let mut index = SpatialIndex::new();
+        index.slots = entries
+            .iter()
+            .enumerate()
+            .map(|(slot, (item, _))| (*item, slot))
+            .collect();
+        index.items = entries.iter().map(|(item, _)| *item).collect();
+        index
+            .levels
+            .push(entries.iter().map(|(_, area)| *area).collect());
+        while index.levels.last().unwrap().len() > NODE_SIZE {
+            let level = index
+                .levels
+                .last()
+                .unwrap()
+                .chunks(NODE_SIZE)
+                .map(|nodes| nodes.iter().fold(Area::EMPTY, |all, area| all.union(area)))
+                .collect();
+            index.levels.push(level);
+        }
+        index
+    }
+
+    // the items whose bounds meet min..max
+    pub fn search(&self, min: &Point, max: &Point) -> Vec<ItemRef> {
+        let area = Area {
+            min: (min.x, min.y),
+            max: (max.x, max.y),
+        };
+        let mut found = Vec::<ItemRef>::new();
+        if let Some(top) = self.levels.last() {
+            self.descend(self.levels.len() - 1, 0..top.len(), &area, &mut found);
+        }
+        found
+    }
+
+    fn descend(&self, level: usize, nodes: Range<usize>, area: &Area, found: &mut Vec<ItemRef>) {
+        for node in nodes {
+            if !self.levels[level][node].intersects(area) {
+                continue;
+            }
+            match level {
+                0 => found.push(self.items[node]),
+                _ => {
+                    let below = self.levels[level - 1].len();
+                    let children = node * NODE_SIZE..((node + 1) * NODE_SIZE).min(below);
+                    self.descend(level - 1, children, area, found);
+                }
+            }
+        }
+    }
+
+    // an item's new bounds, the nodes above it are refitted
+    pub fn update(&mut self, item: ItemRef, bounds: Option<(Point, Point)>) {
+        let mut node = match self.slots.get(&item) {
+            Some(slot) => *slot,
+            None => return,
+        };
+        self.levels[0][node] = Area::new(bounds);
+        for level in 1..self.levels.len() {
+            node /= NODE_SIZE;
+            let below = &self.levels[level - 1];
+            let start = node * NODE_SIZE;
+            let area = below[start..(start + NODE_SIZE).min(below.len())]
+                .iter()
+                .fold(Area::EMPTY, |all, area| all.union(area));
+            self.levels[level][node] = area;
+        }
+    }
+}
+
+// distance from a point to a box, 0 inside it
+fn box_distance(point: &Point, (min, max): &(Point, Point)) -> f64 {
+    let dx = (min.x - point.x).max(point.x - max.x).max(0.0);
+    let dy = (min.y - point.y).max(point.y - max.y).max(0.0);
+    dx.hypot(dy)
+}
+
+fn line_distance(point: &Point, points: &[Point]) -> f64 {
+    match points {
+        [only] => point.distance(only),
+        _ => points
+            .windows(2)
+            .map(|seg| point.segment_distance(&seg[0], &seg[1]))
+            .fold(f64::INFINITY, f64::min),
+    }
+}
+
+impl Schematic {
+    // every item, kind by kind in the order they're drawn
+    pub fn items(&self) -> Vec<ItemRef> {
+        let mut items = Vec::<ItemRef>::new();
+        items.extend((0..self.symbs.len()).map(ItemRef::Symbol));
+        items.extend((0..self.sheets.len()).map(ItemRef::Sheet));
+        items.extend((0..self.wires.len()).map(ItemRef::Wire));
+        items.extend((0..self.juncs.len()).map(ItemRef::Junction));
+        items.extend((0..self.texts.len()).map(ItemRef::Text));
+        items.extend((0..self.polys.len()).map(ItemRef::Polyline));
+        items.extend((0..self.labels.len()).map(ItemRef::Label));
+        items
+    }
+
+    pub fn build_spatial_index(&self) -> SpatialIndex {
+        SpatialIndex::build(
+            self.items()
+                .into_iter()
+                .map(|item| (item, self.item_bounds(item)))
+                .collect(),
+        )
+    }
+
+    // refreshes an item's bounds in the spatial index after an edit that
+    // didn't add or remove anything
+    pub fn update_bounds(&mut self, uuid: &Uuid) {
+        if let Some(item) = self.find_by_uuid(uuid) {
+            let bounds = self.item_bounds(item);
+            self.spatial.update(item, bounds);
+        }
+    }
+
+    // items within tolerance (mm) of a point, nearest first and the smallest
+    // first among equals. wires and lines count by their segments, anything
+    // else by its bounds
+    pub fn hit_test(&self, x: f64, y: f64, tolerance: f64) -> Vec<ItemRef> {
+        let point = Point::new(x, y);
+        let min = Point::new(x - tolerance, y - tolerance);
+        let max = Point::new(x + tolerance, y + tolerance);
+        let mut hits = self
+            .spatial
+            .search(&min, &max)
+            .into_iter()
+            .filter_map(|item| {
+                let bounds = self.item_bounds(item)?;
+                let distance = match item {
+                    ItemRef::Wire(i) => line_distance(&point, &self.wires[i].poss),
+                    ItemRef::Polyline(i) => line_distance(&point, &self.polys[i].poss),
+                    _ => box_distance(&point, &bounds),
+                };
+                let size = (bounds.1.x - bounds.0.x) * (bounds.1.y - bounds.0.y);
+                match distance <= tolerance {
+                    true => Some((distance, size, item)),
+                    false => None,
+                }
+            })
+            .collect::<Vec<_>>();
+        hits.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+        hits.into_iter().map(|(_, _, item)| item).collect()
+    }
+
+    // items whose bounds meet the area min..max, for drawing only what's in
+    // view or a crossing selection
+    pub fn items_in_rect(&self, min: &Point, max: &Point) -> Vec<ItemRef> {
+        self.spatial.search(min, max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn example() -> Schematic {
+        Schematic::from_str(include_str!("../test/example.kicad_sch"))
+    }
+
+    // what hit_test measures, by scanning every item
+    fn distance(schem: &Schematic, item: ItemRef, bounds: &(Point, Point), point: &Point) -> f64 {
+        match item {
+            ItemRef::Wire(i) => line_distance(point, &schem.wires[i].poss),
+            ItemRef::Polyline(i) => line_distance(point, &schem.polys[i].poss),
+            _ => box_distance(point, bounds),
+        }
+    }
+
+    // a grid of 5 mm cells over the whole page, each probed with a rect and
+    // a point against a scan of every item
+    fn agrees(schem: &Schematic) {
+        let all = schem
+            .items()
+            .into_iter()
+            .filter_map(|item| Some((item, schem.item_bounds(item)?)))
+            .collect::<Vec<_>>();
+        let (_, page) = schem.page_bounds();
+        let mut hits = 0;
+        for i in 0..(page.x / 5.0) as usize {
+            for j in 0..(page.y / 5.0) as usize {
+                let (x, y) = (i as f64 * 5.0, j as f64 * 5.0);
+                let area = Area::new(Some((Point::new(x, y), Point::new(x + 12.5, y + 7.5))));
+                let found = schem.items_in_rect(&Point::new(x, y), &Point::new(x + 12.5, y + 7.5));
+                let unique = found.iter().copied().collect::<HashSet<_>>();
+                assert_eq!(found.len(), unique.len());
+                let scan = all
+                    .iter()
+                    .filter(|(_, bounds)| Area::new(Some(bounds.clone())).intersects(&area))
+                    .map(|(item, _)| *item)
+                    .collect::<HashSet<_>>();
+                assert_eq!(unique, scan);
+
+                let point = Point::new(x + 2.5, y + 2.5);
+                let found = schem.hit_test(point.x, point.y, 1.27);
+                let scan = all
+                    .iter()
+                    .filter(|(item, bounds)| distance(schem, *item, bounds, &point) <= 1.27)
+                    .map(|(item, _)| *item)
+                    .collect::<HashSet<_>>();
+                assert_eq!(found.iter().copied().collect::<HashSet<_>>(), scan);
+                hits += found.len();
+            }
+        }
+        assert!(hits > 0);
+    }
+
+    #[test]
+    fn matches_a_scan() {
+        let schem = example();
+        assert!(schem.items().len() > NODE_SIZE * NODE_SIZE);
+        agrees(&schem);
+    }
+
+    #[test]
+    fn matches_a_scan_after_edits() {
+        let mut schem = example();
+        let moved = [
+            schem.symbs[0].uuid,
+            schem.wires[0].uuid,
+            schem.labels[3].uuid,
+        ];
+        for (n, uuid) in moved.iter().enumerate() {
+            schem.move_item(uuid, 40.0 * n as f64 - 60.0, 25.4).unwrap();
+        }
+        agrees(&schem);
+    }
+
+    #[test]
+    fn nearest_hit_first() {
+        let schem = example();
+        let junc = &schem.juncs[0];
+        let point = Point::new(junc.pos.x + 0.3, junc.pos.y + 0.2);
+        let hits = schem.hit_test(point.x, point.y, 2.54);
+        assert!(hits.len() > 1);
+        let distances = hits
+            .iter()
+            .map(|hit| distance(&schem, *hit, &schem.item_bounds(*hit).unwrap(), &point))
+            .collect::<Vec<_>>();
+        assert!(distances.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(schem.hit_test(-50.0, -50.0, 1.0).is_empty());
+    }
+}
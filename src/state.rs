@@ -0,0 +1,224 @@
+use crate::schematic::Point;
+use std::fmt;
+use std::str::FromStr;
+
+// where a viewer is in a design, for links: "sheet=/power&ref=U3&net=VBUS"
+// as a url fragment. a viewer given one without a sheet, zoom or center
+// keeps its own, without a net or ref it has none
+#[derive(Debug, Clone)]
+pub struct ViewState {
+    pub sheet: Option<String>,     // path, "/" is the root
+    pub zoom: Option<f64>,         // px per mm
+    pub center: Option<Point>,     // mm, in the middle of the canvas
+    pub net: Option<String>,       // highlighted
+    pub reference: Option<String>, // selected symbol
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseStateError(String);
+
+impl fmt::Display for ParseStateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid view state: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseStateError {}
+
+impl ViewState {
+    pub fn new() -> ViewState {
+        ViewState {
+            sheet: None,
+            zoom: None,
+            center: None,
+            net: None,
+            reference: None,
+        }
+    }
+}
+
+impl Default for ViewState {
+    fn default() -> ViewState {
+        ViewState::new()
+    }
+}
+
+// everything but letters, digits, "-._~" and "/" is percent encoded, net
+// names like "+3V3" survive a page reading them with URLSearchParams
+fn encode(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                out.push(byte as char)
+            }
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
+
+fn decode(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::<u8>::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = bytes.get(i + 1..i + 3)?;
+                if !hex.iter().all(u8::is_ascii_hexdigit) {
+                    return None;
+                }
+                let hex = std::str::from_utf8(hex).ok()?;
+                out.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 3;
+            }
+            byte => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+// short, a hundredth of a mm or a thousandth of a px per mm is plenty
+fn number(value: f64, places: usize) -> String {
+    let text = format!("{:.*}", places, value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    match text {
+        "-0" => "0".to_string(),
+        text => text.to_string(),
+    }
+}
+
+// a fragment with or without its "#", unknown keys are skipped so links
+// from newer viewers still open
+impl FromStr for ViewState {
+    type Err = ParseStateError;
+
+    fn from_str(text: &str) -> Result<ViewState, ParseStateError> {
+        let error = || ParseStateError(text.to_string());
+        let number = |value: &str| match value.parse::<f64>() {
+            Ok(value) if value.is_finite() => Ok(value),
+            _ => Err(error()),
+        };
+        let mut state = ViewState::new();
+        let fragment = text.strip_prefix('#').unwrap_or(text);
+        for pair in fragment.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = decode(value).ok_or_else(error)?;
+            match key {
+                "sheet" => state.sheet = Some(value),
+                "zoom" => state.zoom = Some(number(&value)?),
+                "center" => {
+                    let (x, y) = value.split_once(',').ok_or_else(error)?;
+                    state.center = Some(Point::new(number(x)?, number(y)?));
+                }
+                "net" => state.net = Some(value).filter(|net| !net.is_empty()),
+                "ref" => state.reference = Some(value).filter(|reference| !reference.is_empty()),
+                _ => (),
+            }
+        }
+        Ok(state)
+    }
+}
+
+// the fragment without its "#"
+impl fmt::Display for ViewState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut pairs = Vec::<String>::new();
+        if let Some(sheet) = &self.sheet {
+            pairs.push(format!("sheet={}", encode(sheet)));
+        }
+        if let Some(reference) = &self.reference {
+            pairs.push(format!("ref={}", encode(reference)));
+        }
+        if let Some(net) = &self.net {
+            pairs.push(format!("net={}", encode(net)));
+        }
+        if let Some(zoom) = self.zoom {
+            pairs.push(format!("zoom={}", number(zoom, 3)));
+        }
+        if let Some(center) = &self.center {
+            let (x, y) = (number(center.x, 2), number(center.y, 2));
+            pairs.push(format!("center={},{}", x, y));
+        }
+        write!(f, "{}", pairs.join("&"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> ViewState {
+        text.parse::<ViewState>().unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let state = ViewState {
+            sheet: Some("/power supply/usb".to_string()),
+            zoom: Some(4.125),
+            center: Some(Point::new(148.5, -10.25)),
+            net: Some("+3V3 & 5V=é".to_string()),
+            reference: Some("U3".to_string()),
+        };
+        let text = state.to_string();
+        assert_eq!(
+            text,
+            "sheet=/power%20supply/usb&ref=U3&net=%2B3V3%20%26%205V%3D%C3%A9&zoom=4.125&center=148.5,-10.25"
+        );
+        let back = parse(&format!("#{}", text));
+        assert_eq!(back.sheet, state.sheet);
+        assert_eq!(back.zoom, state.zoom);
+        assert!(back
+            .center
+            .as_ref()
+            .unwrap()
+            .same_as(&Point::new(148.5, -10.25)));
+        assert_eq!(back.net, state.net);
+        assert_eq!(back.reference, state.reference);
+        assert_eq!(back.to_string(), text);
+    }
+
+    #[test]
+    fn short_numbers() {
+        let state = ViewState {
+            zoom: Some(2.0),
+            center: Some(Point::new(10.004, -0.001)),
+            ..ViewState::new()
+        };
+        assert_eq!(state.to_string(), "zoom=2&center=10,0");
+        assert_eq!(ViewState::new().to_string(), "");
+    }
+
+    #[test]
+    fn partial_fragments() {
+        let state = parse("#ref=R1&future=1&net=");
+        assert_eq!(state.reference.as_deref(), Some("R1"));
+        assert_eq!(state.net, None);
+        assert!(state.sheet.is_none() && state.zoom.is_none() && state.center.is_none());
+        assert!(parse("").reference.is_none());
+        assert!(parse("#").sheet.is_none());
+    }
+
+    #[test]
+    fn rejected() {
+        for text in [
+            "zoom=big",
+            "zoom=inf",
+            "center=1",
+            "center=1,NaN",
+            "net=%2",
+            "net=%zz",
+            "net=%FF",
+        ] {
+            assert_eq!(
+                text.parse::<ViewState>().err(),
+                Some(ParseStateError(text.to_string()))
+            );
+        }
+    }
+}
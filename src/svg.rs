@@ -0,0 +1,320 @@
+use crate::renderer::*;
+use crate::schematic::*;
+use crate::theme::{Color, Theme};
+use std::f64;
+use std::fmt::Write;
+
+#[derive(Debug, Clone)]
+pub struct SvgOptions {
+    pub frame: bool,      // draw the page frame
+    pub background: bool, // fill the page with the theme background
+}
+
+impl SvgOptions {
+    pub fn new() -> SvgOptions {
+        SvgOptions {
+            frame: true,
+            background: true,
+        }
+    }
+}
+
+impl Default for SvgOptions {
+    fn default() -> SvgOptions {
+        SvgOptions::new()
+    }
+}
+
+// writes svg elements in the renderer's units (mm)
+pub struct SvgRenderer {
+    pub out: String,
+    depth: usize,
+    path: String,
+    style: Paint,
+}
+
+// short decimal, svg files get large quickly
+fn num(value: f64) -> String {
+    let text = format!("{:.4}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    match text {
+        "-0" | "" => "0".to_string(),
+        _ => text.to_string(),
+    }
+}
+
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+// hex color plus an opacity attribute when translucent
+fn paint(attr: &str, color: &Color) -> String {
+    let hex = Color::rgb(color.r, color.g, color.b).to_hex();
+    if color.a >= 1.0 {
+        format!("{}=\"{}\"", attr, hex)
+    } else {
+        format!("{}=\"{}\" {}-opacity=\"{}\"", attr, hex, attr, num(color.a))
+    }
+}
+
+impl SvgRenderer {
+    pub fn new() -> SvgRenderer {
+        SvgRenderer {
+            out: String::new(),
+            depth: 0,
+            path: String::new(),
+            style: Paint::blank(),
+        }
+    }
+
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.depth {
+            self.out.push_str("  ");
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    fn open(&mut self, text: &str) {
+        self.line(text);
+        self.depth += 1;
+    }
+
+    fn close(&mut self) {
+        self.depth -= 1;
+        self.line("</g>");
+    }
+}
+
+impl Default for SvgRenderer {
+    fn default() -> SvgRenderer {
+        SvgRenderer::new()
+    }
+}
+
+impl Renderer for SvgRenderer {
+    fn begin_path(&mut self) {
+        self.path.clear();
+    }
+
+    fn move_to(&mut self, x: f64, y: f64) {
+        let _ = write!(self.path, "M{} {} ", num(x), num(y));
+    }
+
+    fn line_to(&mut self, x: f64, y: f64) {
+        let command = if self.path.is_empty() { "M" } else { "L" };
+        let _ = write!(self.path, "{}{} {} ", command, num(x), num(y));
+    }
+
+    // same semantics as the canvas: a line to the start, then clockwise
+    fn arc(&mut self, x: f64, y: f64, radius: f64, start: f64, end: f64) {
+        let tau = f64::consts::PI * 2.0;
+        let point = |angle: f64| (x + radius * angle.cos(), y + radius * angle.sin());
+        let (sx, sy) = point(start);
+        self.line_to(sx, sy);
+        let sweep = if end - start >= tau {
+            tau
+        } else {
+            (end - start).rem_euclid(tau)
+        };
+        // svg can't draw a full circle in one arc
+        let steps = if sweep > f64::consts::PI { 2 } else { 1 };
+        for step in 1..=steps {
+            let (ex, ey) = point(start + sweep * step as f64 / steps as f64);
+            let _ = write!(
+                self.path,
+                "A{} {} 0 0 1 {} {} ",
+                num(radius),
+                num(radius),
+                num(ex),
+                num(ey)
+            );
+        }
+    }
+
+    fn rect(&mut self, x: f64, y: f64, width: f64, height: f64) {
+        let _ = write!(
+            self.path,
+            "M{} {} h{} v{} h{} Z ",
+            num(x),
+            num(y),
+            num(width),
+            num(height),
+            num(-width)
+        );
+    }
+
+    fn close_path(&mut self) {
+        self.path.push_str("Z ");
+    }
+
+    fn fill(&mut self) {
+        if self.path.is_empty() {
+            return;
+        }
+        let line = format!(
+            "<path d=\"{}\" {} stroke=\"none\"/>",
+            self.path.trim_end(),
+            paint("fill", &self.style.fill)
+        );
+        self.line(&line);
+    }
+
+    fn stroke(&mut self) {
+        if self.path.is_empty() {
+            return;
+        }
+        let mut line = format!(
+            "<path d=\"{}\" fill=\"none\" {} stroke-width=\"{}\"",
+            self.path.trim_end(),
+            paint("stroke", &self.style.stroke),
+            num(self.style.width)
+        );
+        if !self.style.dash.is_empty() {
+            let dash = self
+                .style
+                .dash
+                .iter()
+                .map(|len| num(*len))
+                .collect::<Vec<_>>();
+            let _ = write!(line, " stroke-dasharray=\"{}\"", dash.join(" "));
+        }
+        line.push_str("/>");
+        self.line(&line);
+    }
+
+    fn text(&mut self, text: &str, x: f64, y: f64) {
+        let style = &self.style;
+        let mut line = format!(
+            "<text x=\"{}\" y=\"{}\" font-size=\"{}\" {}",
+            num(x),
+            num(y),
+            num(style.font_size),
+            paint("fill", &style.fill)
+        );
+        match style.align {
+            TextAlign::Left => {}
+            TextAlign::Center => line.push_str(" text-anchor=\"middle\""),
+            TextAlign::Right => line.push_str(" text-anchor=\"end\""),
+        }
+        match style.baseline {
+            TextBaseline::Alphabetic => {}
+            TextBaseline::Top => line.push_str(" dominant-baseline=\"text-before-edge\""),
+            TextBaseline::Middle => line.push_str(" dominant-baseline=\"central\""),
+            TextBaseline::Bottom => line.push_str(" dominant-baseline=\"text-after-edge\""),
+        }
+        if style.bold {
+            line.push_str(" font-weight=\"bold\"");
+        }
+        if style.italic {
+            line.push_str(" font-style=\"italic\"");
+        }
+        let _ = write!(line, ">{}</text>", escape(text));
+        self.line(&line);
+    }
+
+    fn push_transform(&mut self, t: &Transform) {
+        let line = format!(
+            "<g transform=\"matrix({} {} {} {} {} {})\">",
+            num(t.a),
+            num(t.b),
+            num(t.c),
+            num(t.d),
+            num(t.e),
+            num(t.f)
+        );
+        self.open(&line);
+    }
+
+    fn pop_transform(&mut self) {
+        self.close();
+    }
+
+    fn set_style(&mut self, style: &Paint) {
+        self.style = style.clone();
+    }
+
+    fn begin_group(&mut self, group: &Group) {
+        let mut line = format!("<g class=\"{}\"", escape(&group.class));
+        // not an id, those have to be unique in a page that may show the
+        // same sheet twice
+        if let Some(id) = &group.id {
+            let _ = write!(line, " data-uuid=\"{}\"", id);
+        }
+        for (key, value) in &group.data {
+            let _ = write!(line, " data-{}=\"{}\"", key, escape(value));
+        }
+        line.push('>');
+        self.open(&line);
+    }
+
+    fn end_group(&mut self) {
+        self.close();
+    }
+}
+
+impl Schematic {
+    // standalone svg of the sheet, 1 user unit = 1 mm
+    pub fn to_svg(&self, theme: &Theme, options: &SvgOptions) -> String {
+        let (width, height) = self.page_size();
+        let mut r = SvgRenderer::new();
+        r.line("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>");
+        let mut root = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" version=\"1.1\" width=\"{}mm\" height=\"{}mm\" viewBox=\"0 0 {} {}\"",
+            num(width),
+            num(height),
+            num(width),
+            num(height)
+        );
+        if !self.uuid.is_nil() {
+            let _ = write!(root, " data-uuid=\"{}\"", self.uuid);
+        }
+        root.push_str(
+            " font-family=\"monospace\" stroke-linecap=\"round\" stroke-linejoin=\"round\">",
+        );
+        r.open(&root);
+        if options.background {
+            let line = format!(
+                "<rect class=\"background\" width=\"{}\" height=\"{}\" {}/>",
+                num(width),
+                num(height),
+                paint("fill", &theme.background)
+            );
+            r.line(&line);
+        }
+        if options.frame {
+            self.render_frame(&mut r, theme);
+        }
+        self.render(&mut r, theme, &[]);
+        r.depth -= 1;
+        r.line("</svg>");
+        r.out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::netlist::tests::{sheet, wire};
+
+    #[test]
+    fn items_are_tagged_with_their_uuid() {
+        let schem = sheet(&[], &[wire((0.0, 0.0), (10.0, 0.0), 1)]);
+        let svg = schem.to_svg(&Theme::kicad_default(), &SvgOptions::new());
+        assert!(
+            svg.contains("<g class=\"wire\" data-uuid=\"00000000-0000-4000-8000-000000000001\">")
+        );
+        assert!(!svg.contains(" id=\""));
+    }
+}
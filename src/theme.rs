@@ -1,31 +1,145 @@
-#[derive(Debug, Clone)]
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Color {
   pub r : u8,
   pub g : u8,
   pub b : u8,
-  pub a : u8
+  pub a : f64, // 0.0 - 1.0
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseColorError(String);
+
+impl fmt::Display for ParseColorError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "invalid color: {:?}", self.0)
+  }
+}
+
+impl std::error::Error for ParseColorError {}
+
 impl Color {
   pub fn blank() -> Color {
     Color {
       r: 0,
       g: 0,
       b: 0,
-      a: 0
+      a: 0.0
+    }
+  }
+
+  pub fn new(r: u8, g: u8, b: u8, a: f64) -> Color {
+    Color { r, g, b, a: a.clamp(0.0, 1.0) }
+  }
+
+  pub fn rgb(r: u8, g: u8, b: u8) -> Color {
+    Color::new(r, g, b, 1.0)
+  }
+
+  // "rgb(r, g, b)" or "rgba(r, g, b, a)", usable as a canvas style
+  pub fn to_css(&self) -> String {
+    if self.a >= 1.0 {
+      format!("rgb({}, {}, {})", self.r, self.g, self.b)
+    } else {
+      format!("rgba({}, {}, {}, {:.3})", self.r, self.g, self.b, self.a)
+    }
+  }
+
+  // "#rrggbb", or "#rrggbbaa" when not opaque
+  pub fn to_hex(&self) -> String {
+    if self.a >= 1.0 {
+      format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    } else {
+      let a = (self.a * 255.0).round() as u8;
+      format!("#{:02x}{:02x}{:02x}{:02x}", self.r, self.g, self.b, a)
+    }
+  }
+}
+
+impl fmt::Display for Color {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.to_css())
+  }
+}
+
+// accepts "#rgb", "#rrggbb", "#rrggbbaa", "rgb(r, g, b)" and "rgba(r, g, b, a)"
+impl FromStr for Color {
+  type Err = ParseColorError;
+
+  fn from_str(text: &str) -> Result<Color, ParseColorError> {
+    let error = || ParseColorError(text.to_string());
+    let text = text.trim();
+    if let Some(hex) = text.strip_prefix('#') {
+      if !hex.is_ascii() {
+        return Err(error());
+      }
+      let digit = |i: usize, len: usize| u8::from_str_radix(&hex[i * len..i * len + len], 16).map_err(|_| error());
+      return match hex.len() {
+        3 => Ok(Color::rgb(digit(0, 1)? * 17, digit(1, 1)? * 17, digit(2, 1)? * 17)),
+        6 => Ok(Color::rgb(digit(0, 2)?, digit(1, 2)?, digit(2, 2)?)),
+        8 => Ok(Color::new(digit(0, 2)?, digit(1, 2)?, digit(2, 2)?, digit(3, 2)? as f64 / 255.0)),
+        _ => Err(error()),
+      };
     }
+    let args = text
+      .strip_prefix("rgba(")
+      .or_else(|| text.strip_prefix("rgb("))
+      .and_then(|rest| rest.strip_suffix(')'))
+      .ok_or_else(error)?
+      .split(',')
+      .map(|arg| arg.trim())
+      .collect::<Vec<_>>();
+    if args.len() != 3 && args.len() != 4 {
+      return Err(error());
+    }
+    let channel = |arg: &str| arg.parse::<u8>().map_err(|_| error());
+    let alpha = match args.get(3) {
+      Some(arg) => arg.parse::<f64>().map_err(|_| error())?,
+      None => 1.0,
+    };
+    Ok(Color::new(channel(args[0])?, channel(args[1])?, channel(args[2])?, alpha))
   }
 }
 
+// schematic color keys of kicad's theme json
+const KICAD_KEYS: [&str; 36] = [
+  "anchor", "aux_items", "background", "brightened", "bus", "bus_junction",
+  "component_body", "component_outline", "cursor", "erc_error", "erc_warning",
+  "fields", "grid", "grid_axes", "hidden", "junction", "label_global",
+  "label_hier", "label_local", "net_name", "no_connect", "note", "pin",
+  "pin_name", "pin_number", "reference", "shadow", "sheet", "sheet_background",
+  "sheet_fields", "sheet_filename", "sheet_label", "sheet_name", "value",
+  "wire", "worksheet",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseThemeError(String);
+
+impl fmt::Display for ParseThemeError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "invalid theme: {}", self.0)
+  }
+}
+
+impl std::error::Error for ParseThemeError {}
+
 #[derive(Debug, Clone)]
 pub struct Theme {
+  pub name : String,
   pub anchors : Color,
   pub axes : Color,
   pub background : Color,
   pub bus_junction : Color,
   pub busses : Color,
+  pub component_body : Color,
+  pub component_outline : Color,
   pub cursor : Color,
   pub drawing_sheet : Color,
+  pub erc_error : Color,
+  pub erc_warning : Color,
+  pub fields : Color,
   pub global_label : Color,
   pub grid : Color,
   pub helper_items : Color,
@@ -34,27 +148,458 @@ pub struct Theme {
   pub highlighted_item : Color,
   pub junction : Color,
   pub labels : Color,
-  // todo : more  
+  pub net_names : Color,
+  pub no_connect : Color,
+  pub notes : Color,
+  pub pin : Color,
+  pub pin_name : Color,
+  pub pin_number : Color,
+  pub reference : Color,
+  pub shadow : Color,
+  pub sheet : Color,
+  pub sheet_background : Color,
+  pub sheet_fields : Color,
+  pub sheet_filename : Color,
+  pub sheet_label : Color,
+  pub sheet_name : Color,
+  pub value : Color,
+  pub wire : Color,
+  pub override_item_colors : bool, // ignore colors set on items
+}
+
+impl Default for Theme {
+  fn default() -> Theme {
+    Theme::new()
+  }
 }
 
 impl Theme {
+  // kicad's default schematic colors
   pub fn new() -> Theme {
     Theme {
-      anchors : Color::blank(),
-      axes : Color::blank(),
-      background : Color::blank(),
-      bus_junction : Color::blank(),
-      busses : Color::blank(),
-      cursor : Color::blank(),
-      drawing_sheet : Color::blank(),
-      global_label : Color::blank(),
-      grid : Color::blank(),
-      helper_items : Color::blank(),
-      hidden_items : Color::blank(),
-      hierarchical_label : Color::blank(),
-      highlighted_item : Color::blank(),
-      junction : Color::blank(),
-      labels : Color::blank(),
-    }
-  }
-}
\ No newline at end of file
+      name : "KiCad Default".to_string(),
+      anchors : Color::rgb(0, 0, 255),
+      axes : Color::rgb(0, 0, 132),
+      background : Color::rgb(245, 244, 239),
+      bus_junction : Color::rgb(0, 0, 132),
+      busses : Color::rgb(0, 0, 132),
+      component_body : Color::rgb(255, 255, 194),
+      component_outline : Color::rgb(132, 0, 0),
+      cursor : Color::rgb(15, 15, 15),
+      drawing_sheet : Color::rgb(132, 0, 0),
+      erc_error : Color::new(230, 9, 13, 0.8),
+      erc_warning : Color::new(209, 146, 0, 0.8),
+      fields : Color::rgb(132, 0, 132),
+      global_label : Color::rgb(132, 0, 0),
+      grid : Color::rgb(181, 181, 181),
+      helper_items : Color::rgb(0, 0, 0),
+      hidden_items : Color::rgb(94, 194, 194),
+      hierarchical_label : Color::rgb(114, 86, 0),
+      highlighted_item : Color::rgb(255, 0, 255),
+      junction : Color::rgb(0, 150, 0),
+      labels : Color::rgb(15, 15, 15),
+      net_names : Color::rgb(132, 132, 132),
+      no_connect : Color::rgb(0, 0, 132),
+      notes : Color::rgb(0, 0, 194),
+      pin : Color::rgb(132, 0, 0),
+      pin_name : Color::rgb(0, 100, 100),
+      pin_number : Color::rgb(169, 0, 0),
+      reference : Color::rgb(0, 100, 100),
+      shadow : Color::new(102, 179, 255, 0.8),
+      sheet : Color::rgb(132, 0, 0),
+      sheet_background : Color::new(255, 255, 255, 0.0),
+      sheet_fields : Color::rgb(132, 0, 132),
+      sheet_filename : Color::rgb(114, 86, 0),
+      sheet_label : Color::rgb(0, 100, 100),
+      sheet_name : Color::rgb(0, 100, 100),
+      value : Color::rgb(0, 100, 100),
+      wire : Color::rgb(0, 150, 0),
+      override_item_colors : false,
+    }
+  }
+
+  // item colors win unless unset or overridden by the theme
+  pub fn resolve<'a>(&self, item: &'a Option<Color>, fallback: &'a Color) -> &'a Color {
+    match item {
+      Some(color) if !self.override_item_colors => color,
+      _ => fallback,
+    }
+  }
+
+  // the theme with every line and text color made the highlight color,
+  // fills stay so highlighted symbols still read
+  pub fn highlighted(&self) -> Theme {
+    let color = &self.highlighted_item;
+    Theme {
+      name : self.name.clone(),
+      bus_junction : color.clone(),
+      busses : color.clone(),
+      component_outline : color.clone(),
+      fields : color.clone(),
+      global_label : color.clone(),
+      hierarchical_label : color.clone(),
+      junction : color.clone(),
+      labels : color.clone(),
+      net_names : color.clone(),
+      no_connect : color.clone(),
+      notes : color.clone(),
+      pin : color.clone(),
+      pin_name : color.clone(),
+      pin_number : color.clone(),
+      reference : color.clone(),
+      sheet : color.clone(),
+      sheet_fields : color.clone(),
+      sheet_filename : color.clone(),
+      sheet_label : color.clone(),
+      sheet_name : color.clone(),
+      value : color.clone(),
+      wire : color.clone(),
+      override_item_colors : true,
+      ..self.clone()
+    }
+  }
+
+  pub fn kicad_default() -> Theme {
+    Theme::new()
+  }
+
+  // eeschema's pre 6.0 colors
+  pub fn kicad_classic() -> Theme {
+    Theme {
+      name : "KiCad Classic".to_string(),
+      anchors : Color::rgb(0, 0, 255),
+      axes : Color::rgb(0, 0, 132),
+      background : Color::rgb(255, 255, 255),
+      bus_junction : Color::rgb(0, 0, 132),
+      busses : Color::rgb(0, 0, 132),
+      component_body : Color::rgb(255, 255, 194),
+      component_outline : Color::rgb(132, 0, 0),
+      cursor : Color::rgb(0, 0, 0),
+      drawing_sheet : Color::rgb(132, 0, 0),
+      erc_error : Color::new(255, 0, 0, 0.8),
+      erc_warning : Color::new(0, 255, 0, 0.8),
+      fields : Color::rgb(132, 0, 132),
+      global_label : Color::rgb(132, 0, 0),
+      grid : Color::rgb(132, 132, 132),
+      helper_items : Color::rgb(0, 0, 0),
+      hidden_items : Color::rgb(194, 194, 194),
+      hierarchical_label : Color::rgb(132, 132, 0),
+      highlighted_item : Color::rgb(255, 0, 255),
+      junction : Color::rgb(0, 132, 0),
+      labels : Color::rgb(0, 0, 0),
+      net_names : Color::rgb(132, 132, 132),
+      no_connect : Color::rgb(0, 0, 132),
+      notes : Color::rgb(0, 0, 132),
+      pin : Color::rgb(132, 0, 0),
+      pin_name : Color::rgb(0, 132, 132),
+      pin_number : Color::rgb(132, 0, 0),
+      reference : Color::rgb(0, 132, 132),
+      shadow : Color::new(102, 179, 255, 0.8),
+      sheet : Color::rgb(132, 0, 132),
+      sheet_background : Color::new(255, 255, 255, 0.0),
+      sheet_fields : Color::rgb(132, 0, 132),
+      sheet_filename : Color::rgb(132, 132, 0),
+      sheet_label : Color::rgb(0, 132, 132),
+      sheet_name : Color::rgb(0, 132, 132),
+      value : Color::rgb(0, 132, 132),
+      wire : Color::rgb(0, 132, 0),
+      override_item_colors : false,
+    }
+  }
+
+  pub fn light() -> Theme {
+    Theme {
+      name : "light".to_string(),
+      ..Theme::kicad_default()
+    }
+  }
+
+  pub fn dark() -> Theme {
+    Theme {
+      name : "dark".to_string(),
+      anchors : Color::rgb(100, 100, 255),
+      axes : Color::rgb(100, 100, 180),
+      background : Color::rgb(30, 30, 30),
+      bus_junction : Color::rgb(90, 150, 255),
+      busses : Color::rgb(90, 150, 255),
+      component_body : Color::rgb(52, 52, 40),
+      component_outline : Color::rgb(220, 120, 110),
+      cursor : Color::rgb(220, 220, 220),
+      drawing_sheet : Color::rgb(180, 90, 90),
+      erc_error : Color::new(255, 60, 60, 0.8),
+      erc_warning : Color::new(255, 190, 40, 0.8),
+      fields : Color::rgb(200, 130, 200),
+      global_label : Color::rgb(230, 110, 110),
+      grid : Color::rgb(80, 80, 80),
+      helper_items : Color::rgb(200, 200, 200),
+      hidden_items : Color::rgb(110, 110, 110),
+      hierarchical_label : Color::rgb(220, 190, 90),
+      highlighted_item : Color::rgb(255, 80, 255),
+      junction : Color::rgb(80, 210, 80),
+      labels : Color::rgb(230, 230, 230),
+      net_names : Color::rgb(160, 160, 160),
+      no_connect : Color::rgb(120, 150, 255),
+      notes : Color::rgb(120, 150, 255),
+      pin : Color::rgb(220, 120, 110),
+      pin_name : Color::rgb(110, 200, 200),
+      pin_number : Color::rgb(220, 130, 130),
+      reference : Color::rgb(110, 200, 200),
+      shadow : Color::new(102, 179, 255, 0.5),
+      sheet : Color::rgb(220, 120, 110),
+      sheet_background : Color::new(0, 0, 0, 0.0),
+      sheet_fields : Color::rgb(200, 130, 200),
+      sheet_filename : Color::rgb(220, 190, 90),
+      sheet_label : Color::rgb(110, 200, 200),
+      sheet_name : Color::rgb(110, 200, 200),
+      value : Color::rgb(110, 200, 200),
+      wire : Color::rgb(80, 210, 80),
+      override_item_colors : false,
+    }
+  }
+
+  // saturated colors on black, for projectors and low vision
+  pub fn high_contrast() -> Theme {
+    Theme {
+      name : "high-contrast".to_string(),
+      anchors : Color::rgb(0, 255, 255),
+      axes : Color::rgb(255, 255, 255),
+      background : Color::rgb(0, 0, 0),
+      bus_junction : Color::rgb(0, 200, 255),
+      busses : Color::rgb(0, 200, 255),
+      component_body : Color::rgb(0, 0, 0),
+      component_outline : Color::rgb(255, 255, 0),
+      cursor : Color::rgb(255, 255, 255),
+      drawing_sheet : Color::rgb(255, 255, 255),
+      erc_error : Color::rgb(255, 0, 0),
+      erc_warning : Color::rgb(255, 160, 0),
+      fields : Color::rgb(255, 120, 255),
+      global_label : Color::rgb(255, 80, 80),
+      grid : Color::rgb(128, 128, 128),
+      helper_items : Color::rgb(255, 255, 255),
+      hidden_items : Color::rgb(160, 160, 160),
+      hierarchical_label : Color::rgb(255, 200, 0),
+      highlighted_item : Color::rgb(255, 0, 255),
+      junction : Color::rgb(0, 255, 0),
+      labels : Color::rgb(255, 255, 255),
+      net_names : Color::rgb(200, 200, 200),
+      no_connect : Color::rgb(0, 200, 255),
+      notes : Color::rgb(0, 255, 255),
+      pin : Color::rgb(255, 255, 0),
+      pin_name : Color::rgb(0, 255, 255),
+      pin_number : Color::rgb(255, 255, 255),
+      reference : Color::rgb(0, 255, 255),
+      shadow : Color::new(255, 255, 255, 0.6),
+      sheet : Color::rgb(255, 255, 0),
+      sheet_background : Color::new(0, 0, 0, 0.0),
+      sheet_fields : Color::rgb(255, 120, 255),
+      sheet_filename : Color::rgb(255, 200, 0),
+      sheet_label : Color::rgb(0, 255, 255),
+      sheet_name : Color::rgb(0, 255, 255),
+      value : Color::rgb(0, 255, 255),
+      wire : Color::rgb(0, 255, 0),
+      override_item_colors : true,
+    }
+  }
+
+  // black on white for printing, item colors ignored
+  pub fn monochrome() -> Theme {
+    let mut theme = Theme::kicad_default();
+    for key in KICAD_KEYS.iter() {
+      *theme.field(key).unwrap() = Color::rgb(0, 0, 0);
+    }
+    Theme {
+      name : "monochrome".to_string(),
+      background : Color::rgb(255, 255, 255),
+      component_body : Color::new(255, 255, 255, 0.0),
+      sheet_background : Color::new(255, 255, 255, 0.0),
+      grid : Color::rgb(200, 200, 200),
+      shadow : Color::new(0, 0, 0, 0.3),
+      override_item_colors : true,
+      ..theme
+    }
+  }
+
+  pub fn builtin_names() -> &'static [&'static str] {
+    &["light", "dark", "high-contrast", "monochrome", "KiCad Default", "KiCad Classic"]
+  }
+
+  pub fn builtin(name: &str) -> Option<Theme> {
+    match name {
+      "light" => Some(Theme::light()),
+      "dark" => Some(Theme::dark()),
+      "high-contrast" => Some(Theme::high_contrast()),
+      "monochrome" | "print" => Some(Theme::monochrome()),
+      "KiCad Default" => Some(Theme::kicad_default()),
+      "KiCad Classic" => Some(Theme::kicad_classic()),
+      _ => None,
+    }
+  }
+
+  // reads a kicad `colors/*.json` theme, keys missing from its schematic
+  // section keep kicad's defaults
+  pub fn from_kicad_json(json: &str) -> Result<Theme, ParseThemeError> {
+    let root = serde_json::from_str::<serde_json::Value>(json)
+      .map_err(|err| ParseThemeError(err.to_string()))?;
+    let mut theme = Theme::new();
+    if let Some(name) = root.pointer("/meta/name").and_then(|name| name.as_str()) {
+      theme.name = name.to_string();
+    }
+    let schematic = match root.get("schematic").and_then(|section| section.as_object()) {
+      Some(schematic) => schematic,
+      None => return Err(ParseThemeError("missing schematic section".to_string())),
+    };
+    for (key, value) in schematic {
+      if key == "override_item_colors" {
+        theme.override_item_colors = value.as_bool().unwrap_or(false);
+        continue;
+      }
+      let field = match theme.field(key) {
+        Some(field) => field,
+        None => continue, // newer kicad keys we don't draw
+      };
+      let color = value
+        .as_str()
+        .ok_or_else(|| ParseThemeError(format!("{} is not a color string", key)))?;
+      *field = color
+        .parse::<Color>()
+        .map_err(|err| ParseThemeError(format!("{}: {}", key, err)))?;
+    }
+    Ok(theme)
+  }
+
+  // maps kicad's json keys onto theme fields
+  fn field(&mut self, key: &str) -> Option<&mut Color> {
+    Some(match key {
+      "anchor" => &mut self.anchors,
+      "aux_items" => &mut self.helper_items,
+      "background" => &mut self.background,
+      "brightened" => &mut self.highlighted_item,
+      "bus" => &mut self.busses,
+      "bus_junction" => &mut self.bus_junction,
+      "component_body" => &mut self.component_body,
+      "component_outline" => &mut self.component_outline,
+      "cursor" => &mut self.cursor,
+      "erc_error" => &mut self.erc_error,
+      "erc_warning" => &mut self.erc_warning,
+      "fields" => &mut self.fields,
+      "grid" => &mut self.grid,
+      "grid_axes" => &mut self.axes,
+      "hidden" => &mut self.hidden_items,
+      "junction" => &mut self.junction,
+      "label_global" => &mut self.global_label,
+      "label_hier" => &mut self.hierarchical_label,
+      "label_local" => &mut self.labels,
+      "net_name" => &mut self.net_names,
+      "no_connect" => &mut self.no_connect,
+      "note" => &mut self.notes,
+      "pin" => &mut self.pin,
+      "pin_name" => &mut self.pin_name,
+      "pin_number" => &mut self.pin_number,
+      "reference" => &mut self.reference,
+      "shadow" => &mut self.shadow,
+      "sheet" => &mut self.sheet,
+      "sheet_background" => &mut self.sheet_background,
+      "sheet_fields" => &mut self.sheet_fields,
+      "sheet_filename" => &mut self.sheet_filename,
+      "sheet_label" => &mut self.sheet_label,
+      "sheet_name" => &mut self.sheet_name,
+      "value" => &mut self.value,
+      "wire" => &mut self.wire,
+      "worksheet" => &mut self.drawing_sheet,
+      _ => return None,
+    })
+  }
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use symbolic_expressions::parser::parse_str;
+
+  #[test]
+  fn colors_round_trip() {
+    for hex in ["#f5f4ef", "#000000", "#e6090dcc", "#ffffff00"] {
+      assert_eq!(hex.parse::<Color>().unwrap().to_hex(), hex);
+    }
+    for css in ["rgb(245, 244, 239)", "rgba(230, 9, 13, 0.800)", "rgba(0, 0, 0, 0.000)"] {
+      let color = css.parse::<Color>().unwrap();
+      assert_eq!(color.to_css(), css);
+      assert_eq!(color.to_string().parse::<Color>(), Ok(color.clone()));
+      assert_eq!(color.to_hex().parse::<Color>().unwrap().to_hex(), color.to_hex());
+    }
+    assert_eq!("#abc".parse::<Color>(), Ok(Color::rgb(0xaa, 0xbb, 0xcc)));
+    assert_eq!(" rgb(1,2,3) ".parse::<Color>(), Ok(Color::rgb(1, 2, 3)));
+    assert_eq!("rgba(1, 2, 3, 7)".parse::<Color>(), Ok(Color::rgb(1, 2, 3)));
+  }
+
+  #[test]
+  fn bad_colors() {
+    for text in ["", "#12", "#12345", "#gggggg", "#ééé", "rgb(1, 2)", "rgb(256, 0, 0)", "rgba(1, 2, 3, x)", "hsl(0, 0, 0)", "rgb(1, 2, 3"] {
+      assert_eq!(text.parse::<Color>(), Err(ParseColorError(text.to_string())));
+    }
+  }
+
+  #[test]
+  fn file_colors() {
+    let color = |text: &str| Color::from_sexp(&parse_str(text).unwrap());
+    assert_eq!(color("(color 132 0 0 1)"), Some(Color::rgb(132, 0, 0)));
+    assert_eq!(color("(color 0 0 0 0.5)"), Some(Color::new(0, 0, 0, 0.5)));
+    // unset, drawn in the theme's color
+    assert_eq!(color("(color 0 0 0 0)"), None);
+    let theme = Theme::new();
+    assert_eq!(theme.resolve(&color("(color 0 0 0 0)"), &theme.wire), &theme.wire);
+  }
+
+  #[test]
+  fn kicad_theme() {
+    let colors = KICAD_KEYS
+      .iter()
+      .enumerate()
+      .map(|(i, key)| format!("\"{}\": \"rgb({}, 0, 0)\"", key, i))
+      .collect::<Vec<_>>();
+    let json = format!(
+      "{{\"board\": {{\"grid\": \"#ffffff\"}}, \"meta\": {{\"name\": \"Solarized\", \"version\": 5}}, \
+       \"schematic\": {{{}, \"override_item_colors\": true, \"op_currents\": \"rgb(1, 2, 3)\"}}}}",
+      colors.join(", ")
+    );
+    let mut theme = Theme::from_kicad_json(&json).unwrap();
+    assert_eq!(theme.name, "Solarized");
+    assert!(theme.override_item_colors);
+    for (i, key) in KICAD_KEYS.iter().enumerate() {
+      assert_eq!(theme.field(key).cloned(), Some(Color::rgb(i as u8, 0, 0)), "{}", key);
+    }
+    assert_eq!(theme.labels, Color::rgb(18, 0, 0));
+    assert_eq!(theme.drawing_sheet, Color::rgb(35, 0, 0));
+  }
+
+  #[test]
+  fn partial_kicad_theme() {
+    let json = r##"{"schematic": {"wire": "rgba(1, 2, 3, 0.500)", "label_local": "#102030"}}"##;
+    let theme = Theme::from_kicad_json(json).unwrap();
+    let default = Theme::new();
+    assert_eq!(theme.name, default.name);
+    assert_eq!(theme.wire, Color::new(1, 2, 3, 0.5));
+    assert_eq!(theme.labels, Color::rgb(0x10, 0x20, 0x30));
+    assert_eq!(theme.background, default.background);
+    assert_eq!(theme.erc_error, default.erc_error);
+    assert_eq!(theme.override_item_colors, default.override_item_colors);
+  }
+
+  #[test]
+  fn bad_kicad_themes() {
+    let error = |json: &str| Theme::from_kicad_json(json).unwrap_err().0;
+    assert!(error(r#"{"schematic": {"wire": "rgb(0, 300, 0)"}}"#).starts_with("wire: "));
+    assert!(error(r#"{"schematic": {"bus": 7}}"#).starts_with("bus "));
+    assert_eq!(error(r#"{"meta": {"name": "Board only"}, "board": {}}"#), "missing schematic section");
+    assert!(Theme::from_kicad_json(r#"{"schematic": "#).is_err());
+  }
+
+  #[test]
+  fn builtin_themes() {
+    for name in Theme::builtin_names() {
+      let theme = Theme::builtin(name).unwrap_or_else(|| panic!("{} is not a builtin", name));
+      assert_eq!(&theme.name, name);
+    }
+    assert_eq!(Theme::builtin("print").unwrap().name, "monochrome");
+    assert!(Theme::builtin("Light").is_none());
+  }
+}
@@ -0,0 +1,248 @@
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// kicad item id, either a modern uuid "xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx"
+// or a legacy 8 hex digit timestamp (kicad 5), stored the way kicad's KIID
+// does: the timestamp in the last 4 bytes with everything else zero
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Uuid([u8; 16]);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseUuidError(String);
+
+impl fmt::Display for ParseUuidError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid uuid: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseUuidError {}
+
+impl Uuid {
+    pub fn nil() -> Uuid {
+        Uuid([0; 16])
+    }
+
+    pub fn from_bytes(bytes: [u8; 16]) -> Uuid {
+        Uuid(bytes)
+    }
+
+    pub fn from_timestamp(timestamp: u32) -> Uuid {
+        let mut bytes = [0; 16];
+        bytes[12..].copy_from_slice(&timestamp.to_be_bytes());
+        Uuid(bytes)
+    }
+
+    // a random uuid for new items (not for anything security related)
+    pub fn new_v4() -> Uuid {
+        let mut bytes = random_bytes();
+        bytes[6] = bytes[6] & 0x0f | 0x40;
+        bytes[8] = bytes[8] & 0x3f | 0x80;
+        Uuid(bytes)
+    }
+
+    // the uuid of an id as a file spells it. kicad only writes the two forms
+    // above, anything else (hand edits, other tools) gets one derived from
+    // its text, so the same id always names the same item
+    pub fn from_text(text: &str) -> Uuid {
+        if let Ok(uuid) = text.parse::<Uuid>() {
+            return uuid;
+        }
+        // fnv-1a, twice with different offsets for the two halves
+        let mut bytes = [0; 16];
+        for (half, offset) in bytes
+            .chunks_mut(8)
+            .zip([0xcbf29ce484222325u64, 0x84222325cbf29ce4])
+        {
+            let hash = text.bytes().fold(offset, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+            });
+            half.copy_from_slice(&hash.to_be_bytes());
+        }
+        // version 8, custom
+        bytes[6] = bytes[6] & 0x0f | 0x80;
+        bytes[8] = bytes[8] & 0x3f | 0x80;
+        Uuid(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
+
+    pub fn is_nil(&self) -> bool {
+        self.0 == [0; 16]
+    }
+
+    pub fn is_legacy(&self) -> bool {
+        !self.is_nil() && self.0[..12] == [0; 12]
+    }
+}
+
+// std's hasher keys are seeded by the os, so no rng crate is needed. a
+// counter keeps uuids of one run apart where they aren't (wasm32 without a
+// browser, whose keys are fixed)
+fn hashed_bytes() -> [u8; 16] {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut bytes = [0; 16];
+    for half in bytes.chunks_mut(8) {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+        half.copy_from_slice(&hasher.finish().to_le_bytes());
+    }
+    bytes
+}
+
+#[cfg(not(all(feature = "web", target_arch = "wasm32")))]
+fn random_bytes() -> [u8; 16] {
+    hashed_bytes()
+}
+
+// in the browser from crypto.getRandomValues, and Math.random where there's
+// no window (workers)
+#[cfg(all(feature = "web", target_arch = "wasm32"))]
+fn random_bytes() -> [u8; 16] {
+    let mut bytes = hashed_bytes();
+    let crypto = web_sys::window().and_then(|window| window.crypto().ok());
+    let filled =
+        crypto.is_some_and(|crypto| crypto.get_random_values_with_u8_array(&mut bytes).is_ok());
+    if !filled {
+        for byte in bytes.iter_mut() {
+            *byte ^= (js_sys::Math::random() * 256.0) as u8;
+        }
+    }
+    bytes
+}
+
+fn hex_bytes(text: &str, out: &mut [u8]) -> Option<()> {
+    if text.len() != out.len() * 2 || !text.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    for (index, byte) in out.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&text[index * 2..index * 2 + 2], 16).ok()?;
+    }
+    Some(())
+}
+
+impl FromStr for Uuid {
+    type Err = ParseUuidError;
+
+    fn from_str(text: &str) -> Result<Uuid, ParseUuidError> {
+        let error = || ParseUuidError(text.to_string());
+        if text.len() == 8 {
+            let mut bytes = [0; 4];
+            hex_bytes(text, &mut bytes).ok_or_else(error)?;
+            return Ok(Uuid::from_timestamp(u32::from_be_bytes(bytes)));
+        }
+        let groups = text.split('-').collect::<Vec<_>>();
+        if groups.iter().map(|group| group.len()).ne([8, 4, 4, 4, 12]) {
+            return Err(error());
+        }
+        let mut bytes = [0; 16];
+        let mut start = 0;
+        for group in groups {
+            let end = start + group.len() / 2;
+            hex_bytes(group, &mut bytes[start..end]).ok_or_else(error)?;
+            start = end;
+        }
+        Ok(Uuid(bytes))
+    }
+}
+
+impl fmt::Display for Uuid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_legacy() {
+            // kicad writes timestamps as upper case hex
+            for byte in &self.0[12..] {
+                write!(f, "{:02X}", byte)?;
+            }
+            return Ok(());
+        }
+        for (index, byte) in self.0.iter().enumerate() {
+            if let 4 | 6 | 8 | 10 = index {
+                write!(f, "-")?;
+            }
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Uuid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Uuid({})", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let text = "e1d888f9-47ba-4b65-8b76-ae4073eb1c03";
+        let uuid = text.parse::<Uuid>().unwrap();
+        assert_eq!(uuid.to_string(), text);
+        assert!(!uuid.is_legacy());
+        // upper case is the same uuid, written in kicad's lower case
+        let upper = text.to_uppercase().parse::<Uuid>().unwrap();
+        assert_eq!(upper, uuid);
+        assert_eq!(upper.to_string(), text);
+        assert_eq!(
+            Uuid::nil().to_string(),
+            "00000000-0000-0000-0000-000000000000"
+        );
+    }
+
+    #[test]
+    fn legacy_timestamps() {
+        let uuid = "5e3b7c1a".parse::<Uuid>().unwrap();
+        assert!(uuid.is_legacy());
+        assert_eq!(uuid, Uuid::from_timestamp(0x5e3b7c1a));
+        assert_eq!(uuid.to_string(), "5E3B7C1A");
+        assert_eq!(uuid.to_string().parse::<Uuid>(), Ok(uuid));
+        assert!(!"00000000".parse::<Uuid>().unwrap().is_legacy());
+    }
+
+    #[test]
+    fn rejected() {
+        for text in [
+            "",
+            "5e3b7c1",
+            "5e3b7c1g",
+            "e1d888f9-47ba-4b65-8b76-ae4073eb1c0",
+            "e1d888f947ba4b658b76ae4073eb1c03",
+            "e1d888f9-47ba-4b65-8b76-ae4073eb1c0x",
+            "e1d888f9-47ba4-b65-8b76-ae4073eb1c03",
+            "+1d888f9-47ba-4b65-8b76-ae4073eb1c03",
+        ] {
+            assert_eq!(text.parse::<Uuid>(), Err(ParseUuidError(text.to_string())));
+        }
+    }
+
+    #[test]
+    fn from_text() {
+        let text = "e1d888f9-47ba-4b65-8b76-ae4073eb1c03";
+        assert_eq!(Uuid::from_text(text), text.parse::<Uuid>().unwrap());
+        assert_eq!(
+            Uuid::from_text("5E3B7C1A"),
+            Uuid::from_timestamp(0x5e3b7c1a)
+        );
+        // anything else always gets the same, distinct uuid
+        let odd = Uuid::from_text("not-a-uuid");
+        assert_eq!(odd, Uuid::from_text("not-a-uuid"));
+        assert_ne!(odd, Uuid::from_text("not-a-uuid2"));
+        assert!(!odd.is_nil() && !odd.is_legacy());
+        assert_eq!(odd.as_bytes()[6] >> 4, 8);
+    }
+
+    #[test]
+    fn random() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        assert_ne!(a, b);
+        assert_eq!(a.as_bytes()[6] >> 4, 4);
+        assert_eq!(a.as_bytes()[8] >> 6, 2);
+    }
+}
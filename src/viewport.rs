@@ -0,0 +1,153 @@
+use crate::renderer::Transform;
+use crate::schematic::Point;
+
+// maps sheet mm to screen pixels: the point at the screen's top left and
+// how many pixels a mm is
+#[derive(Debug, Clone)]
+pub struct Viewport {
+    pub offset: Point,
+    pub scale: f64,
+}
+
+impl Viewport {
+    // zoom limits in px per mm, a whole A0 sheet on a phone down to a pin
+    // filling the screen
+    pub const MIN_SCALE: f64 = 0.1;
+    pub const MAX_SCALE: f64 = 400.0;
+
+    pub fn new() -> Viewport {
+        Viewport {
+            offset: Point::new(0.0, 0.0),
+            scale: 4.0,
+        }
+    }
+
+    pub fn transform(&self) -> Transform {
+        Transform::scale(self.scale, self.scale)
+            .then(&Transform::translate(-self.offset.x, -self.offset.y))
+    }
+
+    pub fn to_model(&self, x: f64, y: f64) -> Point {
+        Point::new(
+            self.offset.x + x / self.scale,
+            self.offset.y + y / self.scale,
+        )
+    }
+
+    pub fn to_screen(&self, point: &Point) -> (f64, f64) {
+        (
+            (point.x - self.offset.x) * self.scale,
+            (point.y - self.offset.y) * self.scale,
+        )
+    }
+
+    pub fn set_scale(&mut self, scale: f64) {
+        self.scale = scale.clamp(Viewport::MIN_SCALE, Viewport::MAX_SCALE);
+    }
+
+    // zooms by factor keeping the sheet point under (x, y) px in place
+    pub fn zoom_at(&mut self, factor: f64, x: f64, y: f64) {
+        let anchor = self.to_model(x, y);
+        self.set_scale(self.scale * factor);
+        self.offset = Point::new(anchor.x - x / self.scale, anchor.y - y / self.scale);
+    }
+
+    // moves the sheet by (dx, dy) px, like dragging it
+    pub fn pan_by(&mut self, dx: f64, dy: f64) {
+        self.offset.x -= dx / self.scale;
+        self.offset.y -= dy / self.scale;
+    }
+
+    // puts a sheet point in the middle of a width x height px screen
+    pub fn center_on(&mut self, point: &Point, width: f64, height: f64) {
+        self.offset = Point::new(
+            point.x - width / 2.0 / self.scale,
+            point.y - height / 2.0 / self.scale,
+        );
+    }
+
+    // shows the area min..max whole and centered, with margin px around it
+    pub fn fit(&mut self, min: &Point, max: &Point, width: f64, height: f64, margin: f64) {
+        let (w, h) = ((max.x - min.x).max(1e-3), (max.y - min.y).max(1e-3));
+        let scale = ((width - 2.0 * margin) / w).min((height - 2.0 * margin) / h);
+        self.set_scale(scale);
+        let center = Point::new((min.x + max.x) / 2.0, (min.y + max.y) / 2.0);
+        self.center_on(&center, width, height);
+    }
+}
+
+impl Default for Viewport {
+    fn default() -> Viewport {
+        Viewport::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: (f64, f64), b: (f64, f64)) -> bool {
+        (a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9
+    }
+
+    #[test]
+    fn screen_round_trip() {
+        let mut viewport = Viewport::new();
+        viewport.offset = Point::new(-12.5, 40.0);
+        viewport.set_scale(3.7);
+        for point in [
+            Point::new(0.0, 0.0),
+            Point::new(100.33, 76.2),
+            Point::new(-5.0, 1e4),
+        ] {
+            let (x, y) = viewport.to_screen(&point);
+            let back = viewport.to_model(x, y);
+            assert!(close((back.x, back.y), (point.x, point.y)));
+        }
+        assert_eq!(viewport.to_screen(&viewport.offset.clone()), (0.0, 0.0));
+    }
+
+    #[test]
+    fn zoom_keeps_the_anchor() {
+        let mut viewport = Viewport::new();
+        viewport.offset = Point::new(20.0, 10.0);
+        let anchor = viewport.to_model(300.0, 200.0);
+        for factor in [2.0, 0.5, 1.5, 1e6, 1e-6] {
+            viewport.zoom_at(factor, 300.0, 200.0);
+            assert!(
+                close(viewport.to_screen(&anchor), (300.0, 200.0)),
+                "{}",
+                factor
+            );
+        }
+        // the last two hit the limits and still kept it
+        assert_eq!(viewport.scale, Viewport::MIN_SCALE);
+    }
+
+    #[test]
+    fn scale_is_clamped() {
+        let mut viewport = Viewport::new();
+        viewport.set_scale(0.0);
+        assert_eq!(viewport.scale, Viewport::MIN_SCALE);
+        viewport.set_scale(1e9);
+        assert_eq!(viewport.scale, Viewport::MAX_SCALE);
+        viewport.set_scale(12.0);
+        assert_eq!(viewport.scale, 12.0);
+    }
+
+    #[test]
+    fn fit_centers_the_box() {
+        let mut viewport = Viewport::new();
+        let (min, max) = (Point::new(10.0, 20.0), Point::new(110.0, 70.0));
+        viewport.fit(&min, &max, 800.0, 600.0, 50.0);
+        // the width is what limits it, 700 px for 100 mm
+        assert_eq!(viewport.scale, 7.0);
+        assert!(close(viewport.to_screen(&min), (50.0, 125.0)));
+        assert!(close(viewport.to_screen(&max), (750.0, 475.0)));
+
+        // a single point doesn't divide by zero and is still centered
+        viewport.fit(&min, &min, 800.0, 600.0, 50.0);
+        assert_eq!(viewport.scale, Viewport::MAX_SCALE);
+        assert!(close(viewport.to_screen(&min), (400.0, 300.0)));
+    }
+}
@@ -0,0 +1,1796 @@
+use crate::edit::{Axis, EditError, History};
+use crate::erc::ErcViolation;
+use crate::hierarchy::Hierarchy;
+use crate::netlist::Netlist;
+use crate::schematic::*;
+use crate::search::{SearchHit, SearchOptions};
+use crate::state::ViewState;
+use crate::svg;
+use crate::theme::Theme;
+use crate::viewport::Viewport;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+// kicad's default grid, drags snap to it
+const GRID: f64 = 1.27;
+
+// touch gestures, times in ms and distances in css px
+const TAP_TIME: f64 = 250.0; // longest press that's still a tap
+const TAP_SLOP: f64 = 10.0; // how far a tap may wander
+const DOUBLE_TAP_TIME: f64 = 300.0;
+const DOUBLE_TAP_SLOP: f64 = 30.0;
+const FRICTION: f64 = 0.003; // momentum lost per ms
+const MIN_VELOCITY: f64 = 0.02; // px per ms, slower flings stop
+
+// how long zooming to something takes, ms
+const FLIGHT_TIME: f64 = 300.0;
+
+// keyboard zoom and pan steps, a factor and a share of the canvas
+const ZOOM_STEP: f64 = 1.5;
+const PAN_STEP: f64 = 0.1;
+
+// what keys do, bound by name from js with `Viewer::bind`
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Action {
+    ZoomFit,
+    ZoomIn,
+    ZoomOut,
+    PanLeft,
+    PanRight,
+    PanUp,
+    PanDown,
+    Find,
+    FindNext,
+    FindPrevious,
+    Clear,
+    LeaveSheet,
+    HighlightNet,
+    Rotate,
+    MirrorX,
+    MirrorY,
+    Undo,
+    Redo,
+}
+
+const ACTIONS: [(&str, Action); 18] = [
+    ("zoom_fit", Action::ZoomFit),
+    ("zoom_in", Action::ZoomIn),
+    ("zoom_out", Action::ZoomOut),
+    ("pan_left", Action::PanLeft),
+    ("pan_right", Action::PanRight),
+    ("pan_up", Action::PanUp),
+    ("pan_down", Action::PanDown),
+    ("find", Action::Find),
+    ("find_next", Action::FindNext),
+    ("find_previous", Action::FindPrevious),
+    ("clear", Action::Clear),
+    ("leave_sheet", Action::LeaveSheet),
+    ("highlight_net", Action::HighlightNet),
+    ("rotate", Action::Rotate),
+    ("mirror_x", Action::MirrorX),
+    ("mirror_y", Action::MirrorY),
+    ("undo", Action::Undo),
+    ("redo", Action::Redo),
+];
+
+// eeschema's default hotkeys
+const KEYS: [(&str, Action); 20] = [
+    ("Home", Action::ZoomFit),
+    ("F1", Action::ZoomIn),
+    ("F2", Action::ZoomOut),
+    ("ArrowLeft", Action::PanLeft),
+    ("ArrowRight", Action::PanRight),
+    ("ArrowUp", Action::PanUp),
+    ("ArrowDown", Action::PanDown),
+    ("Ctrl+F", Action::Find),
+    ("F3", Action::FindNext),
+    ("Shift+F3", Action::FindPrevious),
+    ("Escape", Action::Clear),
+    ("Backspace", Action::LeaveSheet),
+    ("~", Action::HighlightNet),
+    ("`", Action::HighlightNet),
+    ("R", Action::Rotate),
+    ("X", Action::MirrorX),
+    ("Y", Action::MirrorY),
+    ("Ctrl+Z", Action::Undo),
+    ("Ctrl+Shift+Z", Action::Redo),
+    ("Ctrl+Y", Action::Redo),
+];
+
+impl Action {
+    fn name(self) -> &'static str {
+        ACTIONS
+            .iter()
+            .find(|(_, action)| *action == self)
+            .unwrap()
+            .0
+    }
+
+    fn from_name(name: &str) -> Option<Action> {
+        ACTIONS
+            .iter()
+            .find(|(other, _)| *other == name)
+            .map(|(_, action)| *action)
+    }
+}
+
+// a key as bindings name it: "Ctrl+Shift+Z", "Home", "~". shift is only
+// named for letters and named keys, other characters show it already
+fn key_name(ctrl: bool, alt: bool, shift: bool, key: &str) -> String {
+    let letter = key.chars().count() == 1 && key.chars().all(char::is_alphabetic);
+    let named = key.chars().count() > 1;
+    let mut name = String::new();
+    if ctrl {
+        name += "Ctrl+";
+    }
+    if alt {
+        name += "Alt+";
+    }
+    if shift && (letter || named) {
+        name += "Shift+";
+    }
+    match letter {
+        true => name += &key.to_uppercase(),
+        false => name += key,
+    }
+    name
+}
+
+// "ctrl+f", "Cmd+Shift+z" or "Ctrl++" as `key_name` would have it
+fn parse_key(text: &str) -> Result<String, String> {
+    let (modifiers, key) = match text.strip_suffix("++") {
+        Some(modifiers) => (modifiers, "+"),
+        None => match text.rfind('+') {
+            Some(split) if text != "+" => (&text[..split], &text[split + 1..]),
+            _ => ("", text),
+        },
+    };
+    if key.is_empty() {
+        return Err("empty key".to_string());
+    }
+    let (mut ctrl, mut alt, mut shift) = (false, false, false);
+    for modifier in modifiers.split('+').filter(|modifier| !modifier.is_empty()) {
+        match modifier.to_lowercase().as_str() {
+            "ctrl" | "control" | "cmd" | "meta" => ctrl = true,
+            "alt" | "option" => alt = true,
+            "shift" => shift = true,
+            _ => return Err(format!("unknown modifier {:?} in {:?}", modifier, text)),
+        }
+    }
+    Ok(key_name(ctrl, alt, shift, key))
+}
+
+// the draggable item under a point, labels and text sit on top of
+// symbols and the smallest symbol wins where they overlap
+fn pick(schem: &Schematic, at: &Point) -> Option<Uuid> {
+    let layer = |item: &ItemRef| match item {
+        ItemRef::Label(_) => Some(0),
+        ItemRef::Text(_) => Some(1),
+        ItemRef::Symbol(_) => Some(2),
+        _ => None,
+    };
+    let area = |item: ItemRef| match schem.item_bounds(item) {
+        Some((min, max)) => (max.x - min.x) * (max.y - min.y),
+        None => f64::INFINITY,
+    };
+    schem
+        .hit_test(at.x, at.y, 0.5)
+        .into_iter()
+        .filter_map(|item| Some((layer(&item)?, area(item), item)))
+        .min_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)))
+        .map(|(_, _, item)| schem.item_uuid(item))
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+fn middle(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0)
+}
+
+// fingers on the canvas
+#[derive(Default)]
+struct Touch {
+    points: Vec<(i32, (f64, f64))>,   // pointer id and last position
+    start: Option<(f64, (f64, f64))>, // time and place of what may be a tap
+    last_tap: Option<(f64, (f64, f64))>,
+    velocity: (f64, f64), // of a one finger pan, px per ms
+    time: f64,            // of the last move
+}
+
+// an animated move of the view, eased in scale and position
+struct Flight {
+    from: Viewport,
+    to: Viewport,
+    start: Option<f64>, // time of its first frame
+}
+
+// an item following the pointer, along with the wire ends on its pins
+struct Drag {
+    uuid: Uuid,
+    start: Point,      // where the pointer went down, mm
+    moved: (f64, f64), // how far the item has gone so far
+    ends: Vec<(Uuid, usize)>,
+}
+
+// everything a viewer draws and edits, shared with its event handlers
+struct View {
+    canvas: web_sys::HtmlCanvasElement,
+    // the design, the shown sheet is taken out into `schematic` and its
+    // undo history into `history` while it's shown
+    hierarchy: Hierarchy,
+    sheet: usize,
+    histories: Vec<History>,
+    schematic: Schematic,
+    loaded: bool,
+    markers: Vec<ErcViolation>,
+    netlist: Netlist, // of the shown sheet, kept up to date by `edited`
+    theme: Theme,
+    viewport: Viewport,
+    pan: Option<(f64, f64)>, // last pointer position of a mouse pan, px
+    touch: Touch,
+    momentum: Option<((f64, f64), f64)>, // velocity and time of the last frame
+    flight: Option<Flight>,
+    frame: Option<Closure<dyn FnMut(f64)>>, // calls `step`
+    frame_id: Option<i32>,                  // a requested frame
+    history: History,
+    selected: Option<Uuid>,
+    hovered: Option<Uuid>, // the symbol under the mouse
+    drag: Option<Drag>,
+    highlight: Option<String>,      // net name
+    found: Vec<(usize, SearchHit)>, // search hits on every sheet
+    hit: Option<usize>,             // the one shown
+    highlighted: Vec<ItemRef>,      // the net and hits on this sheet
+    cursor: Option<(f64, f64)>,     // last pointer position, px
+    keys: HashMap<String, Action>,
+    handlers: HashMap<String, js_sys::Function>,
+    events: Vec<(&'static str, JsValue)>, // for the handlers, see `dispatch`
+    ratio: f64,                           // device px per css px
+    ratio_watch: Vec<MediaWatch>,         // the current one last
+}
+
+// a matchMedia query for the current pixel ratio, it fires when the ratio
+// changes (zooming the page, moving to another screen)
+struct MediaWatch {
+    query: web_sys::MediaQueryList,
+    closure: Closure<dyn FnMut(web_sys::Event)>,
+}
+
+impl View {
+    fn draw(&self) -> Result<(), JsValue> {
+        self.schematic.draw(
+            &self.canvas,
+            &self.viewport,
+            self.ratio,
+            &self.theme,
+            &self.markers,
+            &self.highlighted,
+        )
+    }
+
+    // sizes the backing store to the canvas' css size at the screen's pixel
+    // ratio, the viewport stays in css px so nothing else changes
+    fn resize(&mut self) {
+        self.ratio = web_sys::window().unwrap().device_pixel_ratio();
+        let (width, height) = self.size();
+        let width = (width * self.ratio).round() as u32;
+        let height = (height * self.ratio).round() as u32;
+        if self.canvas.width() != width {
+            self.canvas.set_width(width);
+        }
+        if self.canvas.height() != height {
+            self.canvas.set_height(height);
+        }
+    }
+
+    // pointer position on the canvas, px
+    fn position(&self, event: &web_sys::MouseEvent) -> (f64, f64) {
+        let rect = self.canvas.get_bounding_client_rect();
+        (
+            event.client_x() as f64 - rect.left() - self.canvas.client_left() as f64,
+            event.client_y() as f64 - rect.top() - self.canvas.client_top() as f64,
+        )
+    }
+
+    // pointer position in mm
+    fn to_model(&self, event: &web_sys::MouseEvent) -> Point {
+        let (x, y) = self.position(event);
+        self.viewport.to_model(x, y)
+    }
+
+    // css px
+    fn size(&self) -> (f64, f64) {
+        (
+            self.canvas.client_width() as f64,
+            self.canvas.client_height() as f64,
+        )
+    }
+
+    fn pan_to(&mut self, x: f64, y: f64) {
+        let (width, height) = self.size();
+        self.viewport.center_on(&Point::new(x, y), width, height);
+    }
+
+    // the view showing min..max whole, margin px around it
+    fn framing(&self, min: &Point, max: &Point, margin: f64) -> Viewport {
+        let (width, height) = self.size();
+        let mut viewport = self.viewport.clone();
+        viewport.fit(min, max, width, height, margin);
+        viewport
+    }
+
+    // everything drawn on the sheet, or the page when there's nothing
+    fn content_view(&self) -> Viewport {
+        let (min, max) = self
+            .schematic
+            .content_bounds()
+            .unwrap_or_else(|| self.schematic.page_bounds());
+        self.framing(&min, &max, 20.0)
+    }
+
+    // the whole page frame
+    fn page_view(&self) -> Viewport {
+        let (min, max) = self.schematic.page_bounds();
+        self.framing(&min, &max, 10.0)
+    }
+
+    // an area in the middle of the canvas with the sheet around it, small
+    // items aren't blown up to fill it
+    fn area_view(&self, min: &Point, max: &Point) -> Viewport {
+        let center = Point::new((min.x + max.x) / 2.0, (min.y + max.y) / 2.0);
+        let (width, height) = ((max.x - min.x).max(40.0), (max.y - min.y).max(30.0));
+        self.framing(
+            &Point::new(center.x - width / 2.0, center.y - height / 2.0),
+            &Point::new(center.x + width / 2.0, center.y + height / 2.0),
+            40.0,
+        )
+    }
+
+    // moves the view there over a few frames, at once for users who asked
+    // for reduced motion
+    fn fly_to(&mut self, to: Viewport) {
+        self.momentum = None;
+        let reduced = web_sys::window()
+            .unwrap()
+            .match_media("(prefers-reduced-motion: reduce)")
+            .ok()
+            .flatten()
+            .is_some_and(|query| query.matches());
+        if reduced {
+            self.flight = None;
+            self.viewport = to;
+            return;
+        }
+        self.flight = Some(Flight {
+            from: self.viewport.clone(),
+            to,
+            start: None,
+        });
+        self.animate();
+    }
+
+    // stops whatever the view was doing on its own, the user took over
+    fn land(&mut self) {
+        self.flight = None;
+        self.momentum = None;
+    }
+
+    fn pick(&self, at: &Point) -> Option<Uuid> {
+        pick(&self.schematic, at)
+    }
+
+    fn press(&mut self, at: Point) -> bool {
+        self.select(self.pick(&at));
+        let uuid = match self.selected {
+            Some(uuid) => uuid,
+            None => return false,
+        };
+        let ends = self.schematic.attached_ends(&uuid);
+        let mut uuids = vec![uuid];
+        uuids.extend(ends.iter().map(|(wire, _)| *wire));
+        self.history.begin(&self.schematic, &uuids);
+        self.drag = Some(Drag {
+            uuid,
+            start: at,
+            moved: (0.0, 0.0),
+            ends,
+        });
+        true
+    }
+
+    fn drag_to(&mut self, at: Point) -> bool {
+        let drag = match self.drag.as_mut() {
+            Some(drag) => drag,
+            None => return false,
+        };
+        let snap = |delta: f64| (delta / GRID).round() * GRID;
+        let to = (snap(at.x - drag.start.x), snap(at.y - drag.start.y));
+        let (dx, dy) = (to.0 - drag.moved.0, to.1 - drag.moved.1);
+        if dx == 0.0 && dy == 0.0 {
+            return false;
+        }
+        drag.moved = to;
+        let _ = self.schematic.move_item(&drag.uuid, dx, dy);
+        for (wire, index) in &drag.ends {
+            let _ = self.schematic.move_wire_end(wire, *index, dx, dy);
+        }
+        true
+    }
+
+    fn release(&mut self) -> bool {
+        match self.drag.take() {
+            Some(drag) if drag.moved != (0.0, 0.0) => {
+                self.history.commit(&self.schematic);
+                self.edited();
+                true
+            }
+            Some(_) => {
+                self.history.cancel(&mut self.schematic);
+                false
+            }
+            None => false,
+        }
+    }
+
+    // what a key event is bound to does, none if it's not bound
+    fn key(&mut self, event: &web_sys::KeyboardEvent) -> Option<bool> {
+        let name = key_name(
+            event.ctrl_key() || event.meta_key(),
+            event.alt_key(),
+            event.shift_key(),
+            &event.key(),
+        );
+        let action = *self.keys.get(&name)?;
+        Some(self.act(action))
+    }
+
+    // zooms about the pointer and highlights under it, or the middle of the
+    // canvas before the pointer came
+    fn act(&mut self, action: Action) -> bool {
+        self.land();
+        let (width, height) = self.size();
+        let (x, y) = self.cursor.unwrap_or((width / 2.0, height / 2.0));
+        let (dx, dy) = (width * PAN_STEP, height * PAN_STEP);
+        match action {
+            Action::ZoomFit => self.fly_to(self.content_view()),
+            Action::ZoomIn => self.viewport.zoom_at(ZOOM_STEP, x, y),
+            Action::ZoomOut => self.viewport.zoom_at(1.0 / ZOOM_STEP, x, y),
+            Action::PanLeft => self.viewport.pan_by(dx, 0.0),
+            Action::PanRight => self.viewport.pan_by(-dx, 0.0),
+            Action::PanUp => self.viewport.pan_by(0.0, dy),
+            Action::PanDown => self.viewport.pan_by(0.0, -dy),
+            Action::Find => {
+                self.events.push(("find", JsValue::UNDEFINED));
+                return false;
+            }
+            Action::FindNext | Action::FindPrevious => {
+                let sheet = self.sheet;
+                let by = match action {
+                    Action::FindNext => 1,
+                    _ => -1,
+                };
+                let done = self.step_hit(by);
+                if self.sheet != sheet {
+                    self.sheet_changed();
+                }
+                return done;
+            }
+            Action::Clear => {
+                self.select(None);
+                self.found.clear();
+                self.hit = None;
+                return self.highlight_net(None);
+            }
+            Action::LeaveSheet => {
+                return match self.hierarchy.sheets[self.sheet].parent {
+                    Some(parent) => self.navigate(parent),
+                    None => false,
+                }
+            }
+            Action::HighlightNet => {
+                // a few px either side of the pointer
+                let point = self.viewport.to_model(x, y);
+                let tolerance = 4.0 / self.viewport.scale;
+                let net = self.schematic.net_at(&self.netlist, &point, tolerance);
+                return self.highlight_net(net.map(|net| self.netlist.nets[net].name.clone()));
+            }
+            Action::Rotate | Action::MirrorX | Action::MirrorY => {
+                return self.edit_selected(action)
+            }
+            Action::Undo => return self.undo(),
+            Action::Redo => return self.redo(),
+        }
+        true
+    }
+
+    // rotates or mirrors the selection (a drag in progress takes the change
+    // into its own step)
+    fn edit_selected(&mut self, action: Action) -> bool {
+        let uuid = match self.selected {
+            Some(uuid) => uuid,
+            None => return false,
+        };
+        let edit = |schem: &mut Schematic| match action {
+            Action::Rotate => schem.rotate_item(&uuid),
+            Action::MirrorX => schem.mirror_item(&uuid, Axis::X),
+            Action::MirrorY => schem.mirror_item(&uuid, Axis::Y),
+            _ => Err(EditError::Unsupported(uuid)),
+        };
+        let done = match self.drag {
+            Some(_) => edit(&mut self.schematic),
+            None => self.history.record(&mut self.schematic, &[uuid], edit),
+        };
+        if done.is_ok() && self.drag.is_none() {
+            self.edited();
+        }
+        done.is_ok()
+    }
+
+    fn undo(&mut self) -> bool {
+        let done = self.history.undo(&mut self.schematic);
+        self.edited();
+        done
+    }
+
+    fn redo(&mut self) -> bool {
+        let done = self.history.redo(&mut self.schematic);
+        self.edited();
+        done
+    }
+
+    // erc markers and the highlight follow the edit
+    fn edited(&mut self) {
+        self.markers = self.schematic.erc();
+        self.netlist = self.schematic.netlist();
+        self.rehighlight();
+    }
+
+    // the page hears of a new selection with the symbol's details, null for
+    // anything else
+    fn select(&mut self, uuid: Option<Uuid>) {
+        if uuid != self.selected {
+            self.selected = uuid;
+            let info = self.symbol_info(uuid).unwrap_or_else(|error| {
+                report(Err(error));
+                JsValue::NULL
+            });
+            self.events.push(("select", info));
+        }
+    }
+
+    // a tooltip's worth, as the mouse moves onto a symbol and null as it
+    // leaves it (or the canvas, with no point)
+    fn hover(&mut self, at: Option<&Point>) {
+        let uuid = at.and_then(|at| {
+            self.schematic
+                .hit_test(at.x, at.y, 0.5)
+                .into_iter()
+                .find(|item| matches!(item, ItemRef::Symbol(_)))
+                .map(|item| self.schematic.item_uuid(item))
+        });
+        if uuid != self.hovered {
+            self.hovered = uuid;
+            let info = self.symbol_info(uuid).unwrap_or_else(|error| {
+                report(Err(error));
+                JsValue::NULL
+            });
+            self.events.push(("hover", info));
+        }
+    }
+
+    // see `SymbolInfo` for the shape
+    fn symbol_info(&self, uuid: Option<Uuid>) -> Result<JsValue, JsValue> {
+        match uuid.and_then(|uuid| self.schematic.find_by_uuid(&uuid)) {
+            Some(ItemRef::Symbol(index)) => {
+                to_js(&self.schematic.symbol_info(&self.netlist, index))
+            }
+            _ => Ok(JsValue::NULL),
+        }
+    }
+
+    // nets are highlighted by name like kicad does, so the highlight
+    // carries over to other sheets where the name is used
+    fn highlight_net(&mut self, name: Option<String>) -> bool {
+        self.highlight = name;
+        self.rehighlight();
+        true
+    }
+
+    fn rehighlight(&mut self) {
+        let mut highlighted = Vec::new();
+        if let Some(name) = &self.highlight {
+            for net in &self.netlist.nets {
+                if net.name == *name || net.names.contains(name) {
+                    highlighted.extend(self.schematic.net_items(net));
+                }
+            }
+        }
+        for (sheet, hit) in &self.found {
+            if *sheet != self.sheet {
+                continue;
+            }
+            match hit.field.as_str() {
+                // the whole net, not just where it was found
+                "Net" => {
+                    for net in self.netlist.nets.iter().filter(|net| net.name == hit.text) {
+                        highlighted.extend(self.schematic.net_items(net));
+                    }
+                }
+                _ => highlighted.extend(self.schematic.find_by_uuid(&hit.uuid)),
+            }
+        }
+        self.highlighted = highlighted;
+    }
+
+    // hits on every sheet in page order, best first on each. none is shown
+    // until the first step
+    fn search(&mut self, query: &str, options: &SearchOptions) {
+        self.found.clear();
+        self.hit = None;
+        for sheet in 0..self.hierarchy.sheets.len() {
+            let schem = match sheet == self.sheet {
+                true => &self.schematic,
+                false => &self.hierarchy.sheets[sheet].schematic,
+            };
+            let hits = schem.search(query, options);
+            self.found.extend(hits.into_iter().map(|hit| (sheet, hit)));
+        }
+        self.rehighlight();
+    }
+
+    // the next or previous hit, round to the start after the last
+    fn step_hit(&mut self, by: isize) -> bool {
+        if self.found.is_empty() {
+            return false;
+        }
+        let count = self.found.len() as isize;
+        let hit = match self.hit {
+            Some(hit) => (hit as isize + by).rem_euclid(count),
+            None if by > 0 => 0,
+            None => count - 1,
+        } as usize;
+        self.hit = Some(hit);
+        let (sheet, uuid) = (self.found[hit].0, self.found[hit].1.uuid);
+        self.open_sheet(sheet);
+        if let Some(item) = self.schematic.find_by_uuid(&uuid) {
+            if let Some((min, max)) = self.schematic.item_bounds(item) {
+                self.fly_to(self.area_view(&min, &max));
+            }
+        }
+        true
+    }
+
+    // flies to what `find` gives the bounds of, on the shown sheet or else
+    // the first other sheet that has it
+    fn zoom_to(&mut self, find: impl Fn(&Schematic) -> Option<(Point, Point)>) -> bool {
+        let found = std::iter::once(self.sheet)
+            .chain(0..self.hierarchy.sheets.len())
+            .find_map(|sheet| {
+                let schem = match sheet == self.sheet {
+                    true => &self.schematic,
+                    false => &self.hierarchy.sheets[sheet].schematic,
+                };
+                find(schem).map(|bounds| (sheet, bounds))
+            });
+        match found {
+            Some((sheet, (min, max))) => {
+                self.open_sheet(sheet);
+                self.fly_to(self.area_view(&min, &max));
+                true
+            }
+            None => false,
+        }
+    }
+
+    // a new design, shown from its root sheet
+    fn show(&mut self, mut hierarchy: Hierarchy) {
+        std::mem::swap(&mut self.schematic, &mut hierarchy.sheets[0].schematic);
+        self.histories = (0..hierarchy.sheets.len())
+            .map(|_| History::new())
+            .collect();
+        self.hierarchy = hierarchy;
+        self.sheet = 0;
+        self.history = History::new();
+        self.loaded = true;
+        self.opened();
+    }
+
+    // puts the shown sheet back into the hierarchy and takes out another
+    fn open_sheet(&mut self, index: usize) -> bool {
+        if index == self.sheet || index >= self.hierarchy.sheets.len() {
+            return false;
+        }
+        if self.drag.take().is_some() {
+            self.history.cancel(&mut self.schematic);
+        }
+        for sheet in [self.sheet, index] {
+            std::mem::swap(
+                &mut self.schematic,
+                &mut self.hierarchy.sheets[sheet].schematic,
+            );
+            std::mem::swap(&mut self.history, &mut self.histories[sheet]);
+        }
+        self.sheet = index;
+        self.opened();
+        true
+    }
+
+    // a sheet change the user made, the page hears of it
+    fn navigate(&mut self, index: usize) -> bool {
+        let done = self.open_sheet(index);
+        if done {
+            self.sheet_changed();
+        }
+        done
+    }
+
+    fn sheet_changed(&mut self) {
+        let path = JsValue::from_str(&self.hierarchy.sheets[self.sheet].path);
+        self.events.push(("sheet", path));
+    }
+
+    // into the sheet of the sheet symbol at a point, like double clicking
+    // one in eeschema
+    fn enter(&mut self, at: &Point) -> bool {
+        let child = self
+            .schematic
+            .sheets
+            .iter()
+            .find(|sheet| sheet.contains(at))
+            .and_then(|sheet| self.hierarchy.child(self.sheet, &sheet.uuid));
+        match child {
+            Some(child) => self.navigate(child),
+            None => false,
+        }
+    }
+
+    // the view to link to, where it's flying to if it is
+    fn state(&self) -> ViewState {
+        let (width, height) = self.size();
+        let viewport = match &self.flight {
+            Some(flight) => &flight.to,
+            None => &self.viewport,
+        };
+        let reference = match self
+            .selected
+            .and_then(|uuid| self.schematic.find_by_uuid(&uuid))
+        {
+            Some(ItemRef::Symbol(index)) => self.schematic.symbs[index].prop("Reference"),
+            _ => None,
+        };
+        ViewState {
+            sheet: Some(self.hierarchy.sheets[self.sheet].path.clone()),
+            zoom: Some(viewport.scale),
+            center: Some(viewport.to_model(width / 2.0, height / 2.0)),
+            net: self.highlight.clone(),
+            reference: reference.map(|reference| reference.to_string()),
+        }
+    }
+
+    // opens the state's sheet, highlights its net and selects its symbol,
+    // then zooms where it says or onto the symbol. the page hears of the
+    // sheet and selection like it would of a click. nothing changes when the
+    // sheet or symbol isn't in the design
+    fn set_state(&mut self, state: &ViewState) -> Result<(), String> {
+        let mut sheet = match &state.sheet {
+            Some(path) => self
+                .hierarchy
+                .find(path)
+                .ok_or_else(|| format!("no sheet {:?}", path))?,
+            None => self.sheet,
+        };
+        let mut symbol = None;
+        if let Some(reference) = &state.reference {
+            // on the sheet asked for, or any when none was
+            let sheets = match state.sheet {
+                Some(_) => vec![sheet],
+                None => std::iter::once(sheet)
+                    .chain(0..self.hierarchy.sheets.len())
+                    .collect(),
+            };
+            let found = sheets.into_iter().find_map(|index| {
+                let schem = match index == self.sheet {
+                    true => &self.schematic,
+                    false => &self.hierarchy.sheets[index].schematic,
+                };
+                let symb = schem
+                    .symbs
+                    .iter()
+                    .find(|symb| symb.prop("Reference") == Some(reference.as_str()))?;
+                Some((index, symb.uuid, schem.symbol_bounds(reference)?))
+            });
+            let (index, uuid, bounds) =
+                found.ok_or_else(|| format!("no symbol {:?}", reference))?;
+            sheet = index;
+            symbol = Some((uuid, bounds));
+        }
+
+        if sheet != self.sheet {
+            self.select(None);
+            self.navigate(sheet);
+        }
+        self.highlight_net(state.net.clone());
+        self.select(symbol.as_ref().map(|(uuid, _)| *uuid));
+        self.land();
+        let (width, height) = self.size();
+        let center = self.viewport.to_model(width / 2.0, height / 2.0);
+        if let Some(zoom) = state.zoom {
+            self.viewport.set_scale(zoom);
+        }
+        match (&state.center, &symbol) {
+            (Some(center), _) => self.viewport.center_on(center, width, height),
+            (None, Some((_, (min, max)))) if state.zoom.is_none() => {
+                self.viewport = self.area_view(min, max)
+            }
+            (None, _) => self.viewport.center_on(&center, width, height),
+        }
+        Ok(())
+    }
+
+    fn opened(&mut self) {
+        self.selected = None;
+        self.hovered = None;
+        self.drag = None;
+        self.edited();
+        self.land();
+        self.viewport = self.content_view();
+    }
+
+    // mouse and pen drag items and pan, fingers pan and pinch
+    fn pointer_down(&mut self, event: &web_sys::PointerEvent) -> bool {
+        let _ = self.canvas.focus();
+        let _ = self.canvas.set_pointer_capture(event.pointer_id());
+        self.land();
+        let at = self.position(event);
+        if event.pointer_type() != "touch" {
+            let item = event.button() == 0 && self.press(self.to_model(event));
+            if !item && (event.button() == 0 || event.button() == 1) {
+                self.pan = Some(at);
+            }
+            return false;
+        }
+        let touch = &mut self.touch;
+        touch.points.push((event.pointer_id(), at));
+        touch.velocity = (0.0, 0.0);
+        touch.time = event.time_stamp();
+        touch.start = match touch.points.len() {
+            1 => Some((event.time_stamp(), at)),
+            _ => None,
+        };
+        false
+    }
+
+    fn pointer_move(&mut self, event: &web_sys::PointerEvent) -> bool {
+        let at = self.position(event);
+        self.cursor = Some(at);
+        if event.pointer_type() != "touch" {
+            return match self.pan {
+                Some(last) => {
+                    self.viewport.pan_by(at.0 - last.0, at.1 - last.1);
+                    self.pan = Some(at);
+                    true
+                }
+                None if self.drag.is_some() => self.drag_to(self.to_model(event)),
+                None => {
+                    let at = self.to_model(event);
+                    self.hover(Some(&at));
+                    false
+                }
+            };
+        }
+        let touch = &mut self.touch;
+        let index = match touch
+            .points
+            .iter()
+            .position(|(id, _)| *id == event.pointer_id())
+        {
+            Some(index) => index,
+            None => return false,
+        };
+        let old = touch.points.clone();
+        touch.points[index].1 = at;
+        if let Some((_, start)) = touch.start {
+            if distance(start, at) > TAP_SLOP {
+                touch.start = None;
+            }
+        }
+        match (old.as_slice(), touch.points.as_slice()) {
+            ([(_, a)], [(_, b)]) => {
+                let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+                self.viewport.pan_by(dx, dy);
+                // smoothed, so one jittery last move doesn't decide a fling
+                let time = event.time_stamp();
+                let dt = (time - touch.time).max(1.0);
+                touch.velocity = (
+                    0.8 * dx / dt + 0.2 * touch.velocity.0,
+                    0.8 * dy / dt + 0.2 * touch.velocity.1,
+                );
+                touch.time = time;
+            }
+            ([(_, a0), (_, a1), ..], [(_, b0), (_, b1), ..]) => {
+                let (from, to) = (middle(*a0, *a1), middle(*b0, *b1));
+                self.viewport.pan_by(to.0 - from.0, to.1 - from.1);
+                let before = distance(*a0, *a1);
+                if before > 1.0 {
+                    self.viewport
+                        .zoom_at(distance(*b0, *b1) / before, to.0, to.1);
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    // a lifted finger can end a tap, a double tap or fling the sheet on
+    fn pointer_up(&mut self, event: &web_sys::PointerEvent, cancelled: bool) -> bool {
+        let _ = self.canvas.release_pointer_capture(event.pointer_id());
+        if event.pointer_type() != "touch" {
+            self.pan = None;
+            return self.release();
+        }
+        let time = event.time_stamp();
+        let touch = &mut self.touch;
+        let before = touch.points.len();
+        touch.points.retain(|(id, _)| *id != event.pointer_id());
+        if before == 2 {
+            // the other finger pans on, but a pinch doesn't fling
+            touch.velocity = (0.0, 0.0);
+            touch.time = time;
+        }
+        if cancelled || !touch.points.is_empty() {
+            return false;
+        }
+        if let Some((start, at)) = touch.start.take() {
+            if time - start < TAP_TIME {
+                match touch.last_tap.take() {
+                    Some((last, place))
+                        if time - last < DOUBLE_TAP_TIME
+                            && distance(place, at) < DOUBLE_TAP_SLOP =>
+                    {
+                        return self.zoom_in_at(at);
+                    }
+                    _ => touch.last_tap = Some((time, at)),
+                }
+            }
+            return false;
+        }
+        // no fling if the finger stopped before lifting
+        let velocity = touch.velocity;
+        if time - touch.time < 50.0 && velocity.0.hypot(velocity.1) > MIN_VELOCITY {
+            self.momentum = Some((velocity, time));
+            self.animate();
+        }
+        false
+    }
+
+    // the item at a point fills the view, or it's twice as close there
+    fn zoom_in_at(&mut self, (x, y): (f64, f64)) -> bool {
+        let point = self.viewport.to_model(x, y);
+        let bounds = self
+            .pick(&point)
+            .and_then(|uuid| self.schematic.find_by_uuid(&uuid))
+            .and_then(|item| self.schematic.item_bounds(item));
+        match bounds {
+            Some((min, max)) => self.fly_to(self.framing(&min, &max, 40.0)),
+            None => self.viewport.zoom_at(2.0, x, y),
+        }
+        true
+    }
+
+    // asks for a frame, `step` runs in it
+    fn animate(&mut self) {
+        if self.frame_id.is_some() {
+            return;
+        }
+        if let Some(frame) = &self.frame {
+            self.frame_id = web_sys::window()
+                .unwrap()
+                .request_animation_frame(frame.as_ref().unchecked_ref())
+                .ok();
+        }
+    }
+
+    // one animation frame, true while there's more to come
+    fn step(&mut self, time: f64) -> bool {
+        self.fly(time) || self.coast(time)
+    }
+
+    // eases out, the scale changes evenly in ratio so zooming far doesn't
+    // rush, and the middle of the canvas slides between the two
+    fn fly(&mut self, time: f64) -> bool {
+        let (width, height) = self.size();
+        let flight = match &mut self.flight {
+            Some(flight) => flight,
+            None => return false,
+        };
+        let start = *flight.start.get_or_insert(time);
+        let t = ((time - start) / FLIGHT_TIME).clamp(0.0, 1.0);
+        if t >= 1.0 {
+            self.viewport = flight.to.clone();
+            self.flight = None;
+            return false;
+        }
+        let ease = 1.0 - (1.0 - t).powi(3);
+        let from = flight.from.to_model(width / 2.0, height / 2.0);
+        let to = flight.to.to_model(width / 2.0, height / 2.0);
+        let scale = flight.from.scale * (flight.to.scale / flight.from.scale).powf(ease);
+        let center = Point::new(
+            from.x + (to.x - from.x) * ease,
+            from.y + (to.y - from.y) * ease,
+        );
+        self.viewport.set_scale(scale);
+        self.viewport.center_on(&center, width, height);
+        true
+    }
+
+    // a fling slowing down
+    fn coast(&mut self, time: f64) -> bool {
+        let ((vx, vy), last) = match self.momentum {
+            Some(momentum) => momentum,
+            None => return false,
+        };
+        let dt = (time - last).clamp(0.0, 100.0);
+        self.viewport.pan_by(vx * dt, vy * dt);
+        let decay = (-dt * FRICTION).exp();
+        let velocity = (vx * decay, vy * decay);
+        if velocity.0.hypot(velocity.1) < MIN_VELOCITY {
+            self.momentum = None;
+            return false;
+        }
+        self.momentum = Some((velocity, time));
+        true
+    }
+}
+
+// calls the page's handlers for the events the view queued, after the view
+// is let go so they can use the viewer
+fn dispatch(view: &RefCell<View>) {
+    loop {
+        let (handler, value) = {
+            let view = &mut *view.borrow_mut();
+            if view.events.is_empty() {
+                return;
+            }
+            let (name, value) = view.events.remove(0);
+            (view.handlers.get(name).cloned(), value)
+        };
+        if let Some(handler) = handler {
+            let _ = handler.call1(&JsValue::NULL, &value);
+        }
+    }
+}
+
+// for callbacks, which have no one to return an error to: it's logged and
+// the viewer carries on
+fn report(result: Result<(), JsValue>) {
+    if let Err(error) = result {
+        web_sys::console::error_1(&error);
+    }
+}
+
+// the json form of a value (see src/json.rs) as a js object
+fn to_js<T: serde::Serialize>(value: &T) -> Result<JsValue, JsValue> {
+    let json =
+        serde_json::to_string(value).map_err(|error| JsValue::from_str(&error.to_string()))?;
+    js_sys::JSON::parse(&json)
+}
+
+// redraws at a new pixel ratio. a query only matches one ratio so each
+// change makes the next one, the view is held weakly to not keep it alive
+fn watch_ratio(view: &Rc<RefCell<View>>) -> Result<(), JsValue> {
+    let window = web_sys::window().unwrap();
+    let query = format!("(resolution: {}dppx)", window.device_pixel_ratio());
+    let query = match window.match_media(&query)? {
+        Some(query) => query,
+        None => return Ok(()),
+    };
+    let weak = Rc::downgrade(view);
+    let closure = Closure::wrap(Box::new(move |_: web_sys::Event| {
+        if let Some(view) = weak.upgrade() {
+            view.borrow_mut().resize();
+            report(view.borrow().draw());
+            report(watch_ratio(&view));
+        }
+    }) as Box<dyn FnMut(_)>);
+    query.add_event_listener_with_callback("change", closure.as_ref().unchecked_ref())?;
+
+    // the old query's closure may be the one running, it's dropped a
+    // change later
+    let watches = &mut view.borrow_mut().ratio_watch;
+    if let Some(old) = watches.last() {
+        let _ = old
+            .query
+            .remove_event_listener_with_callback("change", old.closure.as_ref().unchecked_ref());
+    }
+    if watches.len() > 1 {
+        watches.remove(0);
+    }
+    watches.push(MediaWatch { query, closure });
+    Ok(())
+}
+
+// an event handler, kept so it can be removed again
+struct Listener {
+    target: web_sys::EventTarget,
+    name: &'static str,
+    capture: bool,
+    closure: Closure<dyn FnMut(web_sys::Event)>,
+}
+
+// a schematic viewer on a canvas, a page can have any number of them. the
+// canvas keeps the size the page gives it, zoom and pan only change the
+// viewport
+#[wasm_bindgen]
+pub struct Viewer {
+    view: Rc<RefCell<View>>,
+    listeners: Vec<Listener>,
+    observer: Option<(web_sys::ResizeObserver, Closure<dyn FnMut()>)>,
+}
+
+impl Viewer {
+    // f gets the event and the view and says whether to redraw
+    fn listen<E: JsCast + 'static>(
+        &mut self,
+        target: &web_sys::EventTarget,
+        name: &'static str,
+        capture: bool,
+        mut f: impl FnMut(&E, &mut View) -> bool + 'static,
+    ) -> Result<(), JsValue> {
+        let view = self.view.clone();
+        let closure = Closure::wrap(Box::new(move |event: web_sys::Event| {
+            if f(event.unchecked_ref::<E>(), &mut view.borrow_mut()) {
+                report(view.borrow().draw());
+            }
+            dispatch(&view);
+        }) as Box<dyn FnMut(_)>);
+        target.add_event_listener_with_callback_and_bool(
+            name,
+            closure.as_ref().unchecked_ref(),
+            capture,
+        )?;
+        self.listeners.push(Listener {
+            target: target.clone(),
+            name,
+            capture,
+            closure,
+        });
+        Ok(())
+    }
+
+    // events an export queued go out once it has returned, a handler can't
+    // call back into a viewer that's still busy
+    fn dispatch_later(&self) {
+        let weak = Rc::downgrade(&self.view);
+        let later = Closure::once_into_js(move || {
+            if let Some(view) = weak.upgrade() {
+                dispatch(&view);
+            }
+        });
+        if let Some(window) = web_sys::window() {
+            window.queue_microtask(later.unchecked_ref());
+        }
+    }
+
+    fn no_schematic(&self) -> Result<(), JsValue> {
+        match self.view.borrow().loaded {
+            true => Ok(()),
+            false => Err(JsValue::from_str("no schematic loaded")),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Viewer {
+    #[wasm_bindgen(constructor)]
+    pub fn new(canvas: web_sys::HtmlCanvasElement) -> Result<Viewer, JsValue> {
+        // focusable, so keys go to the viewer that was clicked
+        if !canvas.has_attribute("tabindex") {
+            canvas.set_attribute("tabindex", "0")?;
+        }
+        let mut viewer = Viewer {
+            view: Rc::new(RefCell::new(View {
+                canvas: canvas.clone(),
+                hierarchy: Hierarchy::new(Schematic::blank(), ""),
+                sheet: 0,
+                histories: vec![History::new()],
+                schematic: Schematic::blank(),
+                loaded: false,
+                markers: Vec::new(),
+                netlist: Schematic::blank().netlist(),
+                theme: Theme::new(),
+                viewport: Viewport::new(),
+                pan: None,
+                touch: Touch::default(),
+                momentum: None,
+                flight: None,
+                frame: None,
+                frame_id: None,
+                history: History::new(),
+                selected: None,
+                hovered: None,
+                drag: None,
+                highlight: None,
+                found: Vec::new(),
+                hit: None,
+                highlighted: Vec::new(),
+                cursor: None,
+                keys: KEYS
+                    .iter()
+                    .map(|(key, action)| (key.to_string(), *action))
+                    .collect(),
+                handlers: HashMap::new(),
+                events: Vec::new(),
+                ratio: 1.0,
+                ratio_watch: Vec::new(),
+            })),
+            listeners: Vec::new(),
+            observer: None,
+        };
+
+        // the page sizes the canvas with css, without any its width and
+        // height attributes would and grow with the backing store
+        let style = canvas.style();
+        if style.get_property_value("width")?.is_empty() {
+            style.set_property("width", &format!("{}px", canvas.client_width()))?;
+        }
+        if style.get_property_value("height")?.is_empty() {
+            style.set_property("height", &format!("{}px", canvas.client_height()))?;
+        }
+        // the browser mustn't scroll or zoom the page under a gesture
+        style.set_property("touch-action", "none")?;
+        viewer.view.borrow_mut().resize();
+        watch_ratio(&viewer.view)?;
+
+        let weak = Rc::downgrade(&viewer.view);
+        let frame = Closure::wrap(Box::new(move |time: f64| {
+            if let Some(view) = weak.upgrade() {
+                let view = &mut *view.borrow_mut();
+                view.frame_id = None;
+                if view.step(time) {
+                    view.animate();
+                }
+                report(view.draw());
+            }
+        }) as Box<dyn FnMut(f64)>);
+        viewer.view.borrow_mut().frame = Some(frame);
+        let view = viewer.view.clone();
+        let closure = Closure::wrap(Box::new(move || {
+            view.borrow_mut().resize();
+            report(view.borrow().draw());
+        }) as Box<dyn FnMut()>);
+        let observer = web_sys::ResizeObserver::new(closure.as_ref().unchecked_ref())?;
+        observer.observe(&canvas);
+        viewer.observer = Some((observer, closure));
+
+        // zooms about the cursor, line and page deltas are made pixels
+        viewer.listen(
+            &canvas,
+            "wheel",
+            false,
+            |event: &web_sys::WheelEvent, view| {
+                event.prevent_default();
+                let delta = match event.delta_mode() {
+                    web_sys::WheelEvent::DOM_DELTA_LINE => event.delta_y() * 16.0,
+                    web_sys::WheelEvent::DOM_DELTA_PAGE => event.delta_y() * 400.0,
+                    _ => event.delta_y(),
+                };
+                let (x, y) = view.position(event);
+                view.land();
+                view.viewport.zoom_at((-delta * 0.0015).exp(), x, y);
+                true
+            },
+        )?;
+
+        // pointer events cover mouse, pen and touch, the canvas captures
+        // the pointer so a drag can leave it
+        viewer.listen(
+            &canvas,
+            "pointerdown",
+            false,
+            |event: &web_sys::PointerEvent, view| {
+                event.prevent_default();
+                view.pointer_down(event)
+            },
+        )?;
+        viewer.listen(
+            &canvas,
+            "pointermove",
+            false,
+            |event: &web_sys::PointerEvent, view| view.pointer_move(event),
+        )?;
+        viewer.listen(
+            &canvas,
+            "pointerup",
+            false,
+            |event: &web_sys::PointerEvent, view| view.pointer_up(event, false),
+        )?;
+        viewer.listen(
+            &canvas,
+            "pointercancel",
+            false,
+            |event: &web_sys::PointerEvent, view| view.pointer_up(event, true),
+        )?;
+
+        viewer.listen(
+            &canvas,
+            "pointerleave",
+            false,
+            |_: &web_sys::PointerEvent, view| {
+                view.hover(None);
+                false
+            },
+        )?;
+        viewer.listen(
+            &canvas,
+            "dblclick",
+            false,
+            |event: &web_sys::MouseEvent, view| {
+                let at = view.to_model(event);
+                view.enter(&at)
+            },
+        )?;
+
+        // bound keys don't reach the browser, so ctrl+f doesn't open its find
+        // and backspace doesn't go back
+        viewer.listen(
+            &canvas,
+            "keydown",
+            false,
+            |event: &web_sys::KeyboardEvent, view| match view.key(event) {
+                Some(changed) => {
+                    event.prevent_default();
+                    changed
+                }
+                None => false,
+            },
+        )?;
+
+        Ok(viewer)
+    }
+
+    // shows a new file, zoomed to fit, dropping the undo history
+    pub fn load(&mut self, text: &str) -> Result<(), JsValue> {
+        let schem =
+            Schematic::parse(text).map_err(|error| JsValue::from_str(&error.to_string()))?;
+        let hierarchy = Hierarchy::new(schem, "");
+        self.view.borrow_mut().show(hierarchy);
+        self.redraw()
+    }
+
+    // a design of several sheets, files maps file names (as sheet symbols
+    // name them, relative to the root's directory) to their text. sheets
+    // missing from it or that don't parse are left out
+    pub fn load_design(&mut self, root: &str, files: &js_sys::Object) -> Result<(), JsValue> {
+        let file = |name: &str| {
+            js_sys::Reflect::get(files, &JsValue::from_str(name))
+                .ok()?
+                .as_string()
+        };
+        let text = file(root).ok_or_else(|| JsValue::from_str(&format!("no file {:?}", root)))?;
+        let schem = Schematic::parse(&text)
+            .map_err(|error| JsValue::from_str(&format!("{}: {}", root, error)))?;
+        let hierarchy = Hierarchy::load(schem, root, &mut |name| file(name));
+        self.view.borrow_mut().show(hierarchy);
+        self.redraw()
+    }
+
+    // shows the sheet at a path, "/" or "/power/usb"
+    pub fn open_sheet(&mut self, path: &str) -> Result<(), JsValue> {
+        {
+            let view = &mut *self.view.borrow_mut();
+            let index = view
+                .hierarchy
+                .find(path)
+                .ok_or_else(|| JsValue::from_str(&format!("no sheet {:?}", path)))?;
+            view.open_sheet(index);
+        }
+        self.redraw()
+    }
+
+    // path of the shown sheet
+    pub fn sheet(&self) -> String {
+        let view = self.view.borrow();
+        view.hierarchy.sheets[view.sheet].path.clone()
+    }
+
+    // paths of every sheet in page order
+    pub fn sheets(&self) -> js_sys::Array {
+        self.view
+            .borrow()
+            .hierarchy
+            .sheets
+            .iter()
+            .map(|node| JsValue::from_str(&node.path))
+            .collect()
+    }
+
+    // binds a key ("Ctrl+F", "Home", "~") to an action by name (see
+    // `bindings()`), an empty action unbinds it
+    pub fn bind(&mut self, key: &str, action: &str) -> Result<(), JsValue> {
+        let key = parse_key(key).map_err(|error| JsValue::from_str(&error))?;
+        let keys = &mut self.view.borrow_mut().keys;
+        if action.is_empty() {
+            keys.remove(&key);
+            return Ok(());
+        }
+        let action = Action::from_name(action)
+            .ok_or_else(|| JsValue::from_str(&format!("unknown action {:?}", action)))?;
+        keys.insert(key, action);
+        Ok(())
+    }
+
+    // key -> action for every bound key
+    pub fn bindings(&self) -> Result<js_sys::Object, JsValue> {
+        let bindings = js_sys::Object::new();
+        for (key, action) in &self.view.borrow().keys {
+            js_sys::Reflect::set(
+                &bindings,
+                &JsValue::from_str(key),
+                &JsValue::from_str(action.name()),
+            )?;
+        }
+        Ok(bindings)
+    }
+
+    // calls handler on an event the user caused, none removes it:
+    //   "find"    the find key
+    //   "sheet"   went into or out of a sheet, with its path
+    //   "hover"   the mouse went onto a symbol, or off it with null
+    //   "select"  a click selected a symbol, or something else with null
+    // symbols come as `SymbolInfo` (see inspect.rs): reference, value,
+    // footprint, datasheet, lib_id, uuid, unit, fields [{name, value,
+    // shown}] and pins [{number, name, kind, net}]
+    pub fn on(&mut self, event: &str, handler: Option<js_sys::Function>) {
+        let handlers = &mut self.view.borrow_mut().handlers;
+        match handler {
+            Some(handler) => handlers.insert(event.to_string(), handler),
+            None => handlers.remove(event),
+        };
+    }
+
+    // highlights a net on every sheet by name, empty clears it
+    pub fn highlight_net(&mut self, name: &str) -> Result<(), JsValue> {
+        let name = Some(name.to_string()).filter(|name| !name.is_empty());
+        self.view.borrow_mut().highlight_net(name);
+        self.redraw()
+    }
+
+    pub fn highlighted_net(&self) -> Option<String> {
+        self.view.borrow().highlight.clone()
+    }
+
+    // the shown sheet, zoom, center and highlighted net and selected symbol
+    // as a url fragment without its "#" (see state.rs), for links and
+    // `history.replaceState`
+    pub fn get_state(&self) -> String {
+        self.view.borrow().state().to_string()
+    }
+
+    // goes back to a state `get_state` gave, or a link's fragment like
+    // "#sheet=/power&ref=U3&net=VBUS". "sheet" and "select" fire after it
+    // returns
+    pub fn set_state(&mut self, fragment: &str) -> Result<(), JsValue> {
+        self.no_schematic()?;
+        let state = fragment
+            .parse::<ViewState>()
+            .map_err(|error| JsValue::from_str(&error.to_string()))?;
+        self.view
+            .borrow_mut()
+            .set_state(&state)
+            .map_err(|error| JsValue::from_str(&error))?;
+        self.dispatch_later();
+        self.redraw()
+    }
+
+    // finds a query (wildcards * and ?) in references, values, fields,
+    // labels, net names and text on every sheet, options turn those off by
+    // name (see `SearchOptions`) or turn on case_sensitive. the hits are
+    // highlighted and returned best first, each {sheet, uuid, field, text,
+    // pos, bounds, score}, `next_hit()` shows the first
+    pub fn search(
+        &mut self,
+        query: &str,
+        options: Option<js_sys::Object>,
+    ) -> Result<js_sys::Array, JsValue> {
+        let mut search = SearchOptions::new();
+        if let Some(options) = options {
+            let flag = |name: &str, default: bool| {
+                js_sys::Reflect::get(&options, &JsValue::from_str(name))
+                    .ok()
+                    .and_then(|value| value.as_bool())
+                    .unwrap_or(default)
+            };
+            search = SearchOptions {
+                references: flag("references", search.references),
+                values: flag("values", search.values),
+                fields: flag("fields", search.fields),
+                labels: flag("labels", search.labels),
+                nets: flag("nets", search.nets),
+                text: flag("text", search.text),
+                case_sensitive: flag("case_sensitive", search.case_sensitive),
+            };
+        }
+        let hits = js_sys::Array::new();
+        {
+            let view = &mut *self.view.borrow_mut();
+            view.search(query, &search);
+            for (sheet, hit) in &view.found {
+                let mut value = serde_json::to_value(hit)
+                    .map_err(|error| JsValue::from_str(&error.to_string()))?;
+                let path = view.hierarchy.sheets[*sheet].path.clone();
+                value["sheet"] = serde_json::Value::String(path);
+                hits.push(&to_js(&value)?);
+            }
+        }
+        self.redraw()?;
+        Ok(hits)
+    }
+
+    // steps through the hits, zooming to each and opening its sheet. the
+    // index of the hit shown, none without hits
+    pub fn next_hit(&mut self) -> Result<Option<u32>, JsValue> {
+        self.view.borrow_mut().step_hit(1);
+        self.redraw()?;
+        Ok(self.view.borrow().hit.map(|hit| hit as u32))
+    }
+
+    pub fn previous_hit(&mut self) -> Result<Option<u32>, JsValue> {
+        self.view.borrow_mut().step_hit(-1);
+        self.redraw()?;
+        Ok(self.view.borrow().hit.map(|hit| hit as u32))
+    }
+
+    pub fn clear_search(&mut self) -> Result<(), JsValue> {
+        {
+            let view = &mut *self.view.borrow_mut();
+            view.found.clear();
+            view.hit = None;
+            view.rehighlight();
+        }
+        self.redraw()
+    }
+
+    // a symbol of the shown sheet by uuid, like the "select" event has it
+    pub fn inspect(&self, uuid: &str) -> Result<JsValue, JsValue> {
+        let uuid = uuid
+            .parse::<Uuid>()
+            .map_err(|_| JsValue::from_str(&format!("bad uuid {:?}", uuid)))?;
+        match self.view.borrow().symbol_info(Some(uuid))? {
+            info if info.is_null() => Err(JsValue::from_str(&format!("no symbol {}", uuid))),
+            info => Ok(info),
+        }
+    }
+
+    // px per mm, kept within `Viewport`'s limits
+    pub fn set_zoom(&mut self, zoom: f64) -> Result<(), JsValue> {
+        let view = &mut *self.view.borrow_mut();
+        view.land();
+        view.viewport.set_scale(zoom);
+        self.redraw()
+    }
+
+    pub fn zoom(&self) -> f64 {
+        self.view.borrow().viewport.scale
+    }
+
+    // centers the view on a point of the sheet, in mm
+    pub fn pan_to(&mut self, x: f64, y: f64) -> Result<(), JsValue> {
+        let view = &mut *self.view.borrow_mut();
+        view.land();
+        view.pan_to(x, y);
+        self.redraw()
+    }
+
+    // zooms to everything drawn on the sheet, the frame left out
+    pub fn zoom_to_fit(&mut self) -> Result<(), JsValue> {
+        {
+            let view = &mut *self.view.borrow_mut();
+            view.fly_to(view.content_view());
+        }
+        self.redraw()
+    }
+
+    // zooms out to the whole page frame
+    pub fn zoom_to_page(&mut self) -> Result<(), JsValue> {
+        {
+            let view = &mut *self.view.borrow_mut();
+            view.fly_to(view.page_view());
+        }
+        self.redraw()
+    }
+
+    // zooms to an item by uuid, opening the sheet it's on
+    pub fn zoom_to_item(&mut self, uuid: &str) -> Result<(), JsValue> {
+        let uuid = uuid
+            .parse::<Uuid>()
+            .map_err(|_| JsValue::from_str(&format!("bad uuid {:?}", uuid)))?;
+        let found = self.view.borrow_mut().zoom_to(|schem| {
+            schem
+                .find_by_uuid(&uuid)
+                .and_then(|item| schem.item_bounds(item))
+        });
+        match found {
+            true => self.redraw(),
+            false => Err(JsValue::from_str(&format!("no item {}", uuid))),
+        }
+    }
+
+    // zooms to a symbol by reference ("U3"), all of its units, opening the
+    // sheet it's on
+    pub fn zoom_to_reference(&mut self, reference: &str) -> Result<(), JsValue> {
+        let found = self
+            .view
+            .borrow_mut()
+            .zoom_to(|schem| schem.symbol_bounds(reference));
+        match found {
+            true => self.redraw(),
+            false => Err(JsValue::from_str(&format!("no symbol {:?}", reference))),
+        }
+    }
+
+    pub fn redraw(&self) -> Result<(), JsValue> {
+        self.view.borrow().draw()
+    }
+
+    // removes the viewer's event handlers, it can't be used afterwards
+    pub fn destroy(&mut self) {
+        if let Some((observer, _)) = self.observer.take() {
+            observer.disconnect();
+        }
+        if let Some(id) = self.view.borrow_mut().frame_id.take() {
+            let _ = web_sys::window().unwrap().cancel_animation_frame(id);
+        }
+        for watch in self.view.borrow_mut().ratio_watch.drain(..) {
+            let _ = watch.query.remove_event_listener_with_callback(
+                "change",
+                watch.closure.as_ref().unchecked_ref(),
+            );
+        }
+        for listener in self.listeners.drain(..) {
+            let _ = listener
+                .target
+                .remove_event_listener_with_callback_and_bool(
+                    listener.name,
+                    listener.closure.as_ref().unchecked_ref(),
+                    listener.capture,
+                );
+        }
+    }
+
+    // switches to a built-in theme (see `themes()`) and redraws
+    pub fn set_theme(&mut self, name: &str) -> Result<(), JsValue> {
+        let theme = Theme::builtin(name)
+            .ok_or_else(|| JsValue::from_str(&format!("unknown theme {:?}", name)))?;
+        self.view.borrow_mut().theme = theme;
+        self.redraw()
+    }
+
+    // undoes the last edit, false when there's nothing to undo
+    pub fn undo(&mut self) -> Result<bool, JsValue> {
+        let done = self.view.borrow_mut().undo();
+        self.redraw()?;
+        Ok(done)
+    }
+
+    pub fn redo(&mut self) -> Result<bool, JsValue> {
+        let done = self.view.borrow_mut().redo();
+        self.redraw()?;
+        Ok(done)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.view.borrow().history.can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.view.borrow().history.can_redo()
+    }
+
+    // svg of the schematic in the current theme, for downloading
+    pub fn to_svg(&self) -> Result<String, JsValue> {
+        self.no_schematic()?;
+        let view = self.view.borrow();
+        Ok(view.schematic.to_svg(&view.theme, &svg::SvgOptions::new()))
+    }
+
+    // the model, see json.rs for its shape
+    pub fn to_json(&self) -> Result<String, JsValue> {
+        self.no_schematic()?;
+        Ok(self.view.borrow().schematic.to_json())
+    }
+
+    // the edited schematic as a .kicad_sch file
+    pub fn to_kicad_sch(&self) -> Result<String, JsValue> {
+        self.no_schematic()?;
+        Ok(self.view.borrow().schematic.to_kicad_sch())
+    }
+}
+
+impl Drop for Viewer {
+    fn drop(&mut self) {
+        self.destroy();
+    }
+}
+
+#[wasm_bindgen]
+pub fn themes() -> js_sys::Array {
+    Theme::builtin_names()
+        .iter()
+        .map(|name| JsValue::from_str(name))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_names() {
+        assert_eq!(key_name(true, false, false, "f"), "Ctrl+F");
+        assert_eq!(key_name(true, true, true, "z"), "Ctrl+Alt+Shift+Z");
+        assert_eq!(key_name(false, false, true, "Home"), "Shift+Home");
+        // shift is already in the character
+        assert_eq!(key_name(false, false, true, "~"), "~");
+        assert_eq!(key_name(true, false, true, "+"), "Ctrl++");
+    }
+
+    #[test]
+    fn parse_keys() {
+        assert_eq!(parse_key("ctrl+f"), Ok("Ctrl+F".to_string()));
+        assert_eq!(parse_key("Cmd+Shift+z"), Ok("Ctrl+Shift+Z".to_string()));
+        assert_eq!(
+            parse_key("control+option+Delete"),
+            Ok("Ctrl+Alt+Delete".to_string())
+        );
+        assert_eq!(parse_key("~"), Ok("~".to_string()));
+        assert_eq!(parse_key("shift+~"), Ok("~".to_string()));
+        assert_eq!(parse_key("+"), Ok("+".to_string()));
+        assert_eq!(parse_key("Ctrl++"), Ok("Ctrl++".to_string()));
+        assert_eq!(parse_key("Home"), Ok("Home".to_string()));
+
+        assert_eq!(parse_key(""), Err("empty key".to_string()));
+        assert_eq!(parse_key("ctrl+"), Err("empty key".to_string()));
+        assert_eq!(
+            parse_key("hyper+f"),
+            Err("unknown modifier \"hyper\" in \"hyper+f\"".to_string())
+        );
+        assert!(parse_key("ctrl+x+f").is_err());
+        // bindings name keys the way `key_name` does
+        for (name, _) in ACTIONS.iter() {
+            assert_eq!(parse_key(name).as_deref(), Ok(*name));
+        }
+    }
+
+    #[test]
+    fn smallest_symbol_is_picked() {
+        let mut schem = Schematic::from_str(include_str!("../test/kicad7.kicad_sch"));
+        let c1 = schem.symbs[1].uuid;
+        let at = schem.symbs[1].pos.clone();
+        let area = |schem: &Schematic, i: usize| {
+            let (min, max) = schem.item_bounds(ItemRef::Symbol(i)).unwrap();
+            (max.x - min.x) * (max.y - min.y)
+        };
+
+        // a resistor on C1, its fields closer in than C1's
+        let r = schem
+            .place_symbol("Device:R", at.clone(), 0.0, (false, false))
+            .unwrap();
+        assert!(area(&schem, 3) < area(&schem, 1));
+        let hits = schem.hit_test(at.x, at.y, 0.5);
+        assert!(hits.contains(&ItemRef::Symbol(1)) && hits.contains(&ItemRef::Symbol(3)));
+        assert_eq!(pick(&schem, &at), Some(r));
+
+        // a label sits on top of both
+        let label = schem.add_label("X", Style::Local, at.clone());
+        assert_eq!(pick(&schem, &at), Some(label));
+        schem.delete_item(&label).unwrap();
+
+        schem.delete_item(&r).unwrap();
+        assert_eq!(pick(&schem, &at), Some(c1));
+        assert_eq!(pick(&schem, &Point::new(0.0, 0.0)), None);
+    }
+}
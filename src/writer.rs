@@ -0,0 +1,1118 @@
+use crate::schematic::*;
+use crate::sexpr::{self, number, quote, Document, Node};
+use crate::theme::Color;
+use std::collections::HashSet;
+
+// file format versions of kicad releases
+pub const KICAD_6: i32 = 20211123;
+pub const KICAD_7: i32 = 20230121;
+pub const KICAD_8: i32 = 20231120;
+
+// heads of the top level items the model knows, in kicad's file order
+const ITEMS: [&str; 10] = [
+    "junction",
+    "no_connect",
+    "wire",
+    "polyline",
+    "text",
+    "label",
+    "global_label",
+    "hierarchical_label",
+    "symbol",
+    "sheet",
+];
+
+// what the file ends with, new items go before these
+const TRAILERS: [&str; 3] = ["sheet_instances", "symbol_instances", "embedded_fonts"];
+
+// how a file spells things, which changed between kicad versions
+struct Dialect {
+    version: i32,
+}
+
+impl Dialect {
+    // ids of new items, parsed ones keep the file's spelling
+    fn uuid(&self, uuid: &Uuid) -> String {
+        match self.version >= KICAD_8 {
+            true => quote(&uuid.to_string()),
+            false => uuid.to_string(),
+        }
+    }
+
+    // a set flag, ` hide` before kicad 8 and ` (hide yes)` since
+    fn flag(&self, name: &str) -> String {
+        match self.version >= KICAD_8 {
+            true => format!(" ({} yes)", name),
+            false => format!(" {}", name),
+        }
+    }
+
+    // a node written in kicad 6's layout (two space indents, relative to the
+    // node), laid out for the file at `depth`
+    fn node(&self, text: &str, depth: usize) -> Node {
+        let node = Node::parse(text).unwrap();
+        if self.version >= KICAD_8 {
+            return sexpr::prettify(&node, depth);
+        }
+        let indent = "  ".repeat(depth);
+        let mut node = Node::parse(&text.replace('\n', &format!("\n{}", indent))).unwrap();
+        node.set_space(&format!("\n{}", indent));
+        node
+    }
+}
+
+fn indent(text: &str) -> String {
+    text.replace('\n', "\n  ")
+}
+
+fn yes(value: bool) -> &'static str {
+    match value {
+        true => "yes",
+        false => "no",
+    }
+}
+
+fn at(pos: &Point) -> String {
+    format!("(at {} {} {})", number(pos.x), number(pos.y), number(pos.a))
+}
+
+fn xy(head: &str, pos: &Point) -> String {
+    format!("({} {} {})", head, number(pos.x), number(pos.y))
+}
+
+fn color(color: &Option<Color>) -> String {
+    match color {
+        Some(color) => format!(
+            "(color {} {} {} {})",
+            color.r,
+            color.g,
+            color.b,
+            number(color.a)
+        ),
+        None => "(color 0 0 0 0)".to_string(),
+    }
+}
+
+fn stroke(stroke: &Stroke) -> String {
+    let format = match stroke.format {
+        StrokeFormat::Dash => "dash",
+        StrokeFormat::DashDot => "dash_dot",
+        StrokeFormat::DashDotDot => "dash_dot_dot",
+        StrokeFormat::Dot => "dot",
+        StrokeFormat::Default => "default",
+        StrokeFormat::Solid => "solid",
+    };
+    format!(
+        "(stroke (width {}) (type {}) {})",
+        number(stroke.width),
+        format,
+        color(&stroke.color)
+    )
+}
+
+fn fill(fill: &FillType) -> String {
+    let fill = match fill {
+        FillType::None => "none",
+        FillType::Outline => "outline",
+        FillType::Background => "background",
+    };
+    format!("(fill (type {}))", fill)
+}
+
+fn effects(effect: &Effect, hide: bool, d: &Dialect) -> String {
+    // unset sizes are kicad's default text size
+    let size = match effect.size.0 > 0.0 {
+        true => effect.size,
+        false => (1.27, 1.27),
+    };
+    let mut out = format!(
+        "(effects (font (size {} {})",
+        number(size.0),
+        number(size.1)
+    );
+    if effect.thickness > 0.0 {
+        out.push_str(&format!(" (thickness {})", number(effect.thickness)));
+    }
+    if effect.bold {
+        out.push_str(&d.flag("bold"));
+    }
+    if effect.italic {
+        out.push_str(&d.flag("italic"));
+    }
+    if effect.color.is_some() {
+        out.push(' ');
+        out.push_str(&color(&effect.color));
+    }
+    out.push(')');
+    if hide || effect.hide {
+        out.push_str(&d.flag("hide"));
+    }
+    out.push(')');
+    out
+}
+
+fn property(prop: &Property, d: &Dialect) -> String {
+    let id = match d.version < KICAD_8 {
+        true => format!(" (id {})", prop.id),
+        false => String::new(),
+    };
+    format!(
+        "(property {} {}{} {}\n  {}\n)",
+        quote(&prop.key),
+        quote(&prop.value),
+        id,
+        at(&prop.pos),
+        effects(&prop.effect, !prop.show, d)
+    )
+}
+
+// kicad has no "mirrored both ways", that's a half turn
+fn mirror(symb: &SymbolInst) -> (Option<&'static str>, Point) {
+    let mut pos = symb.pos.clone();
+    let mirror = match symb.mirror {
+        (false, false) => None,
+        (false, true) => Some("x"),
+        (true, false) => Some("y"),
+        (true, true) => {
+            pos.a = (pos.a + 180.0) % 360.0;
+            None
+        }
+    };
+    (mirror, pos)
+}
+
+fn label_head(shape: &Style) -> &'static str {
+    match shape {
+        Style::Heir => "hierarchical_label",
+        Style::Global => "global_label",
+        Style::Local => "label",
+        Style::Noconn => "no_connect",
+    }
+}
+
+fn pin_type(kind: PinType) -> &'static str {
+    match kind {
+        PinType::Input => "input",
+        PinType::Output => "output",
+        PinType::Bidirectional => "bidirectional",
+        PinType::TriState => "tri_state",
+        PinType::Passive => "passive",
+        PinType::Free => "free",
+        PinType::Unspecified => "unspecified",
+        PinType::PowerIn => "power_in",
+        PinType::PowerOut => "power_out",
+        PinType::OpenCollector => "open_collector",
+        PinType::OpenEmitter => "open_emitter",
+        PinType::NoConnect => "no_connect",
+    }
+}
+
+fn pin_style(style: PinStyle) -> &'static str {
+    match style {
+        PinStyle::Line => "line",
+        PinStyle::Inverted => "inverted",
+        PinStyle::Clock => "clock",
+        PinStyle::InvertedClock => "inverted_clock",
+        PinStyle::InputLow => "input_low",
+        PinStyle::ClockLow => "clock_low",
+        PinStyle::OutputLow => "output_low",
+        PinStyle::EdgeClockHigh => "edge_clock_high",
+        PinStyle::NonLogic => "non_logic",
+    }
+}
+
+// a lib_symbols entry
+fn template(temp: &SymbolTemp, d: &Dialect) -> String {
+    let mut out = format!("(symbol {}", quote(&temp.id));
+    if temp.power {
+        out.push_str(" (power)");
+    }
+    out.push_str(" (in_bom yes) (on_board yes)");
+    for prop in &temp.props {
+        out.push_str(&format!("\n  {}", indent(&property(prop, d))));
+    }
+    for symb in &temp.symbs {
+        let mut unit = format!("(symbol {}", quote(&symb.id));
+        for line in &symb.lines {
+            let pts = line
+                .poss
+                .iter()
+                .map(|pos| format!("\n    {}", xy("xy", pos)))
+                .collect::<String>();
+            unit.push_str(&indent(&format!(
+                "\n(polyline\n  (pts{}\n  )\n  {}\n  {}\n)",
+                pts,
+                stroke(&line.stroke),
+                fill(&line.fill)
+            )));
+        }
+        for rect in &symb.rects {
+            unit.push_str(&indent(&format!(
+                "\n(rectangle {} {}\n  {}\n  {}\n)",
+                xy("start", &rect.poss.0),
+                xy("end", &rect.poss.1),
+                stroke(&rect.stroke),
+                fill(&rect.fill)
+            )));
+        }
+        for circ in &symb.circs {
+            unit.push_str(&indent(&format!(
+                "\n(circle {} (radius {})\n  {}\n  {}\n)",
+                xy("center", &circ.pos),
+                number(circ.radius),
+                stroke(&circ.stroke),
+                fill(&circ.fill)
+            )));
+        }
+        for arc in &symb.arcs {
+            unit.push_str(&indent(&format!(
+                "\n(arc {} {} {}\n  {}\n  {}\n)",
+                xy("start", &arc.poss.0),
+                xy("mid", &arc.poss.1),
+                xy("end", &arc.poss.2),
+                stroke(&arc.stroke),
+                fill(&arc.fill)
+            )));
+        }
+        for pin in &symb.pins {
+            let hide = match pin.hide {
+                true => d.flag("hide"),
+                false => String::new(),
+            };
+            unit.push_str(&indent(&format!(
+                "\n(pin {} {} {} (length {}){}\n  (name {} {})\n  (number {} {})\n)",
+                pin_type(pin.kind),
+                pin_style(pin.style),
+                at(&pin.pos),
+                number(pin.len),
+                hide,
+                quote(&pin.name.0),
+                effects(&pin.name.1, false, d),
+                quote(&pin.numb.0),
+                effects(&pin.numb.1, false, d)
+            )));
+        }
+        unit.push_str("\n)");
+        out.push_str(&format!("\n  {}", indent(&unit)));
+    }
+    out.push_str("\n)");
+    out
+}
+
+// where a new symbol's per-symbol instance data points (kicad 7 on)
+struct Instance {
+    project: String,
+    path: String,
+}
+
+struct Writer<'a> {
+    schem: &'a Schematic,
+    d: Dialect,
+    instance: Option<Instance>, // none when the file has symbol_instances
+    written: HashSet<Uuid>,
+    removed: HashSet<Uuid>, // symbols in the file but not the model
+}
+
+impl<'a> Writer<'a> {
+    fn symbol(&self, symb: &SymbolInst) -> String {
+        let d = &self.d;
+        let (mirror, pos) = mirror(symb);
+        let mirror = match mirror {
+            Some(axis) => format!(" (mirror {})", axis),
+            None => String::new(),
+        };
+        let mut out = format!(
+            "(symbol (lib_id {}) {}{} (unit {})\n  (in_bom {}) (on_board yes)\n  (uuid {})",
+            quote(&symb.id),
+            at(&pos),
+            mirror,
+            symb.unit,
+            yes(symb.in_bom),
+            d.uuid(&symb.uuid)
+        );
+        for prop in &symb.props {
+            out.push_str(&format!("\n  {}", indent(&property(prop, d))));
+        }
+        if let Some(instance) = &self.instance {
+            out.push_str(&format!(
+                "\n  (instances\n    (project {}\n      (path {}\n        (reference {}) (unit {})\n      )\n    )\n  )",
+                quote(&instance.project),
+                quote(&instance.path),
+                quote(symb.prop("Reference").unwrap_or("")),
+                symb.unit
+            ));
+        }
+        out.push_str("\n)");
+        out
+    }
+
+    fn sheet(&self, sheet: &Sheet) -> String {
+        let d = &self.d;
+        let mut out = format!(
+            "(sheet {} (size {} {})\n  {}\n  (fill {})\n  (uuid {})",
+            xy("at", &sheet.pos),
+            number(sheet.size.0),
+            number(sheet.size.1),
+            stroke(&sheet.stroke),
+            color(&sheet.fill),
+            d.uuid(&sheet.uuid)
+        );
+        for prop in &sheet.props {
+            out.push_str(&format!("\n  {}", indent(&property(prop, d))));
+        }
+        for pin in &sheet.pins {
+            out.push_str(&format!("\n  {}", indent(&self.sheet_pin(pin))));
+        }
+        out.push_str("\n)");
+        out
+    }
+
+    // the shape isn't part of the model, pins are written as inputs
+    fn sheet_pin(&self, pin: &SheetPin) -> String {
+        format!(
+            "(pin {} input {}\n  {}\n  (uuid {})\n)",
+            quote(&pin.name),
+            at(&pin.pos),
+            effects(&pin.effect, false, &self.d),
+            self.d.uuid(&pin.uuid)
+        )
+    }
+
+    // a new top level node for an item
+    fn item(&self, item: ItemRef) -> Node {
+        let schem = self.schem;
+        let d = &self.d;
+        let text = match item {
+            ItemRef::Wire(i) | ItemRef::Polyline(i) => {
+                let (head, line) = match item {
+                    ItemRef::Wire(_) => (
+                        "wire",
+                        (
+                            &schem.wires[i].poss,
+                            &schem.wires[i].stroke,
+                            &schem.wires[i].uuid,
+                        ),
+                    ),
+                    _ => (
+                        "polyline",
+                        (
+                            &schem.polys[i].poss,
+                            &schem.polys[i].stroke,
+                            &schem.polys[i].uuid,
+                        ),
+                    ),
+                };
+                let pts = line.0.iter().map(|pos| xy("xy", pos)).collect::<Vec<_>>();
+                format!(
+                    "({} (pts {})\n  {}\n  (uuid {})\n)",
+                    head,
+                    pts.join(" "),
+                    stroke(line.1),
+                    d.uuid(line.2)
+                )
+            }
+            ItemRef::Junction(i) => {
+                let junc = &schem.juncs[i];
+                format!(
+                    "(junction {} (diameter {}) {}\n  (uuid {})\n)",
+                    xy("at", &junc.pos),
+                    number(junc.diameter),
+                    color(&junc.color),
+                    d.uuid(&junc.uuid)
+                )
+            }
+            ItemRef::Text(i) => {
+                let text = &schem.texts[i];
+                format!(
+                    "(text {} {}\n  {}\n  (uuid {})\n)",
+                    quote(&text.text),
+                    at(&text.pos),
+                    effects(&text.effect, false, d),
+                    d.uuid(&text.uuid)
+                )
+            }
+            ItemRef::Label(i) => {
+                let label = &schem.labels[i];
+                let shape = match label.shape {
+                    Style::Global | Style::Heir => " (shape input)",
+                    _ => "",
+                };
+                match label.shape {
+                    Style::Noconn => format!(
+                        "(no_connect {} (uuid {}))",
+                        xy("at", &label.pos),
+                        d.uuid(&label.uuid)
+                    ),
+                    _ => format!(
+                        "({} {}{} {}\n  {}\n  (uuid {})\n)",
+                        label_head(&label.shape),
+                        quote(&label.id),
+                        shape,
+                        at(&label.pos),
+                        effects(&label.effect, false, d),
+                        d.uuid(&label.uuid)
+                    ),
+                }
+            }
+            ItemRef::Symbol(i) => self.symbol(&schem.symbs[i]),
+            ItemRef::Sheet(i) => self.sheet(&schem.sheets[i]),
+        };
+        d.node(&text, 1)
+    }
+
+    // updates a parsed item from the model in place, false when the node
+    // can't be updated (it changed kind) and has to be rewritten
+    fn sync(&self, node: &mut Node, item: ItemRef) -> bool {
+        let schem = self.schem;
+        match (item, node.head().unwrap_or("")) {
+            (ItemRef::Wire(i), "wire") => self.sync_points(node, &schem.wires[i].poss),
+            (ItemRef::Polyline(i), "polyline") => self.sync_points(node, &schem.polys[i].poss),
+            (ItemRef::Junction(i), "junction") => {
+                let junc = &schem.juncs[i];
+                set_point(node, "at", &junc.pos, false);
+                set_number(node, "diameter", junc.diameter);
+            }
+            (ItemRef::Text(i), "text") => {
+                let text = &schem.texts[i];
+                node.items_mut()[1].set_text(&text.text);
+                set_point(node, "at", &text.pos, true);
+            }
+            (ItemRef::Label(i), head) if head == label_head(&schem.labels[i].shape) => {
+                let label = &schem.labels[i];
+                if let Style::Noconn = label.shape {
+                    set_point(node, "at", &label.pos, false);
+                } else {
+                    node.items_mut()[1].set_text(&label.id);
+                    set_point(node, "at", &label.pos, true);
+                }
+            }
+            (ItemRef::Symbol(i), "symbol") => self.sync_symbol(node, &schem.symbs[i]),
+            (ItemRef::Sheet(i), "sheet") => self.sync_sheet(node, &schem.sheets[i]),
+            _ => return false,
+        }
+        true
+    }
+
+    fn sync_points(&self, node: &mut Node, poss: &[Point]) {
+        let pts = match node.child_mut("pts") {
+            Some(pts) => pts,
+            None => return,
+        };
+        let count = pts.items().iter().filter(|item| item.is_list()).count();
+        if count == poss.len() {
+            let xys = pts.items_mut().iter_mut().filter(|item| item.is_list());
+            for (xy, pos) in xys.zip(poss) {
+                xy.items_mut()[1].set_f64(pos.x);
+                xy.items_mut()[2].set_f64(pos.y);
+            }
+            return;
+        }
+        let xys = poss.iter().map(|pos| xy("xy", pos)).collect::<Vec<_>>();
+        let space = pts.space().to_string();
+        *pts = self.d.node(&format!("(pts {})", xys.join(" ")), 2);
+        pts.set_space(&space);
+    }
+
+    fn sync_symbol(&self, node: &mut Node, symb: &SymbolInst) {
+        let (axis, pos) = mirror(symb);
+        set_value(node, "lib_id", &symb.id);
+        set_point(node, "at", &pos, true);
+        match (node.child_mut("mirror"), axis) {
+            (Some(mirror), Some(axis)) => mirror.items_mut()[1].set_text(axis),
+            (Some(_), None) => node
+                .items_mut()
+                .retain(|item| item.head() != Some("mirror")),
+            (None, Some(axis)) => {
+                let index = node
+                    .items()
+                    .iter()
+                    .position(|item| item.head() == Some("at"))
+                    .map(|index| index + 1)
+                    .unwrap_or(1);
+                let mut mirror = Node::parse(&format!("(mirror {})", axis)).unwrap();
+                mirror.set_space(" ");
+                node.items_mut().insert(index, mirror);
+            }
+            (None, None) => {}
+        }
+        set_number(node, "unit", symb.unit as f64);
+        set_value(node, "in_bom", yes(symb.in_bom));
+
+        // kicad 7's instances are matched on the reference they had
+        let reference = symb.prop("Reference").unwrap_or("");
+        let old = node
+            .items()
+            .iter()
+            .find(|item| {
+                item.head() == Some("property")
+                    && item.items()[1].text().as_deref() == Some("Reference")
+            })
+            .and_then(|item| item.items()[2].text());
+        if let Some(instances) = node.child_mut("instances") {
+            for project in instances
+                .items_mut()
+                .iter_mut()
+                .filter(|item| item.head() == Some("project"))
+            {
+                let paths = project
+                    .items()
+                    .iter()
+                    .filter(|item| item.head() == Some("path"))
+                    .count();
+                for path in project
+                    .items_mut()
+                    .iter_mut()
+                    .filter(|item| item.head() == Some("path"))
+                {
+                    if paths == 1 || path.value("reference") == old {
+                        set_value(path, "reference", reference);
+                        set_number(path, "unit", symb.unit as f64);
+                    }
+                }
+            }
+        }
+        self.sync_props(node, &symb.props);
+    }
+
+    fn sync_sheet(&self, node: &mut Node, sheet: &Sheet) {
+        set_point(node, "at", &sheet.pos, false);
+        if let Some(size) = node.child_mut("size") {
+            size.items_mut()[1].set_f64(sheet.size.0);
+            size.items_mut()[2].set_f64(sheet.size.1);
+        }
+        self.sync_props(node, &sheet.props);
+
+        // pins by uuid
+        let uuid = |item: &Node| match item.head() {
+            Some("pin") => item.value("uuid").map(|uuid| Uuid::from_text(&uuid)),
+            _ => None,
+        };
+        let items = node.items_mut();
+        items.retain(|item| {
+            item.head() != Some("pin") || sheet.pins.iter().any(|pin| uuid(item) == Some(pin.uuid))
+        });
+        for pin in &sheet.pins {
+            match items.iter_mut().find(|item| uuid(item) == Some(pin.uuid)) {
+                Some(item) => {
+                    item.items_mut()[1].set_text(&pin.name);
+                    set_point(item, "at", &pin.pos, true);
+                }
+                None => {
+                    let index = insert_index(items, "pin", &["instances"]);
+                    items.insert(index, self.d.node(&self.sheet_pin(pin), 2));
+                }
+            }
+        }
+    }
+
+    // properties by key, values and positions updated, missing ones added
+    // and removed ones dropped
+    fn sync_props(&self, node: &mut Node, props: &[Property]) {
+        let key = |item: &Node| match item.head() {
+            Some("property") => item.items()[1].text(),
+            _ => None,
+        };
+        let items = node.items_mut();
+        items.retain(|item| match key(item) {
+            Some(key) => props.iter().any(|prop| prop.key == key),
+            None => true,
+        });
+        for prop in props {
+            match items
+                .iter_mut()
+                .find(|item| key(item).as_deref() == Some(prop.key.as_str()))
+            {
+                Some(item) => {
+                    item.items_mut()[2].set_text(&prop.value);
+                    set_point(item, "at", &prop.pos, true);
+                }
+                None => {
+                    let index = insert_index(items, "property", &["pin", "instances"]);
+                    items.insert(index, self.d.node(&property(prop, &self.d), 2));
+                }
+            }
+        }
+    }
+
+    fn header(&self, root: &mut Node) {
+        let schem = self.schem;
+        if schem.version > 0 {
+            set_number(root, "version", schem.version as f64);
+        }
+        // compared as uuids, an id keeps the file's spelling
+        if let Some(uuid) = root.child_mut("uuid") {
+            let id = &mut uuid.items_mut()[1];
+            if id.text().map(|id| Uuid::from_text(&id)) != Some(schem.uuid) {
+                id.set_text(&schem.uuid.to_string());
+            }
+        }
+        let (name, size) = match &schem.page {
+            Page::User(width, height) => ("User", Some((*width, *height))),
+            page => (page_name(page), None),
+        };
+        let same = match root.child("paper") {
+            Some(paper) => {
+                let items = paper.items();
+                let number = |index: usize| items.get(index).and_then(|item| item.to_f64());
+                items.get(1).and_then(|item| item.text()).as_deref() == Some(name)
+                    && size
+                        .is_none_or(|size| number(2) == Some(size.0) && number(3) == Some(size.1))
+                    && items.iter().any(|item| item.raw() == Some("portrait")) == schem.portrait
+            }
+            None => return,
+        };
+        if !same {
+            let mut paper = format!("(paper {}", quote(name));
+            if let Some(size) = size {
+                paper.push_str(&format!(" {} {}", number(size.0), number(size.1)));
+            }
+            if schem.portrait {
+                paper.push_str(" portrait");
+            }
+            paper.push(')');
+            let node = root.child_mut("paper").unwrap();
+            let space = node.space().to_string();
+            *node = self.d.node(&paper, 1);
+            node.set_space(&space);
+        }
+    }
+
+    fn items(&mut self, root: &mut Node) {
+        let items = std::mem::take(root.items_mut());
+        let mut out = Vec::<Node>::with_capacity(items.len());
+        for mut node in items {
+            let head = node.head().unwrap_or("").to_string();
+            let uuid = node.value("uuid").map(|uuid| Uuid::from_text(&uuid));
+            let uuid = match uuid {
+                Some(uuid) if ITEMS.contains(&head.as_str()) => uuid,
+                _ => {
+                    out.push(node);
+                    continue;
+                }
+            };
+            match self.schem.find_by_uuid(&uuid) {
+                Some(item) => {
+                    self.written.insert(uuid);
+                    if !self.sync(&mut node, item) {
+                        let space = node.space().to_string();
+                        let id = node.child("uuid").and_then(|uuid| uuid.items().get(1));
+                        let id = id.cloned();
+                        node = self.item(item);
+                        node.set_space(&space);
+                        if let (Some(id), Some(uuid)) = (id, node.child_mut("uuid")) {
+                            uuid.items_mut()[1] = id;
+                        }
+                    }
+                    out.push(node);
+                }
+                None => {
+                    if head == "symbol" {
+                        self.removed.insert(uuid);
+                    }
+                }
+            }
+        }
+
+        // then everything the file didn't have
+        let schem = self.schem;
+        let mut new = Vec::<(ItemRef, Uuid)>::new();
+        new.extend(
+            schem
+                .juncs
+                .iter()
+                .enumerate()
+                .map(|(i, item)| (ItemRef::Junction(i), item.uuid)),
+        );
+        new.extend(
+            schem
+                .wires
+                .iter()
+                .enumerate()
+                .map(|(i, item)| (ItemRef::Wire(i), item.uuid)),
+        );
+        new.extend(
+            schem
+                .polys
+                .iter()
+                .enumerate()
+                .map(|(i, item)| (ItemRef::Polyline(i), item.uuid)),
+        );
+        new.extend(
+            schem
+                .texts
+                .iter()
+                .enumerate()
+                .map(|(i, item)| (ItemRef::Text(i), item.uuid)),
+        );
+        new.extend(
+            schem
+                .labels
+                .iter()
+                .enumerate()
+                .map(|(i, item)| (ItemRef::Label(i), item.uuid)),
+        );
+        new.extend(
+            schem
+                .symbs
+                .iter()
+                .enumerate()
+                .map(|(i, item)| (ItemRef::Symbol(i), item.uuid)),
+        );
+        new.extend(
+            schem
+                .sheets
+                .iter()
+                .enumerate()
+                .map(|(i, item)| (ItemRef::Sheet(i), item.uuid)),
+        );
+        for (item, uuid) in new {
+            if self.written.contains(&uuid) {
+                continue;
+            }
+            let node = self.item(item);
+            let index = insert_index(&out, node.head().unwrap(), &TRAILERS);
+            out.insert(index, node);
+        }
+        *root.items_mut() = out;
+    }
+
+    // templates are written as they were read, added ones sorted in
+    fn lib(&self, root: &mut Node) {
+        let lib = match root.child_mut("lib_symbols") {
+            Some(lib) => lib,
+            None => return,
+        };
+        let id = |item: &Node| item.items().get(1).and_then(|id| id.text());
+        let items = lib.items_mut();
+        items.retain(|item| match (item.head(), id(item)) {
+            (Some("symbol"), Some(id)) => self.schem.lib.contains_key(&id),
+            _ => true,
+        });
+        let mut ids = self.schem.lib.keys().collect::<Vec<_>>();
+        ids.sort();
+        for key in ids {
+            if items.iter().any(|item| id(item).as_ref() == Some(key)) {
+                continue;
+            }
+            let index = items
+                .iter()
+                .position(|item| item.head() == Some("symbol") && id(item).as_ref() > Some(key))
+                .unwrap_or(items.len());
+            items.insert(
+                index,
+                self.d.node(&template(&self.schem.lib[key], &self.d), 2),
+            );
+        }
+    }
+
+    // kicad 6 keeps references in the root sheet's symbol_instances
+    fn symbol_instances(&self, root: &mut Node) {
+        let instances = match root.child_mut("symbol_instances") {
+            Some(instances) => instances,
+            None => return,
+        };
+        let uuid = |item: &Node| {
+            let path = item.items().get(1)?.text()?;
+            let uuid = Uuid::from_text(path.rsplit('/').next()?);
+            Some((path.matches('/').count(), uuid))
+        };
+        let items = instances.items_mut();
+        items.retain(|item| match uuid(item) {
+            Some((_, uuid)) => !self.removed.contains(&uuid),
+            None => true,
+        });
+        let mut seen = HashSet::<Uuid>::new();
+        for item in items.iter_mut() {
+            let symb = match uuid(item) {
+                Some((1, uuid)) => match self.schem.find_by_uuid(&uuid) {
+                    Some(ItemRef::Symbol(i)) => &self.schem.symbs[i],
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            seen.insert(symb.uuid);
+            set_value(item, "reference", symb.prop("Reference").unwrap_or(""));
+            set_number(item, "unit", symb.unit as f64);
+            set_value(item, "value", symb.prop("Value").unwrap_or(""));
+            set_value(item, "footprint", symb.prop("Footprint").unwrap_or(""));
+        }
+        for symb in &self.schem.symbs {
+            if seen.contains(&symb.uuid) {
+                continue;
+            }
+            let path = format!(
+                "(path {}\n  (reference {}) (unit {}) (value {}) (footprint {})\n)",
+                quote(&format!("/{}", symb.uuid)),
+                quote(symb.prop("Reference").unwrap_or("")),
+                symb.unit,
+                quote(symb.prop("Value").unwrap_or("")),
+                quote(symb.prop("Footprint").unwrap_or(""))
+            );
+            items.push(self.d.node(&path, 2));
+        }
+    }
+}
+
+fn page_name(page: &Page) -> &'static str {
+    match page {
+        Page::A5 => "A5",
+        Page::A4 => "A4",
+        Page::A3 => "A3",
+        Page::A2 => "A2",
+        Page::A1 => "A1",
+        Page::A0 => "A0",
+        Page::A => "A",
+        Page::B => "B",
+        Page::C => "C",
+        Page::D => "D",
+        Page::E => "E",
+        Page::USLetter => "USLetter",
+        Page::USLegal => "USLegal",
+        Page::USLedger => "USLedger",
+        Page::User(_, _) => "User",
+    }
+}
+
+// after the last `head`, else before the first of `before`, else at the end
+fn insert_index(items: &[Node], head: &str, before: &[&str]) -> usize {
+    if let Some(index) = items.iter().rposition(|item| item.head() == Some(head)) {
+        return index + 1;
+    }
+    items
+        .iter()
+        .position(|item| item.head().is_some_and(|other| before.contains(&other)))
+        .unwrap_or(items.len())
+}
+
+fn set_point(node: &mut Node, head: &str, pos: &Point, angle: bool) {
+    let at = match node.child_mut(head) {
+        Some(at) => at.items_mut(),
+        None => return,
+    };
+    at[1].set_f64(pos.x);
+    at[2].set_f64(pos.y);
+    if angle {
+        match at.get_mut(3) {
+            Some(a) => a.set_f64(pos.a),
+            None => at.push(Node::number(pos.a)),
+        }
+    }
+}
+
+fn set_value(node: &mut Node, head: &str, value: &str) {
+    if let Some(item) = node
+        .child_mut(head)
+        .and_then(|item| item.items_mut().get_mut(1))
+    {
+        item.set_text(value);
+    }
+}
+
+fn set_number(node: &mut Node, head: &str, value: f64) {
+    if let Some(item) = node
+        .child_mut(head)
+        .and_then(|item| item.items_mut().get_mut(1))
+    {
+        item.set_f64(value);
+    }
+}
+
+impl Schematic {
+    // a new, empty file for schematics that weren't parsed
+    fn skeleton(&self, d: &Dialect) -> Document {
+        let generator = match d.version >= KICAD_8 {
+            true => quote("kiwi"),
+            false => "kiwi".to_string(),
+        };
+        let text = match d.version >= KICAD_7 {
+            true => format!(
+                "(kicad_sch (version {}) (generator {})\n\n  (uuid {})\n\n  (paper \"A4\")\n\n  (lib_symbols\n  )\n\n  (sheet_instances\n    (path \"/\" (page \"1\"))\n  )\n)",
+                d.version,
+                generator,
+                d.uuid(&self.uuid)
+            ),
+            false => format!(
+                "(kicad_sch (version {}) (generator {})\n\n  (uuid {})\n\n  (paper \"A4\")\n\n  (lib_symbols\n  )\n\n  (sheet_instances\n    (path \"/\" (page \"1\"))\n  )\n\n  (symbol_instances\n  )\n)",
+                d.version,
+                generator,
+                d.uuid(&self.uuid)
+            ),
+        };
+        let mut root = Node::parse(&text).unwrap();
+        if d.version >= KICAD_8 {
+            root = sexpr::prettify(&root, 0);
+            root.set_space("");
+        }
+        Document {
+            root,
+            tail: "\n".to_string(),
+        }
+    }
+
+    // the schematic as a .kicad_sch file, in the dialect of its version
+    // (kicad 8 for new schematics). a parsed file is updated rather than
+    // rewritten: nodes the model doesn't know are kept verbatim and an
+    // unchanged schematic writes back byte for byte
+    pub fn to_kicad_sch(&self) -> String {
+        let d = Dialect {
+            version: match self.version > 0 {
+                true => self.version,
+                false => KICAD_8,
+            },
+        };
+        let mut doc = match &self.source {
+            Some(doc) => doc.clone(),
+            None => self.skeleton(&d),
+        };
+        let root = &mut doc.root;
+
+        // new symbols copy the instance path of the file's other symbols
+        let instance = match root.child("symbol_instances").is_none() && d.version >= KICAD_7 {
+            true => {
+                let path = root
+                    .items()
+                    .iter()
+                    .filter(|item| item.head() == Some("symbol"))
+                    .filter_map(|item| item.child("instances")?.child("project"))
+                    .find_map(|project| {
+                        Some((project.items().get(1)?.text()?, project.value("path")?))
+                    });
+                Some(match path {
+                    Some((project, path)) => Instance { project, path },
+                    None => Instance {
+                        project: String::new(),
+                        path: format!("/{}", self.uuid),
+                    },
+                })
+            }
+            false => None,
+        };
+
+        let mut writer = Writer {
+            schem: self,
+            d,
+            instance,
+            written: HashSet::new(),
+            removed: HashSet::new(),
+        };
+        writer.header(root);
+        writer.items(root);
+        writer.lib(root);
+        writer.symbol_instances(root);
+        doc.write()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schematic::*;
+
+    // one file per dialect, with the reference of a symbol in it. kicad 8.0
+    // writes `(hide yes)` in effects but still a bare `hide` in pin_names and
+    // pin_numbers, kicad 9 changed those too
+    const FIXTURES: [(&str, &str); 3] = [
+        (include_str!("../test/example.kicad_sch"), "C20"),
+        (include_str!("../test/kicad7.kicad_sch"), "R1"),
+        (include_str!("../test/kicad8.kicad_sch"), "R5"),
+    ];
+
+    #[test]
+    fn unchanged_files_round_trip() {
+        for (text, _) in FIXTURES {
+            let schem = Schematic::from_str(text);
+            assert!(schem.warnings.is_empty());
+            assert_eq!(schem.to_kicad_sch(), text, "version {}", schem.version);
+        }
+    }
+
+    #[test]
+    fn kicad8_flags() {
+        let schem = Schematic::from_str(FIXTURES[2].0);
+        let hidden = |props: &[Property], key: &str| {
+            props
+                .iter()
+                .find(|prop| prop.key == key)
+                .unwrap()
+                .effect
+                .hide
+        };
+        let lib = &schem.lib["Device:R"];
+        assert!(!hidden(&lib.props, "Reference"));
+        assert!(hidden(&lib.props, "ki_fp_filters"));
+        let symb = &schem.symbs[0];
+        assert!(!hidden(&symb.props, "Value"));
+        assert!(hidden(&symb.props, "Footprint"));
+        assert!(schem.to_kicad_sch().contains("\t\t\t(pin_numbers hide)\n"));
+    }
+
+    #[test]
+    fn edits_touch_only_their_node() {
+        for (text, reference) in FIXTURES {
+            let mut schem = Schematic::from_str(text);
+            schem
+                .set_property(reference, "Datasheet", "https://example.com/part.pdf")
+                .unwrap();
+            let out = schem.to_kicad_sch();
+            let (before, after) = (text.lines(), out.lines());
+            assert_eq!(before.clone().count(), after.clone().count());
+            let changed = before
+                .zip(after)
+                .filter(|(a, b)| a != b)
+                .collect::<Vec<_>>();
+            assert_eq!(changed.len(), 1, "version {}", schem.version);
+            let (old, new) = changed[0];
+            assert!(old.contains("(property \"Datasheet\" \"~\""));
+            assert_eq!(
+                new,
+                old.replace("\"~\"", "\"https://example.com/part.pdf\"")
+            );
+        }
+    }
+
+    const ODD: &str = "(kicad_sch (version 20211123) (generator eeschema)
+
+  (uuid E1D888F9-47BA-4B65-8B76-AE4073EB1C03)
+
+  (paper \"A4\")
+
+  (lib_symbols
+  )
+
+  (wire (pts (xy 0 0) (xy 10 0))
+    (stroke (width 0) (type default) (color 0 0 0 0))
+    (uuid 045624B0-DBB4-43F4-9968-9F470CED6876)
+  )
+
+  (label \"A\" (at 10 0 0)
+    (effects (font (size 1.27 1.27)) (justify left bottom))
+    (uuid not-a-uuid)
+  )
+
+  (sheet_instances
+    (path \"/\" (page \"1\"))
+  )
+)
+";
+
+    #[test]
+    fn odd_uuids_keep_their_spelling() {
+        let mut schem = Schematic::from_str(ODD);
+        assert_eq!(schem.warnings, ["invalid uuid: \"not-a-uuid\""]);
+        assert_eq!(schem.to_kicad_sch(), ODD);
+
+        // updated in place and rebuilt, the ids are still the file's
+        schem.wires[0].poss[1] = Point::new(20.0, 0.0);
+        schem.labels[0].shape = Style::Global;
+        let out = schem.to_kicad_sch();
+        assert!(out.contains("(uuid E1D888F9-47BA-4B65-8B76-AE4073EB1C03)"));
+        assert!(out.contains("(uuid 045624B0-DBB4-43F4-9968-9F470CED6876)"));
+        assert!(out.contains("(xy 20 0)"));
+        assert!(out.contains("(global_label \"A\""));
+        assert_eq!(out.matches("(uuid not-a-uuid)").count(), 1);
+        assert!(!out.contains("(label "));
+
+        let again = Schematic::from_str(&out);
+        assert_eq!(again.labels[0].uuid, schem.labels[0].uuid);
+        assert_eq!(again.to_kicad_sch(), out);
+    }
+}